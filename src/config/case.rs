@@ -0,0 +1,125 @@
+use crate::config::ConfigFileRaw;
+use std::borrow::Cow;
+use std::collections::HashMap;
+
+const CASE_INSENSITIVE: &str = "(?i)";
+
+/// Makes the pattern case insensitive, unless it starts with its own flags,
+/// e.g. `(?-i)` to keep a rule case sensitive.
+pub fn with_case_flag(pattern: &str, case_insensitive: bool) -> Cow<'_, str> {
+    match case_insensitive && !has_leading_flags(pattern) {
+        true => Cow::Owned(format!("{CASE_INSENSITIVE}{pattern}")),
+        false => Cow::Borrowed(pattern),
+    }
+}
+
+/// Tells if the pattern starts with flags, like `(?i)` or `(?-i:...)`, but
+/// not with a group like `(?:...)` or `(?P<name>...)`.
+fn has_leading_flags(pattern: &str) -> bool {
+    pattern
+        .strip_prefix("(?")
+        .and_then(|rest| rest.find([')', ':']).map(|end| &rest[..end]))
+        .is_some_and(|flags| !flags.is_empty() && flags.chars().all(|c| "imsUuxR-".contains(c)))
+}
+
+/// Removes the `(?i)` prefixes made redundant by `matching.case_insensitive`
+/// from the rules, and returns how many were removed. A rule whose pattern
+/// exists without the prefix too is dropped, both were the same rule.
+pub fn strip_case_prefixes(config: &mut ConfigFileRaw) -> usize {
+    let mut stripped = 0;
+    for table in [
+        &mut config.class,
+        &mut config.class_active,
+        &mut config.initial_class,
+        &mut config.initial_class_active,
+        &mut config.class_floating,
+        &mut config.class_floating_active,
+        &mut config.class_xwayland,
+        &mut config.exclude,
+        &mut config.fallback,
+        &mut config.modified_markers,
+        &mut config.client_format_per_class,
+        &mut config.class_colors,
+    ] {
+        stripped += strip_keys(table);
+    }
+    for table in [
+        &mut config.title_in_class,
+        &mut config.title_in_class_active,
+        &mut config.title_in_initial_class,
+        &mut config.title_in_initial_class_active,
+        &mut config.initial_title_in_class,
+        &mut config.initial_title_in_class_active,
+        &mut config.initial_title_in_initial_class,
+        &mut config.initial_title_in_initial_class_active,
+        &mut config.title_history_in_class,
+        &mut config.title_history_in_class_active,
+    ] {
+        stripped += strip_keys(table);
+        for titles in table.values_mut() {
+            stripped += strip_keys(titles);
+        }
+    }
+    stripped += strip_keys(&mut config.min_count_per_class);
+    for list in [&mut config.singleton, &mut config.no_dedup] {
+        for pattern in list.iter_mut() {
+            if let Some(stripped_pattern) = pattern.strip_prefix(CASE_INSENSITIVE) {
+                *pattern = stripped_pattern.to_string();
+                stripped += 1;
+            }
+        }
+    }
+    stripped
+}
+
+fn strip_keys<V>(table: &mut HashMap<String, V>) -> usize {
+    let keys: Vec<String> = table
+        .keys()
+        .filter(|key| key.starts_with(CASE_INSENSITIVE))
+        .cloned()
+        .collect();
+    for key in &keys {
+        if let Some(value) = table.remove(key) {
+            table
+                .entry(key[CASE_INSENSITIVE.len()..].to_string())
+                .or_insert(value);
+        }
+    }
+    keys.len()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_with_case_flag() {
+        assert_eq!(with_case_flag("kitty", false), "kitty");
+        assert_eq!(with_case_flag("kitty", true), "(?i)kitty");
+        assert_eq!(with_case_flag("(?i)kitty", true), "(?i)kitty");
+        assert_eq!(with_case_flag("(?-i)Kitty", true), "(?-i)Kitty");
+        assert_eq!(with_case_flag("(?-i:K)itty", true), "(?-i:K)itty");
+        assert_eq!(with_case_flag("(?:kitty)", true), "(?i)(?:kitty)");
+        assert_eq!(with_case_flag("(?P<app>kitty)", true), "(?i)(?P<app>kitty)");
+    }
+
+    #[test]
+    fn test_strip_case_prefixes() {
+        let mut config: ConfigFileRaw = toml::from_str(
+            r#"
+            singleton = ["(?i)firefox"]
+            [class]
+            "(?i)kitty" = "term"
+            "(?-i)Code" = "code"
+            [title_in_class."(?i)firefox"]
+            "(?i)github" = "gh"
+            "#,
+        )
+        .unwrap();
+        assert_eq!(strip_case_prefixes(&mut config), 4);
+        assert_eq!(config.singleton, ["firefox"]);
+        assert_eq!(config.class["kitty"], "term");
+        assert_eq!(config.class["(?-i)Code"], "code");
+        assert_eq!(config.title_in_class["firefox"]["github"], "gh");
+    }
+}
@@ -0,0 +1,148 @@
+use crate::config::ConfigFile;
+use crate::params::LegendFormat;
+use serde::Serialize;
+use std::error::Error;
+use std::fmt::Write;
+
+/// The icon of a class rule, or of a title rule of a class.
+#[derive(Debug, Serialize, PartialEq, Eq, PartialOrd, Ord)]
+pub struct LegendEntry {
+    table: &'static str,
+    class: String,
+    title: Option<String>,
+    icon: String,
+}
+
+/// Renders the legend of the compiled config, packs included.
+pub fn render_legend(config: &ConfigFile, format: LegendFormat) -> Result<String, Box<dyn Error>> {
+    let entries = legend(config);
+    match format {
+        LegendFormat::Md => Ok(markdown(&entries)),
+        LegendFormat::Json => Ok(serde_json::to_string_pretty(&entries)?),
+    }
+}
+
+/// Lists the icons of the class and title rules, by table, class and title.
+fn legend(config: &ConfigFile) -> Vec<LegendEntry> {
+    let class_tables = [
+        ("class", &config.class),
+        ("class_active", &config.class_active),
+        ("initial_class", &config.initial_class),
+        ("initial_class_active", &config.initial_class_active),
+        ("class_floating", &config.class_floating),
+        ("class_floating_active", &config.class_floating_active),
+        ("class_xwayland", &config.class_xwayland),
+    ];
+    let title_tables = [
+        ("title_in_class", &config.title_in_class),
+        ("title_in_class_active", &config.title_in_class_active),
+        ("title_in_initial_class", &config.title_in_initial_class),
+        (
+            "title_in_initial_class_active",
+            &config.title_in_initial_class_active,
+        ),
+        ("initial_title_in_class", &config.initial_title_in_class),
+        (
+            "initial_title_in_class_active",
+            &config.initial_title_in_class_active,
+        ),
+        (
+            "initial_title_in_initial_class",
+            &config.initial_title_in_initial_class,
+        ),
+        (
+            "initial_title_in_initial_class_active",
+            &config.initial_title_in_initial_class_active,
+        ),
+        ("title_history_in_class", &config.title_history_in_class),
+        (
+            "title_history_in_class_active",
+            &config.title_history_in_class_active,
+        ),
+    ];
+
+    let mut entries = Vec::new();
+    for (table, rules) in class_tables {
+        for (class, icon) in rules {
+            entries.push(LegendEntry {
+                table,
+                class: class.to_string(),
+                title: None,
+                icon: icon.to_string(),
+            });
+        }
+    }
+    for (table, classes) in title_tables {
+        for (class, rules) in classes {
+            for (title, icon) in rules {
+                entries.push(LegendEntry {
+                    table,
+                    class: class.to_string(),
+                    title: Some(title.to_string()),
+                    icon: icon.to_string(),
+                });
+            }
+        }
+    }
+    entries.sort();
+    entries
+}
+
+fn markdown(entries: &[LegendEntry]) -> String {
+    let mut table = "| table | class | title | icon |\n| --- | --- | --- | --- |\n".to_string();
+    for entry in entries {
+        _ = writeln!(
+            table,
+            "| {} | {} | {} | {} |",
+            entry.table,
+            code(&entry.class),
+            entry.title.as_deref().map(code).unwrap_or_default(),
+            escape_pipes(&entry.icon)
+        );
+    }
+    table
+}
+
+fn code(pattern: &str) -> String {
+    format!("`{}`", escape_pipes(pattern))
+}
+
+/// Pipes would end the cell, even in code spans.
+fn escape_pipes(text: &str) -> String {
+    text.replace('|', "\\|")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::read_config_str;
+
+    #[test]
+    fn test_render_legend() {
+        let config = read_config_str(
+            "[class]\n\"kitty|alacritty\" = \"term\"\nfirefox = \"web\"\n\
+             [title_in_class.firefox]\nGitHub = \"gh\"",
+            None,
+            false,
+            false,
+        )
+        .unwrap();
+
+        assert_eq!(
+            render_legend(&config, LegendFormat::Md).unwrap(),
+            "| table | class | title | icon |\n| --- | --- | --- | --- |\n\
+             | class | `firefox` |  | web |\n\
+             | class | `kitty\\|alacritty` |  | term |\n\
+             | title_in_class | `firefox` | `GitHub` | gh |\n"
+        );
+
+        let json: serde_json::Value =
+            serde_json::from_str(&render_legend(&config, LegendFormat::Json).unwrap()).unwrap();
+        assert_eq!(
+            json[2],
+            serde_json::json!({
+                "table": "title_in_class", "class": "firefox", "title": "GitHub", "icon": "gh"
+            })
+        );
+    }
+}
@@ -0,0 +1,207 @@
+use crate::config::ConfigFileRaw;
+use std::collections::HashMap;
+
+/// Reports the icons which would render badly in waybar: unbalanced Pango
+/// tags, stray braces, unescaped `<` or `&`, and control characters. Every
+/// issue is prefixed with the path of its rule, e.g. `class."(?i)kitty"`.
+pub fn lint_icons(config: &ConfigFileRaw) -> Vec<String> {
+    let icons = [
+        ("class", &config.class),
+        ("class_active", &config.class_active),
+        ("initial_class", &config.initial_class),
+        ("initial_class_active", &config.initial_class_active),
+        ("class_floating", &config.class_floating),
+        ("class_floating_active", &config.class_floating_active),
+        ("class_xwayland", &config.class_xwayland),
+        ("workspaces_name", &config.workspaces_name),
+        ("fallback", &config.fallback),
+        ("modified_markers", &config.modified_markers),
+    ];
+    let title_icons = [
+        ("title_in_class", &config.title_in_class),
+        ("title_in_class_active", &config.title_in_class_active),
+        ("title_in_initial_class", &config.title_in_initial_class),
+        (
+            "title_in_initial_class_active",
+            &config.title_in_initial_class_active,
+        ),
+        ("initial_title_in_class", &config.initial_title_in_class),
+        (
+            "initial_title_in_class_active",
+            &config.initial_title_in_class_active,
+        ),
+        (
+            "initial_title_in_initial_class",
+            &config.initial_title_in_initial_class,
+        ),
+        (
+            "initial_title_in_initial_class_active",
+            &config.initial_title_in_initial_class_active,
+        ),
+        ("title_history_in_class", &config.title_history_in_class),
+        (
+            "title_history_in_class_active",
+            &config.title_history_in_class_active,
+        ),
+    ];
+
+    let mut issues = Vec::new();
+    let mut lint_table = |path: String, rules: &HashMap<String, String>| {
+        for (pattern, icon) in rules {
+            for issue in lint_icon(icon) {
+                issues.push(format!("{path}.{pattern:?}: {issue}"));
+            }
+        }
+    };
+    for (table, rules) in icons {
+        lint_table(table.to_string(), rules);
+    }
+    for (table, classes) in title_icons {
+        for (class, rules) in classes {
+            lint_table(format!("{table}.{class:?}"), rules);
+        }
+    }
+    issues.sort();
+    issues
+}
+
+/// Returns the issues of an icon value.
+fn lint_icon(icon: &str) -> Vec<String> {
+    let mut issues = Vec::new();
+    let mut open_tags: Vec<&str> = Vec::new();
+    let mut open_brace = false;
+    let mut chars = icon.char_indices().peekable();
+
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '{' if chars.peek().map(|(_, c)| *c) == Some('{') => {
+                chars.next();
+            }
+            '}' if !open_brace && chars.peek().map(|(_, c)| *c) == Some('}') => {
+                chars.next();
+            }
+            '{' if open_brace => issues.push("`{` inside a placeholder".to_string()),
+            '{' => open_brace = true,
+            '}' if open_brace => open_brace = false,
+            '}' => issues.push("stray `}`, write `}}` for a literal one".to_string()),
+            '<' => match pango_tag(&icon[i..]) {
+                Some((name, len)) => {
+                    if let Some(name) = name.strip_prefix('/') {
+                        match open_tags.pop() {
+                            Some(open) if open == name => {}
+                            Some(open) => {
+                                issues.push(format!("Pango tag <{open}> closed by </{name}>"))
+                            }
+                            None => issues.push(format!("Pango tag </{name}> never opened")),
+                        }
+                    } else if !icon[i..i + len].ends_with("/>") {
+                        open_tags.push(name);
+                    }
+                    // Skip the rest of the tag, its attributes included
+                    while chars.peek().is_some_and(|(j, _)| *j < i + len) {
+                        chars.next();
+                    }
+                }
+                None => issues.push("`<` breaks Pango markup, write `&lt;`".to_string()),
+            },
+            '&' if !is_entity(&icon[i..]) => {
+                issues.push("`&` breaks Pango markup, write `&amp;`".to_string())
+            }
+            c if c.is_control() => issues.push(format!("control character {c:?} breaks waybar")),
+            _ => {}
+        }
+    }
+
+    if open_brace {
+        issues.push("unclosed `{`, write `{{` for a literal one".to_string());
+    }
+    for name in open_tags {
+        issues.push(format!("Pango tag <{name}> never closed"));
+    }
+    issues
+}
+
+/// Returns the name of the tag starting the text, `/b` for `</b>`, and the
+/// length of the tag.
+fn pango_tag(text: &str) -> Option<(&str, usize)> {
+    let end = text.find('>')?;
+    let inner = &text[1..end];
+    let name = inner.strip_prefix('/').unwrap_or(inner);
+    if !name.starts_with(|c: char| c.is_ascii_alphabetic()) {
+        return None;
+    }
+    let name_len = name
+        .find(|c: char| !c.is_ascii_alphanumeric())
+        .unwrap_or(name.len());
+    Some((&inner[..inner.len() - name.len() + name_len], end + 1))
+}
+
+/// Tells if the text starts with an entity like `&amp;` or `&#x1F600;`.
+fn is_entity(text: &str) -> bool {
+    let Some(end) = text.find(';') else {
+        return false;
+    };
+    let entity = &text[1..end];
+    match entity.strip_prefix('#') {
+        Some(code) => match code.strip_prefix('x') {
+            Some(hex) => !hex.is_empty() && hex.chars().all(|c| c.is_ascii_hexdigit()),
+            None => !code.is_empty() && code.chars().all(|c| c.is_ascii_digit()),
+        },
+        None => !entity.is_empty() && entity.chars().all(|c| c.is_ascii_alphanumeric()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lint_icon() {
+        assert!(
+            lint_icon("<span color='red'>{icon}</span> {{x}} &amp; &#x1F600; <br/>").is_empty()
+        );
+
+        assert_eq!(lint_icon("<b>term"), ["Pango tag <b> never closed"]);
+        assert_eq!(lint_icon("term</b>"), ["Pango tag </b> never opened"]);
+        assert_eq!(lint_icon("<b>term</i>"), ["Pango tag <b> closed by </i>"]);
+        assert_eq!(
+            lint_icon("a < b"),
+            ["`<` breaks Pango markup, write `&lt;`"]
+        );
+        assert_eq!(lint_icon("R&D"), ["`&` breaks Pango markup, write `&amp;`"]);
+        assert_eq!(
+            lint_icon("{title"),
+            ["unclosed `{`, write `{{` for a literal one"]
+        );
+        assert_eq!(
+            lint_icon("title}"),
+            ["stray `}`, write `}}` for a literal one"]
+        );
+        assert_eq!(
+            lint_icon("term\n"),
+            ["control character '\\n' breaks waybar"]
+        );
+    }
+
+    #[test]
+    fn test_lint_icons() {
+        let config: ConfigFileRaw = toml::from_str(
+            r#"
+            [class]
+            "(?i)kitty" = "<b>term"
+            firefox = "web"
+
+            [title_in_class.kitty]
+            vim = "R&D"
+            "#,
+        )
+        .unwrap();
+        assert_eq!(
+            lint_icons(&config),
+            [
+                "class.\"(?i)kitty\": Pango tag <b> never closed",
+                "title_in_class.\"kitty\".\"vim\": `&` breaks Pango markup, write `&amp;`",
+            ]
+        );
+    }
+}
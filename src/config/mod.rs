@@ -1,17 +1,66 @@
+mod case;
+mod legend;
+mod lint;
+mod packs;
+pub mod schema;
+mod sources;
+mod write;
+
+pub use case::with_case_flag;
+pub use legend::render_legend;
+pub use packs::{local_path, PackLayers};
+use sources::{locate_rules, RuleSource};
+#[cfg(feature = "tui")]
+pub use write::add_class_rule;
+
+use crate::params::{Args, BackendKind};
 use regex::Regex;
 use semver::Version;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::error::Error;
 use std::fs;
-use std::fs::File;
-use std::io::Write;
+use std::io;
 use std::path::PathBuf;
 use std::process;
 
 const VERSION: &str = env!("CARGO_PKG_VERSION");
 const BIN_NAME: &str = env!("CARGO_BIN_NAME");
 
+/// Utility windows which are never named, whatever their title: desktop
+/// portals, polkit agents and input method panels. Disabled with
+/// `matching.exclude_builtin = false`.
+const BUILTIN_EXCLUDE: [&str; 4] = [
+    "^xdg-desktop-portal",
+    "(?i)polkit",
+    "^ibus-",
+    "(?i)^(org\\.)?fcitx",
+];
+
+/// Keys of `[behavior]`, also accepted at the top level where some of them
+/// were before the section existed.
+const BEHAVIOR_KEYS: [&str; 19] = [
+    "audit_log",
+    "audit_log_max_size",
+    "cache",
+    "startup_throttle",
+    "low_power_on_battery",
+    "notify_on_error",
+    "urgent_timeout",
+    "rewrite_titles",
+    "debounce",
+    "resync",
+    "rename_empty",
+    "only_visible",
+    "backend",
+    "rename_cmd",
+    "include_unmanaged",
+    "mirror_env",
+    "mirror_file",
+    "monitors",
+    "on_conflict",
+];
+
 #[derive(Default, Clone, Debug)]
 pub struct Config {
     pub config: ConfigFile,
@@ -30,10 +79,54 @@ fn default_client_active_formatter() -> String {
     "*{icon}*".to_string()
 }
 
+fn default_client_modified_formatter() -> String {
+    "{client}".to_string()
+}
+
+fn default_client_unmanaged_formatter() -> String {
+    "{client}".to_string()
+}
+
+fn default_client_floating_formatter() -> String {
+    "{client}".to_string()
+}
+
+fn default_placeholder_depth() -> usize {
+    4
+}
+
+fn default_persistent_marker() -> String {
+    "P".to_string()
+}
+
+fn default_title_history_size() -> usize {
+    10
+}
+
+fn default_letter_fallback_format() -> String {
+    "{letter_circled}".to_string()
+}
+
+fn default_active_marker() -> String {
+    "*".to_string()
+}
+
+fn default_urgent_marker() -> String {
+    "!".to_string()
+}
+
+fn default_xwayland_marker() -> String {
+    "X".to_string()
+}
+
 fn default_client_fullscreen_formatter() -> String {
     "[{icon}]".to_string()
 }
 
+fn default_client_active_fullscreen_formatter() -> String {
+    "{client_fullscreen}".to_string()
+}
+
 fn default_client_dup_formatter() -> String {
     "{icon}{counter_sup}".to_string()
 }
@@ -42,10 +135,34 @@ fn default_client_dup_fullscreen_formatter() -> String {
     "[{icon}]{delim}{icon}{counter_unfocused_sup}".to_string()
 }
 
+fn default_client_maximized_formatter() -> String {
+    "{client_fullscreen}".to_string()
+}
+
+fn default_client_dup_maximized_formatter() -> String {
+    "{client_dup_fullscreen}".to_string()
+}
+
 fn default_client_dup_active_formatter() -> String {
     "*{icon}*{delim}{icon}{counter_unfocused_sup}".to_string()
 }
 
+fn default_client_dup_active_fullscreen_formatter() -> String {
+    "{client_dup_fullscreen}".to_string()
+}
+
+fn default_dedup_by() -> Vec<DedupField> {
+    vec![DedupField::Icon, DedupField::Active, DedupField::Fullscreen]
+}
+
+fn default_dominant_rest() -> String {
+    "{delim}+{rest}".to_string()
+}
+
+fn default_pad_char() -> String {
+    " ".to_string()
+}
+
 fn default_workspace_empty_formatter() -> String {
     "{id}".to_string()
 }
@@ -58,6 +175,37 @@ fn default_class() -> HashMap<String, String> {
     HashMap::from([("DEFAULT".to_string(), " {class}".to_string())])
 }
 
+fn default_title_progress_patterns() -> Vec<String> {
+    vec![r"(\d+)%".to_string(), r"(\d+) of (\d+)".to_string()]
+}
+
+fn default_title_progress_bar_width() -> usize {
+    5
+}
+
+fn default_media_classes() -> Vec<String> {
+    vec!["(?i)spotify".to_string(), "(?i)mpv".to_string()]
+}
+
+fn default_media_patterns() -> Vec<String> {
+    vec![
+        "^(?P<track>.+) - mpv$".to_string(),
+        "^(?P<artist>.+?) [-–—] (?P<track>.+)$".to_string(),
+    ]
+}
+
+fn default_true() -> bool {
+    true
+}
+
+fn default_audit_log_max_size() -> u64 {
+    1024 * 1024
+}
+
+fn default_urgent_timeout() -> u64 {
+    300
+}
+
 // Nested serde default doesnt work.
 impl Default for ConfigFormatRaw {
     fn default() -> Self {
@@ -70,33 +218,284 @@ pub struct ConfigFormatRaw {
     #[serde(default)]
     pub max_clients: Option<i32>,
     #[serde(default)]
+    pub max_clients_per_monitor: HashMap<String, i32>,
+    #[serde(default)]
     pub dedup: bool,
     #[serde(default)]
     pub dedup_inactive_fullscreen: bool,
     #[serde(default = "default_delim_formatter")]
     pub delim: String,
+    #[serde(default)]
+    pub collapse_delim: bool,
+    #[serde(default = "default_dedup_by")]
+    pub dedup_by: Vec<DedupField>,
+    #[serde(default)]
+    pub dedup_by_icon: bool,
+    #[serde(default)]
+    pub client_sort: ClientSort,
+    #[serde(default)]
+    pub active_fallback: ActiveFallback,
+    #[serde(default)]
+    pub dominant: bool,
+    #[serde(default)]
+    pub dominant_by: DominantBy,
+    #[serde(default = "default_dominant_rest")]
+    pub dominant_rest: String,
+    #[serde(default)]
+    pub pad_clients_to: Option<usize>,
+    #[serde(default = "default_pad_char")]
+    pub pad_char: String,
+    #[serde(default)]
+    pub total_max_width: Option<usize>,
+    #[serde(default)]
+    pub ascii_fallback: bool,
+    #[serde(default)]
+    pub letter_fallback: bool,
+    #[serde(default = "default_letter_fallback_format")]
+    pub letter_fallback_format: String,
+    #[serde(default = "default_urgent_marker")]
+    pub urgent_marker: String,
+    #[serde(default = "default_active_marker")]
+    pub active_marker: String,
+    #[serde(default = "default_xwayland_marker")]
+    pub xwayland_marker: String,
+    #[serde(default = "default_active_marker")]
+    pub monitor_active_marker: String,
+    #[serde(default)]
+    pub counter_glyphs: HashMap<String, String>,
+    #[serde(default = "default_placeholder_depth")]
+    pub placeholder_depth: usize,
+    #[serde(default)]
+    pub special_prefix: Option<String>,
+    #[serde(default)]
+    pub workspace_special: Option<String>,
+    #[serde(default)]
+    pub workspace_special_empty: Option<String>,
+    #[serde(default = "default_persistent_marker")]
+    pub persistent_marker: String,
+    #[serde(default)]
+    pub workspace_persistent_empty: Option<String>,
     #[serde(default = "default_workspace_formatter")]
     pub workspace: String,
     #[serde(default = "default_workspace_empty_formatter")]
     pub workspace_empty: String,
+    #[serde(default)]
+    pub workspace_empty_active: Option<String>,
+    #[serde(default)]
+    pub workspace_transition: Option<String>,
     #[serde(default = "default_client_formatter")]
     pub client: String,
     #[serde(default = "default_client_fullscreen_formatter")]
     pub client_fullscreen: String,
     #[serde(default = "default_client_active_formatter")]
     pub client_active: String,
+    #[serde(default = "default_client_modified_formatter")]
+    pub client_modified: String,
+    #[serde(default = "default_client_unmanaged_formatter")]
+    pub client_unmanaged: String,
+    #[serde(default = "default_client_floating_formatter")]
+    pub client_floating: String,
+    #[serde(default)]
+    pub client_pinned: Option<String>,
+    #[serde(default = "default_client_active_fullscreen_formatter")]
+    pub client_active_fullscreen: String,
     #[serde(default = "default_client_dup_formatter")]
     pub client_dup: String,
     #[serde(default = "default_client_dup_active_formatter")]
     pub client_dup_active: String,
     #[serde(default = "default_client_dup_fullscreen_formatter")]
     pub client_dup_fullscreen: String,
+    #[serde(default = "default_client_maximized_formatter")]
+    pub client_maximized: String,
+    #[serde(default = "default_client_dup_maximized_formatter")]
+    pub client_dup_maximized: String,
+    #[serde(default = "default_client_dup_active_fullscreen_formatter")]
+    pub client_dup_active_fullscreen: String,
+}
+
+/// Order of the clients in the workspace name.
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ClientSort {
+    /// As listed by Hyprland
+    #[default]
+    Default,
+    /// Left to right, then top to bottom, as on screen
+    Position,
+    /// Alphabetically by the rendered client, ignoring case, accents and icons
+    Alpha,
+}
+
+/// Icon of the focused client when only an inactive rule matches it.
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ActiveFallback {
+    /// The inactive icon in the `client_active` format
+    #[default]
+    Wrap,
+    /// The inactive icon, as if the client was not focused
+    UseInactive,
+    /// The DEFAULT icon of `[class_active]`, else the wrapped inactive icon
+    Default,
+}
+
+/// Client a workspace is named after, with `dominant`.
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum DominantBy {
+    /// The largest window
+    #[default]
+    Area,
+    /// The focused window, else the largest one
+    Focused,
 }
 
-#[derive(Deserialize, Serialize)]
+/// What to do with a workspace named by another tool, with `on_conflict`.
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ConflictPolicy {
+    /// Rename it anyway, names are not signed
+    #[default]
+    Overwrite,
+    /// Leave its name alone
+    Skip,
+    /// Rename it, and tell about it
+    Warn,
+}
+
+/// Client properties that must be equal for clients to be deduplicated.
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum DedupField {
+    Icon,
+    Class,
+    Title,
+    Fullscreen,
+    Active,
+}
+
+impl Default for ConfigBehaviorRaw {
+    fn default() -> Self {
+        toml::from_str("").unwrap()
+    }
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct ConfigBehaviorRaw {
+    #[serde(default)]
+    pub audit_log: Option<PathBuf>,
+    #[serde(default = "default_audit_log_max_size")]
+    pub audit_log_max_size: u64,
+    #[serde(default = "default_true")]
+    pub cache: bool,
+    #[serde(default)]
+    pub startup_throttle: bool,
+    #[serde(default)]
+    pub low_power_on_battery: bool,
+    #[serde(default)]
+    pub notify_on_error: bool,
+    #[serde(default = "default_urgent_timeout")]
+    pub urgent_timeout: u64,
+    #[serde(default)]
+    pub rewrite_titles: bool,
+    #[serde(default)]
+    pub debounce: u64,
+    #[serde(default)]
+    pub resync: u64,
+    #[serde(default = "default_true")]
+    pub rename_empty: bool,
+    #[serde(default)]
+    pub only_visible: bool,
+    #[serde(default)]
+    pub backend: Option<BackendKind>,
+    #[serde(default)]
+    pub rename_cmd: Option<String>,
+    #[serde(default)]
+    pub include_unmanaged: bool,
+    #[serde(default)]
+    pub mirror_env: Option<String>,
+    #[serde(default)]
+    pub mirror_file: Option<PathBuf>,
+    #[serde(default)]
+    pub monitors: Vec<String>,
+    #[serde(default)]
+    pub on_conflict: ConflictPolicy,
+}
+
+impl Default for ConfigMatchingRaw {
+    fn default() -> Self {
+        toml::from_str("").unwrap()
+    }
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct ConfigMatchingRaw {
+    #[serde(default)]
+    pub strip_class_prefixes: Vec<String>,
+    #[serde(default)]
+    pub reverse_dns_short_name: bool,
+    #[serde(default)]
+    pub prefer_initial: bool,
+    #[serde(default)]
+    pub continue_rules: Vec<String>,
+    #[serde(default = "default_title_history_size")]
+    pub title_history_size: usize,
+    #[serde(default = "default_true")]
+    pub exclude_builtin: bool,
+    #[serde(default)]
+    pub case_insensitive: bool,
+}
+
+impl Default for ConfigTitleProgressRaw {
+    fn default() -> Self {
+        toml::from_str("").unwrap()
+    }
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct ConfigTitleProgressRaw {
+    #[serde(default)]
+    pub enabled: bool,
+    #[serde(default = "default_title_progress_patterns")]
+    pub patterns: Vec<String>,
+    #[serde(default = "default_title_progress_bar_width")]
+    pub bar_width: usize,
+}
+
+#[derive(Default, Debug, Clone)]
+pub struct TitleProgress {
+    pub patterns: Vec<Regex>,
+    pub bar_width: usize,
+}
+
+impl Default for ConfigMediaRaw {
+    fn default() -> Self {
+        toml::from_str("").unwrap()
+    }
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct ConfigMediaRaw {
+    #[serde(default)]
+    pub enabled: bool,
+    #[serde(default = "default_media_classes")]
+    pub classes: Vec<String>,
+    #[serde(default = "default_media_patterns")]
+    pub patterns: Vec<String>,
+}
+
+#[derive(Default, Debug, Clone)]
+pub struct Media {
+    pub classes: Vec<Regex>,
+    pub patterns: Vec<Regex>,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
 pub struct ConfigFileRaw {
     #[serde(default)]
     pub version: String,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub packs: Vec<String>,
     #[serde(default = "default_class", alias = "icons")]
     pub class: HashMap<String, String>,
     #[serde(default, alias = "active_icons", alias = "icons_active")]
@@ -106,6 +505,12 @@ pub struct ConfigFileRaw {
     #[serde(default)]
     pub initial_class_active: HashMap<String, String>,
     #[serde(default)]
+    pub class_floating: HashMap<String, String>,
+    #[serde(default)]
+    pub class_floating_active: HashMap<String, String>,
+    #[serde(default)]
+    pub class_xwayland: HashMap<String, String>,
+    #[serde(default)]
     pub workspaces_name: HashMap<String, String>,
     #[serde(default, alias = "title_icons")]
     pub title_in_class: HashMap<String, HashMap<String, String>>,
@@ -124,18 +529,53 @@ pub struct ConfigFileRaw {
     #[serde(default)]
     pub initial_title_in_initial_class_active: HashMap<String, HashMap<String, String>>,
     #[serde(default)]
+    pub title_history_in_class: HashMap<String, HashMap<String, String>>,
+    #[serde(default)]
+    pub title_history_in_class_active: HashMap<String, HashMap<String, String>>,
+    #[serde(default)]
     pub exclude: HashMap<String, String>,
     #[serde(default)]
+    pub fallback: HashMap<String, String>,
+    #[serde(default)]
+    pub modified_markers: HashMap<String, String>,
+    #[serde(default)]
+    pub client_format_per_class: HashMap<String, String>,
+    #[serde(default)]
+    pub class_colors: HashMap<String, String>,
+    #[serde(default)]
+    pub min_count_per_class: HashMap<String, usize>,
+    #[serde(default)]
+    pub singleton: Vec<String>,
+    #[serde(default)]
+    pub no_dedup: Vec<String>,
+    #[serde(default)]
+    pub autospawn: HashMap<String, String>,
+    #[serde(default)]
+    pub title_progress: ConfigTitleProgressRaw,
+    #[serde(default)]
+    pub media: ConfigMediaRaw,
+    #[serde(default)]
     pub format: ConfigFormatRaw,
+    #[serde(default)]
+    pub behavior: ConfigBehaviorRaw,
+    #[serde(default)]
+    pub matching: ConfigMatchingRaw,
+    /// Where the rules are defined, by path, e.g. `class."kitty"`
+    #[serde(skip)]
+    pub rule_sources: HashMap<String, RuleSource>,
 }
 
 #[derive(Default, Debug, Clone)]
 pub struct ConfigFile {
+    pub packs: Vec<String>,
     pub class: Vec<(Regex, String)>,
     pub class_active: Vec<(Regex, String)>,
     pub workspaces_name: Vec<(String, String)>,
     pub initial_class: Vec<(Regex, String)>,
     pub initial_class_active: Vec<(Regex, String)>,
+    pub class_floating: Vec<(Regex, String)>,
+    pub class_floating_active: Vec<(Regex, String)>,
+    pub class_xwayland: Vec<(Regex, String)>,
     pub title_in_class: Vec<(Regex, Vec<(Regex, String)>)>,
     pub title_in_class_active: Vec<(Regex, Vec<(Regex, String)>)>,
     pub title_in_initial_class: Vec<(Regex, Vec<(Regex, String)>)>,
@@ -144,8 +584,65 @@ pub struct ConfigFile {
     pub initial_title_in_class_active: Vec<(Regex, Vec<(Regex, String)>)>,
     pub initial_title_in_initial_class: Vec<(Regex, Vec<(Regex, String)>)>,
     pub initial_title_in_initial_class_active: Vec<(Regex, Vec<(Regex, String)>)>,
+    pub title_history_in_class: Vec<(Regex, Vec<(Regex, String)>)>,
+    pub title_history_in_class_active: Vec<(Regex, Vec<(Regex, String)>)>,
     pub exclude: Vec<(Regex, Regex)>,
+    pub fallback: Vec<(Regex, String)>,
+    pub modified_markers: Vec<(Regex, String)>,
+    pub client_format_per_class: Vec<(Regex, String)>,
+    pub class_colors: Vec<(Regex, String)>,
+    pub min_count_per_class: Vec<(Regex, usize)>,
+    pub singleton: Vec<Regex>,
+    pub no_dedup: Vec<Regex>,
+    pub autospawn: HashMap<i32, String>,
+    /// The invalid regexes, with their path, compiled again on reload
+    pub quarantine: Vec<String>,
+    /// The paths of the rules of each regex, with their file and line
+    pub rule_sources: HashMap<String, Vec<String>>,
+    pub title_progress: TitleProgress,
+    pub media: Media,
     pub format: ConfigFormatRaw,
+    pub behavior: ConfigBehaviorRaw,
+    pub matching: ConfigMatchingRaw,
+}
+
+impl ConfigFile {
+    /// Returns the number of icon rules, invalid regexes are not counted.
+    pub fn rules_count(&self) -> usize {
+        let classes = [
+            &self.class,
+            &self.class_active,
+            &self.initial_class,
+            &self.initial_class_active,
+            &self.class_floating,
+            &self.class_floating_active,
+            &self.class_xwayland,
+        ];
+        let titles = [
+            &self.title_in_class,
+            &self.title_in_class_active,
+            &self.title_in_initial_class,
+            &self.title_in_initial_class_active,
+            &self.initial_title_in_class,
+            &self.initial_title_in_class_active,
+            &self.initial_title_in_initial_class,
+            &self.initial_title_in_initial_class_active,
+            &self.title_history_in_class,
+            &self.title_history_in_class_active,
+        ];
+        classes.iter().map(|rules| rules.len()).sum::<usize>()
+            + titles
+                .iter()
+                .flat_map(|rules| rules.iter())
+                .map(|(_, title_rules)| title_rules.len())
+                .sum::<usize>()
+    }
+
+    /// Tells where the rules of this regex are defined, e.g.
+    /// `class."kitty" (config.toml:12)`.
+    pub fn rule_source(&self, rule: &str) -> Option<String> {
+        self.rule_sources.get(rule).map(|paths| paths.join(", "))
+    }
 }
 
 impl Config {
@@ -154,24 +651,59 @@ impl Config {
         dump_config: bool,
         migrate_config: bool,
     ) -> Result<Config, Box<dyn Error>> {
+        Config::with_layers(cfg_path, dump_config, migrate_config).map(|(config, _)| config)
+    }
+
+    /// Reads the config file, with its pack layers.
+    pub fn with_layers(
+        cfg_path: PathBuf,
+        dump_config: bool,
+        migrate_config: bool,
+    ) -> Result<(Config, PackLayers), Box<dyn Error>> {
         if !cfg_path.exists() {
             _ = create_default_config(&cfg_path);
         }
 
-        Ok(Config {
-            config: read_config_file(Some(cfg_path.clone()), dump_config, migrate_config)?,
+        let config_string = fs::read_to_string(&cfg_path)?;
+        let (config, layers) = read_config_layers(
+            &config_string,
+            Some(cfg_path.clone()),
+            dump_config,
+            migrate_config,
+        )?;
+        let config = Config {
+            config,
             cfg_path: Some(cfg_path),
+        };
+        Ok((config, layers))
+    }
+
+    /// Builds the config from a TOML string, without any file behind it,
+    /// so it is never migrated nor watched for changes.
+    pub fn from_toml(config_string: &str, dump_config: bool) -> Result<Config, Box<dyn Error>> {
+        Ok(Config {
+            config: read_config_str(config_string, None, dump_config, false)?,
+            cfg_path: None,
         })
     }
 }
 
 impl ConfigFileRaw {
+    /// The config with every default value, as TOML, to compare a config
+    /// with the current defaults.
+    pub fn default_toml() -> Result<String, Box<dyn Error>> {
+        let mut config: ConfigFileRaw = toml::from_str("")?;
+        config.version = VERSION.to_string();
+        Ok(toml::to_string(&config)?)
+    }
+
     pub fn migrate(&mut self, cfg_path: &Option<PathBuf>) -> Result<(), Box<dyn Error>> {
         self.version = VERSION.to_string();
         let config_updated = toml::to_string(&self)?;
         if let Some(path) = cfg_path {
-            let config_file = &mut File::create(path)?;
-            write!(config_file, "{config_updated}")?;
+            if let Some(backup) = write::write_config(path, &config_updated)? {
+                println!("Previous config saved in {backup:?}");
+            }
             println!("Config file successfully migrated in {path:?}");
         }
         Ok(())
@@ -183,42 +715,211 @@ pub fn read_config_file(
     dump_config: bool,
     migrate_config: bool,
 ) -> Result<ConfigFile, Box<dyn Error>> {
-    let mut config: ConfigFileRaw = match &cfg_path {
-        Some(path) => {
-            let config_string = fs::read_to_string(path)?;
-            toml::from_str(&config_string).map_err(|e| format!("Unable to parse: {e:?}"))?
-        }
-        None => toml::from_str("").map_err(|e| format!("Unable to parse: {e:?}"))?,
+    let config_string = match &cfg_path {
+        Some(path) => fs::read_to_string(path)?,
+        None => String::new(),
     };
 
-    migrate_config_file(&mut config, migrate_config, cfg_path)?;
+    read_config_str(&config_string, cfg_path, dump_config, migrate_config)
+}
+
+pub fn read_config_str(
+    config_string: &str,
+    cfg_path: Option<PathBuf>,
+    dump_config: bool,
+    migrate_config: bool,
+) -> Result<ConfigFile, Box<dyn Error>> {
+    read_config_layers(config_string, cfg_path, dump_config, migrate_config)
+        .map(|(config, _)| config)
+}
+
+fn read_config_layers(
+    config_string: &str,
+    cfg_path: Option<PathBuf>,
+    dump_config: bool,
+    migrate_config: bool,
+) -> Result<(ConfigFile, PackLayers), Box<dyn Error>> {
+    let (mut config, has_flat_keys) = parse_config(config_string)?;
+    if let Some(path) = &cfg_path {
+        config.rule_sources = locate_rules(config_string, &path.display().to_string());
+    }
+
+    // Only stripped on demand, as it rewrites the whole file
+    let stripped = match migrate_config && config.matching.case_insensitive {
+        true => case::strip_case_prefixes(&mut config),
+        false => 0,
+    };
+    if stripped > 0 {
+        println!("Removing {stripped} (?i) prefixes, redundant with matching.case_insensitive");
+    }
+
+    migrate_config_file(
+        &mut config,
+        migrate_config || has_flat_keys,
+        stripped > 0,
+        cfg_path,
+    )?;
 
     if dump_config {
         println!("{}", serde_json::to_string_pretty(&config)?);
         process::exit(0);
     }
 
-    Ok(ConfigFile {
-        class: generate_icon_config(&config.class),
-        class_active: generate_icon_config(&config.class_active),
+    let layers = PackLayers::load(config);
+    Ok((generate_config(layers.merged()), layers))
+}
+
+/// Parses the config, and tells if behavior keys were found at the top
+/// level.
+fn parse_config(config_string: &str) -> Result<(ConfigFileRaw, bool), Box<dyn Error>> {
+    let mut table: toml::Table =
+        toml::from_str(config_string).map_err(|e| format!("Unable to parse: {e:?}"))?;
+    let has_flat_keys = lift_behavior_keys(&mut table);
+    let config = table
+        .try_into()
+        .map_err(|e| format!("Unable to parse: {e:?}"))?;
+    Ok((config, has_flat_keys))
+}
+
+/// Returns the issues of the config file, its packs are not checked.
+pub fn check_config(cfg_path: &PathBuf) -> Result<Vec<String>, Box<dyn Error>> {
+    let config_string = fs::read_to_string(cfg_path)?;
+    let (mut config, _) = parse_config(&config_string)?;
+    config.rule_sources = locate_rules(&config_string, &cfg_path.display().to_string());
+    let mut issues = lint::lint_icons(&config);
+    issues.extend(generate_config(config).quarantine);
+    issues.sort();
+    Ok(issues)
+}
+
+/// Compiles the config, merged with its packs.
+pub fn generate_config(config: ConfigFileRaw) -> ConfigFile {
+    let mut regexes = RegexCompiler {
+        case_insensitive: config.matching.case_insensitive,
+        sources: config.rule_sources,
+        ..RegexCompiler::default()
+    };
+    ConfigFile {
+        packs: config.packs.clone(),
+        class: generate_icon_config("class", &config.class, &mut regexes),
+        class_active: generate_icon_config("class_active", &config.class_active, &mut regexes),
         workspaces_name: generate_workspaces_name_config(&config.workspaces_name),
-        initial_class: generate_icon_config(&config.initial_class),
-        initial_class_active: generate_icon_config(&config.initial_class_active),
-        title_in_class: generate_title_config(&config.title_in_class),
-        title_in_class_active: generate_title_config(&config.title_in_class_active),
-        title_in_initial_class: generate_title_config(&config.title_in_initial_class),
-        title_in_initial_class_active: generate_title_config(&config.title_in_initial_class_active),
-        initial_title_in_class: generate_title_config(&config.initial_title_in_class),
-        initial_title_in_class_active: generate_title_config(&config.initial_title_in_class_active),
+        initial_class: generate_icon_config("initial_class", &config.initial_class, &mut regexes),
+        initial_class_active: generate_icon_config(
+            "initial_class_active",
+            &config.initial_class_active,
+            &mut regexes,
+        ),
+        class_floating: generate_icon_config(
+            "class_floating",
+            &config.class_floating,
+            &mut regexes,
+        ),
+        class_floating_active: generate_icon_config(
+            "class_floating_active",
+            &config.class_floating_active,
+            &mut regexes,
+        ),
+        class_xwayland: generate_icon_config(
+            "class_xwayland",
+            &config.class_xwayland,
+            &mut regexes,
+        ),
+        title_in_class: generate_title_config(
+            "title_in_class",
+            &config.title_in_class,
+            &mut regexes,
+        ),
+        title_in_class_active: generate_title_config(
+            "title_in_class_active",
+            &config.title_in_class_active,
+            &mut regexes,
+        ),
+        title_in_initial_class: generate_title_config(
+            "title_in_initial_class",
+            &config.title_in_initial_class,
+            &mut regexes,
+        ),
+        title_in_initial_class_active: generate_title_config(
+            "title_in_initial_class_active",
+            &config.title_in_initial_class_active,
+            &mut regexes,
+        ),
+        initial_title_in_class: generate_title_config(
+            "initial_title_in_class",
+            &config.initial_title_in_class,
+            &mut regexes,
+        ),
+        initial_title_in_class_active: generate_title_config(
+            "initial_title_in_class_active",
+            &config.initial_title_in_class_active,
+            &mut regexes,
+        ),
         initial_title_in_initial_class: generate_title_config(
+            "initial_title_in_initial_class",
             &config.initial_title_in_initial_class,
+            &mut regexes,
         ),
         initial_title_in_initial_class_active: generate_title_config(
+            "initial_title_in_initial_class_active",
             &config.initial_title_in_initial_class_active,
+            &mut regexes,
         ),
-        exclude: generate_exclude_config(&config.exclude),
+        title_history_in_class: generate_title_config(
+            "title_history_in_class",
+            &config.title_history_in_class,
+            &mut regexes,
+        ),
+        title_history_in_class_active: generate_title_config(
+            "title_history_in_class_active",
+            &config.title_history_in_class_active,
+            &mut regexes,
+        ),
+        exclude: generate_exclude_config(&config.exclude, &mut regexes)
+            .into_iter()
+            .chain(generate_builtin_exclude_config(&config.matching))
+            .collect(),
+        fallback: generate_icon_config("fallback", &config.fallback, &mut regexes),
+        modified_markers: generate_icon_config(
+            "modified_markers",
+            &config.modified_markers,
+            &mut regexes,
+        ),
+        client_format_per_class: generate_icon_config(
+            "client_format_per_class",
+            &config.client_format_per_class,
+            &mut regexes,
+        ),
+        class_colors: generate_icon_config("class_colors", &config.class_colors, &mut regexes),
+        min_count_per_class: generate_min_count_config(&config.min_count_per_class, &mut regexes),
+        singleton: generate_class_list_config("singleton", &config.singleton, &mut regexes),
+        no_dedup: generate_class_list_config("no_dedup", &config.no_dedup, &mut regexes),
+        autospawn: generate_autospawn_config(&config.autospawn),
+        title_progress: generate_title_progress_config(&config.title_progress, &mut regexes),
+        media: generate_media_config(&config.media, &mut regexes),
         format: config.format,
-    })
+        behavior: config.behavior,
+        matching: config.matching,
+        quarantine: regexes.quarantine,
+        rule_sources: regexes.rules,
+    }
+}
+
+/// Reads the config of the daemon from stdin, the command line or its file.
+/// Only a config file comes with its pack layers, so that its local packs
+/// can be reloaded alone.
+pub fn read_daemon_config(args: &Args) -> Result<(Config, Option<PackLayers>), Box<dyn Error>> {
+    if args.config_from_stdin {
+        let config_string = io::read_to_string(io::stdin())?;
+        return Ok((Config::from_toml(&config_string, args.dump)?, None));
+    }
+    if let Some(config_string) = &args.config_inline {
+        return Ok((Config::from_toml(config_string, args.dump)?, None));
+    }
+
+    let cfg_path = get_config_path(&args.config)?;
+    let (config, layers) = Config::with_layers(cfg_path, args.dump, args.migrate_config)?;
+    Ok((config, Some(layers)))
 }
 
 pub fn get_config_path(args: &Option<String>) -> Result<PathBuf, Box<dyn Error>> {
@@ -233,9 +934,37 @@ pub fn get_config_path(args: &Option<String>) -> Result<PathBuf, Box<dyn Error>>
     Ok(cfg_path)
 }
 
+/// Moves the behavior keys found at the top level into `[behavior]`, the
+/// keys already in the section win. Returns whether any key was moved.
+fn lift_behavior_keys(table: &mut toml::Table) -> bool {
+    let flat_keys: Vec<&str> = BEHAVIOR_KEYS
+        .into_iter()
+        .filter(|key| table.contains_key(*key))
+        .collect();
+    if flat_keys.is_empty() {
+        return false;
+    }
+
+    println!("Moving {flat_keys:?} to the [behavior] section of the config");
+    let mut behavior = match table.remove("behavior") {
+        Some(toml::Value::Table(behavior)) => behavior,
+        _ => toml::Table::new(),
+    };
+    for key in flat_keys {
+        if let Some(value) = table.remove(key) {
+            behavior.entry(key).or_insert(value);
+        }
+    }
+    table.insert("behavior".to_string(), toml::Value::Table(behavior));
+    true
+}
+
+/// Writes the config back in the latest version when asked to, if it is
+/// outdated or was `changed` while loading.
 fn migrate_config_file(
     config: &mut ConfigFileRaw,
     migrate_config: bool,
+    changed: bool,
     cfg_path: Option<PathBuf>,
 ) -> Result<(), Box<dyn Error>> {
     let default_version = Version::parse("1.0.0")?;
@@ -245,7 +974,7 @@ fn migrate_config_file(
     if need_migrate {
         println!("Config in version {actual_version} need to be updated in version {last_version}, run: {BIN_NAME} --migrate-config");
     }
-    if need_migrate && migrate_config {
+    if (need_migrate || changed) && migrate_config {
         config
             .migrate(&cfg_path)
             .map_err(|e| format!("Unable to migrate config {e:?}"))?;
@@ -258,30 +987,183 @@ pub fn create_default_config(cfg_path: &PathBuf) -> Result<&'static str, Box<dyn
     let default_config = r#"
 version = "1.1.14"
 
+# Load [class] icons from packs, the icons below take precedence.
+# Remote packs are fetched once and cached, pin them with #sha256=<hex>
+# packs = ["file:///path/to/pack.toml", "https://example.org/pack.toml#sha256=..."]
+
 # [format]
 # Deduplicate icons if enable.
 # A superscripted counter will be added.
 # dedup = false
 # dedup_inactive_fullscreen = false # dedup more
+# what must be equal for clients to be deduplicated:
+# "icon", "class", "title", "fullscreen" and "active"
+# dedup_by = ["icon", "active", "fullscreen"]
+# group clients by their icon rather than by their rule, to merge the
+# entries of different rules rendering the same glyph
+# dedup_by_icon = false
+# order of the clients, "default", "position" to match the layout on screen
+# or "alpha" to sort them alphabetically
+# client_sort = "default"
 # window delimiter
 # delim = " "
 # max_clients = 30 # you should not need this
+# max_clients of the workspaces of a monitor, by its name
+# max_clients_per_monitor = { eDP-1 = 3, DP-1 = 10 }
+# pad {clients} to a fixed width, so fixed-width buttons don't jiggle
+# pad_clients_to = 10
+# pad_char = " "
+# truncate workspaces names so they fit together in this width,
+# the least recently used workspaces are shortened first
+# total_max_width = 80
+# replace non-ASCII icons with the [fallback] of their class (or the class name),
+# and drop the remaining non-ASCII glyphs, for TTYs and fonts without icons
+# ascii_fallback = false
+# the icon of the classes no rule matches, instead of the DEFAULT one, from
+# their first letter: {letter} (K for kitty) or {letter_circled} (Ⓚ)
+# letter_fallback = false
+# letter_fallback_format = "{letter_circled}"
 
 # available formatter:
 # {counter_sup} - superscripted count of clients on the workspace, and simple {counter}, {delim}
 # {icon}, {client}
 # workspace formatter
 # workspace = "{id}:{delim}{clients}" # {id}, {delim} and {clients} are supported
+# {fullscreen_count} is the number of fullscreen clients of the workspace
+# {excluded_count} is the number of its clients hidden by [exclude]
 # workspace_empty = "{id}" # {id}, {delim} and {clients} are supported
+# the focused workspace while it is empty, workspace_empty if unset
+# workspace_empty_active = "{id}_"
+# sent to `ctl subscribe` with each rename, to animate it, where
+# {workspace_prev_name} and {workspace_name} are the old and new names
+# workspace_transition = "{workspace_prev_name} -> {workspace_name}"
+# empty workspaces configured persistent in Hyprland (workspace rules),
+# {persistent} is persistent_marker on them, empty otherwise
+# workspace_persistent_empty = "<span alpha='50%'>{id}</span>"
+# persistent_marker = "P"
 # client formatter
 # client = "{icon}"
 # client_active = "*{icon}*"
+# focused clients only matching inactive rules: "wrap" their icon in
+# client_active, "use_inactive" as is, or use the "default" of [class_active]
+# active_fallback = "wrap"
+# only show the dominant client of each workspace, by "area" or "focused"
+# (else the largest), followed by dominant_rest when there are other clients
+# dominant = false
+# dominant_by = "area"
+# dominant_rest = "{delim}+{rest}"
+# client_active_fullscreen = "{client_fullscreen}"
+# client with a title matching [modified_markers], {modified} is the marker
+# client_modified = "{client}"
+# client without a pid (e.g. an unmanaged XWayland window), shown with
+# behavior.include_unmanaged
+# client_unmanaged = "{client}"
+# floating client, and pinned one (floating on every workspace), which uses
+# the client_floating format unless set
+# client_floating = "{client}"
+# client_pinned = "^{client}"
+# {urgent} is this marker for windows requesting attention, empty otherwise,
+# and {urgent_for} how long ago they did, e.g. "{icon}{urgent}"
+# urgent_marker = "!"
+# {xwayland} is this marker for XWayland (X11) windows, empty otherwise,
+# e.g. "{icon}{xwayland}"
+# xwayland_marker = "X"
+# how many times placeholders in values are expanded, e.g. {client} is
+# "{icon}" which is the icon of [class]; braces in window titles and
+# classes are never expanded
+# placeholder_depth = 4
+# shown instead of the negative {id} of special workspaces (scratchpads),
+# {special_name} is their name, e.g. "S" or "{special_name}"
+# special_prefix = "S"
+# special workspaces, even empty, instead of workspace and workspace_empty,
+# {name} is their name and {clients} their clients
+# workspace_special = "{name}:{delim}{clients}"
+# special workspaces without clients, workspace_special if unset
+# workspace_special_empty = "{name}"
 
 # deduplicate client formatter
 # client_fullscreen = "[{icon}]"
 # client_dup = "{client}{counter_sup}"
 # client_dup_fullscreen = "[{icon}]{delim}{icon}{counter_unfocused}"
 # client_dup_active = "*{icon}*{delim}{icon}{counter_unfocused}"
+# client_dup_active_fullscreen = "{client_dup_fullscreen}"
+# maximized clients, instead of the fullscreen formats
+# client_maximized = "{client_fullscreen}"
+# client_dup_maximized = "{client_dup_fullscreen}"
+# {counter} is the number of duplicates, the focused one included,
+# and {has_active} this marker if one of them is focused, e.g. "{icon}×{counter}{has_active}"
+# active_marker = "*"
+
+# [format.counter_glyphs]
+# Glyphs of {counter_sup} and {counter_unfocused_sup}, for whole numbers or
+# digits, superscript digits by default
+# "1" = "⚀"
+# "2" = "⚁"
+
+# [matching]
+# When no rule matches, retry without those class prefixes (flatpak, snaps...)
+# strip_class_prefixes = ["org.", "io.github.", "com."]
+# and with the short name of reverse DNS classes (org.telegram.desktop => telegram)
+# reverse_dns_short_name = false
+# Match initial_class and initial_title rules first, for icons that never change
+# prefer_initial = false
+# Rules whose icon is put before the icon of the next matching rule kind,
+# instead of winning outright, e.g. a site from the title then the browser
+# continue_rules = ["(?i)github"]
+# Number of recent titles of each window kept for [title_history_in_class]
+# title_history_size = 10
+# Ignore the case in every rule, except the ones starting with their own
+# flags, e.g. (?-i) to keep a rule case sensitive
+# case_insensitive = false
+
+# [behavior]
+# Append every workspace rename to a file, rotated when bigger than max size,
+# relative paths are placed in the state directory
+# audit_log = "/tmp/hyprland-autoname-workspaces.log"
+# audit_log_max_size = 1048576
+# Rename every workspace on every event, to troubleshoot out-of-sync names
+# cache = true
+# Batch renames while a session restore opens many windows at startup
+# startup_throttle = false
+# On battery or power-saver profile: rename at most every 2s, ignore title
+# changes and only rename visible workspaces
+# low_power_on_battery = false
+# Show config errors as desktop notifications
+# notify_on_error = false
+# Seconds after which a window requesting attention is no longer urgent,
+# 0 to keep it urgent until focused
+# urgent_timeout = 300
+# Tag windows with the words of their icon (hyprctl dispatch tagwindow),
+# e.g. autoname-term, for tools reading window tags rather than workspaces
+# rewrite_titles = false
+# Milliseconds to wait for the events to settle before renaming, to merge
+# bursts (e.g. a layout change) into a single rename, 0 to rename at once
+# debounce = 0
+# Seconds between full renames, to recover names changed by someone else,
+# 0 to only rename on events
+# resync = 0
+# Name the focused workspace while it has no window
+# rename_empty = true
+# Only rename the visible workspaces, the others are renamed once shown
+# only_visible = false
+# Where the windows come from, "hyprland" or "wlr-foreign-toplevel",
+# the --backend option takes precedence
+# backend = "hyprland"
+# Show the windows without a pid (e.g. unmanaged XWayland windows), with
+# format.client_unmanaged
+# include_unmanaged = false
+# Mirror the name of the focused workspace to this Hyprland env variable
+# (hyprctl keyword env) and to this file, relative to the state directory,
+# e.g. for hyprlock labels
+# mirror_env = "AUTONAME_WS"
+# mirror_file = "focused-workspace"
+# Only name the workspaces of these monitors, leaving the others to another
+# tool, all monitors when empty
+# monitors = ["DP-1"]
+# What to do with the workspaces named by another tool: "overwrite" them,
+# "skip" them, or "warn" and overwrite them. Except with "overwrite", our
+# names end with an invisible signature (U+2063) to tell them apart
+# on_conflict = "overwrite"
 
 [class]
 # Add your icons mapping
@@ -303,6 +1185,17 @@ DEFAULT = "*{icon}*"
 # [initial_class_active]
 # "(?i)Kitty" = "*TERM*"
 
+# floating windows, instead of the icon matched on their class
+# [class_floating]
+# "(?i)pavucontrol" = "mixer"
+
+# [class_floating_active]
+# "(?i)pavucontrol" = "*mixer*"
+
+# XWayland (X11) windows, instead of the icon matched on their class
+# [class_xwayland]
+# "(?i)steam" = "steam (X)"
+
 [title_in_class."(?i)kitty"]
 "(?i)neomutt" = "neomutt"
 # regex captures support is supported
@@ -320,6 +1213,50 @@ DEFAULT = "*{icon}*"
 # [initial_title_in_initial_class."(?i)kitty"]
 # "(?i)neomutt" = "neomutt"
 
+# matched against any recent title of the window, for apps that only
+# briefly show a matchable title, after the rules on the current title
+# [title_history_in_class."(?i)zoom"]
+# "(?i)meeting started" = "meeting"
+
+# [fallback]
+# ASCII replacement of the icons of a class, with format.ascii_fallback
+# "(?i)kitty" = "T"
+# "[Ff]irefox" = "W"
+
+# [modified_markers]
+# Titles of clients with unsaved changes, and the marker shown as {modified}
+# "^\\*" = "*"
+# "●" = "●"
+
+# [client_format_per_class]
+# The client format of a class, instead of format.client, also used
+# as {client} by the other client formats
+# "(?i)mpv" = "{icon} {track}"
+
+# [class_colors]
+# The color of a class, shown as {class_color} by the formats, e.g.
+# client = "<span foreground='{class_color}'>{icon}</span>"
+# "(?i)kitty" = "lightblue"
+# "(?i)firefox" = "orange"
+
+# [min_count_per_class]
+# Clients of a class are only shown when the workspace has at least this
+# many of them, e.g. hide a single stray terminal
+# "(?i)kitty" = 2
+
+# [title_progress]
+# Detect progress in titles, exposed as {progress} and {progress_bar}
+# enabled = false
+# patterns = ['(\d+)%', '(\d+) of (\d+)']
+# bar_width = 5
+
+# [media]
+# Read the artist and the track from the titles of media players, exposed
+# as {artist} and {track}, with `artist` and `track` named groups
+# enabled = false
+# classes = ["(?i)spotify", "(?i)mpv"]
+# patterns = ['^(?P<track>.+) - mpv$', '^(?P<artist>.+?) [-–—] (?P<track>.+)$']
+
 # [initial_title."(?i)kitty"]
 # "zsh" = "Zsh"
 
@@ -353,18 +1290,44 @@ aProgram = "^$" # will match null title for aProgram
 "#
     .trim();
 
-    let mut config_file = File::create(cfg_path)?;
-    write!(&mut config_file, "{default_config}")?;
+    write::write_config(cfg_path, default_config)?;
     println!("Default config created in {cfg_path:?}");
 
     Ok(default_config)
 }
 
+/// Compiles the regexes of the config: with `matching.case_insensitive`,
+/// the patterns without leading flags ignore the case, and the invalid
+/// ones are kept in the quarantine. Both are reported with the path of
+/// their rule, and its file and line when known.
+#[derive(Default)]
+struct RegexCompiler {
+    case_insensitive: bool,
+    quarantine: Vec<String>,
+    sources: HashMap<String, RuleSource>,
+    rules: HashMap<String, Vec<String>>,
+}
+
+impl RegexCompiler {
+    /// Returns the path of the pattern, with the file and line of its rule,
+    /// or of the table it belongs to, e.g. for the title regex of `[exclude]`.
+    fn describe(&self, table: &str, pattern: &str) -> String {
+        let path = format!("{table}.{pattern:?}");
+        match self.sources.get(&path).or_else(|| self.sources.get(table)) {
+            Some(source) => format!("{path} ({source})"),
+            None => path,
+        }
+    }
+}
+
 /// Creates a Regex from a given pattern and logs an error if the pattern is invalid.
 ///
 /// # Arguments
 ///
+/// * `table` - The path of the pattern in the config, e.g. `class`.
 /// * `pattern` - A string representing the regex pattern to be compiled.
+/// * `regexes` - The case sensitivity of the config, and the quarantine where
+///   the invalid pattern is reported with its path.
 ///
 /// # Returns
 ///
@@ -373,25 +1336,43 @@ aProgram = "^$" # will match null title for aProgram
 /// # Example
 ///
 /// ```
-/// use regex::Regex;
-/// use crate::regex_with_error_logging;
+/// let mut regexes = RegexCompiler::default();
 ///
-/// let valid_pattern = "Class1";
-/// let invalid_pattern = "Class1[";
-///
-/// assert!(regex_with_error_logging(valid_pattern).is_some());
-/// assert!(regex_with_error_logging(invalid_pattern).is_none());
+/// assert!(regex_with_error_logging("class", "Class1", &mut regexes).is_some());
+/// assert!(regex_with_error_logging("class", "Class1[", &mut regexes).is_none());
+/// assert_eq!(regexes.quarantine, [r#"class."Class1[": unclosed character class"#]);
 /// ```
-fn regex_with_error_logging(pattern: &str) -> Option<Regex> {
-    match Regex::new(pattern) {
-        Ok(re) => Some(re),
+fn regex_with_error_logging(
+    table: &str,
+    pattern: &str,
+    regexes: &mut RegexCompiler,
+) -> Option<Regex> {
+    let path = regexes.describe(table, pattern);
+    match Regex::new(&case::with_case_flag(pattern, regexes.case_insensitive)) {
+        Ok(re) => {
+            regexes.rules.entry(re.to_string()).or_default().push(path);
+            Some(re)
+        }
         Err(e) => {
             println!("Unable to parse regex: {e:?}");
+            regexes
+                .quarantine
+                .push(format!("{path}: {}", regex_error(&e)));
             None
         }
     }
 }
 
+/// Returns the last line of the error, without the caret diagram.
+fn regex_error(error: &regex::Error) -> String {
+    let message = error.to_string();
+    let last_line = message.lines().last().unwrap_or_default();
+    last_line
+        .strip_prefix("error: ")
+        .unwrap_or(last_line)
+        .to_string()
+}
+
 /// Generates the title configuration for the application.
 ///
 /// This function accepts a nested HashMap where the outer HashMap's keys represent class names,
@@ -406,25 +1387,26 @@ fn regex_with_error_logging(pattern: &str) -> Option<Regex> {
 /// # Examples
 ///
 /// ```
-/// let title_icons = generate_title_config(title_icons_map);
+/// let title_icons = generate_title_config("title_in_class", title_icons_map, &mut regexes);
 /// ```
 fn generate_title_config(
+    table: &str,
     icons: &HashMap<String, HashMap<String, String>>,
+    regexes: &mut RegexCompiler,
 ) -> Vec<(Regex, Vec<(Regex, String)>)> {
     icons
         .iter()
         .filter_map(|(class, title_icon)| {
-            regex_with_error_logging(class).map(|re| {
-                (
-                    re,
-                    title_icon
-                        .iter()
-                        .filter_map(|(title, icon)| {
-                            regex_with_error_logging(title).map(|re| (re, icon.to_string()))
-                        })
-                        .collect(),
-                )
-            })
+            let re = regex_with_error_logging(table, class, regexes)?;
+            let class_table = format!("{table}.{class:?}");
+            let titles = title_icon
+                .iter()
+                .filter_map(|(title, icon)| {
+                    regex_with_error_logging(&class_table, title, regexes)
+                        .map(|re| (re, icon.to_string()))
+                })
+                .collect();
+            Some((re, titles))
         })
         .collect()
 }
@@ -442,13 +1424,43 @@ fn generate_title_config(
 /// # Examples
 ///
 /// ```
-/// let icons_config = generate_icon_config(icons_map);
+/// let icons_config = generate_icon_config("class", icons_map, &mut regexes);
 /// ```
-fn generate_icon_config(icons: &HashMap<String, String>) -> Vec<(Regex, String)> {
+fn generate_icon_config(
+    table: &str,
+    icons: &HashMap<String, String>,
+    regexes: &mut RegexCompiler,
+) -> Vec<(Regex, String)> {
     icons
         .iter()
         .filter_map(|(class, icon)| {
-            regex_with_error_logging(class).map(|re| (re, icon.to_string()))
+            regex_with_error_logging(table, class, regexes).map(|re| (re, icon.to_string()))
+        })
+        .collect()
+}
+
+/// Returns the built-in exclude layer, applied after `[exclude]`.
+fn generate_builtin_exclude_config(matching: &ConfigMatchingRaw) -> Vec<(Regex, Regex)> {
+    if !matching.exclude_builtin {
+        return vec![];
+    }
+    let any_title = Regex::new("").expect("valid regex");
+    BUILTIN_EXCLUDE
+        .iter()
+        .map(|class| (Regex::new(class).expect("valid regex"), any_title.clone()))
+        .collect()
+}
+
+/// Compiles the class regexes of `[min_count_per_class]`, invalid ones are
+/// logged and ignored.
+fn generate_min_count_config(
+    min_counts: &HashMap<String, usize>,
+    regexes: &mut RegexCompiler,
+) -> Vec<(Regex, usize)> {
+    min_counts
+        .iter()
+        .filter_map(|(class, min)| {
+            regex_with_error_logging("min_count_per_class", class, regexes).map(|re| (re, *min))
         })
         .collect()
 }
@@ -466,19 +1478,77 @@ fn generate_icon_config(icons: &HashMap<String, String>) -> Vec<(Regex, String)>
 /// # Examples
 ///
 /// ```
-/// let exclude_config = generate_exclude_config(exclude_map);
+/// let exclude_config = generate_exclude_config(exclude_map, &mut regexes);
 /// ```
-fn generate_exclude_config(icons: &HashMap<String, String>) -> Vec<(Regex, Regex)> {
+fn generate_exclude_config(
+    icons: &HashMap<String, String>,
+    regexes: &mut RegexCompiler,
+) -> Vec<(Regex, Regex)> {
     icons
         .iter()
         .filter_map(|(class, title)| {
-            regex_with_error_logging(class).and_then(|re_class| {
-                regex_with_error_logging(title).map(|re_title| (re_class, re_title))
-            })
+            let re_class = regex_with_error_logging("exclude", class, regexes);
+            let re_title = regex_with_error_logging(&format!("exclude.{class:?}"), title, regexes);
+            re_class.zip(re_title)
         })
         .collect()
 }
 
+/// Compiles a list of class regexes, invalid ones are logged and ignored.
+fn generate_class_list_config(
+    table: &str,
+    patterns: &[String],
+    regexes: &mut RegexCompiler,
+) -> Vec<Regex> {
+    patterns
+        .iter()
+        .filter_map(|pattern| regex_with_error_logging(table, pattern, regexes))
+        .collect()
+}
+
+fn generate_autospawn_config(autospawn: &HashMap<String, String>) -> HashMap<i32, String> {
+    autospawn
+        .iter()
+        .filter_map(|(id, command)| match id.parse::<i32>() {
+            Ok(id) => Some((id, command.to_string())),
+            Err(_) => {
+                println!("Unable to autospawn on workspace {id:?}, it is not a workspace id");
+                None
+            }
+        })
+        .collect()
+}
+
+/// Generates the title progress configuration for the application.
+///
+/// Patterns are only compiled when the feature is enabled, so an empty list
+/// means no progress detection at all.
+fn generate_title_progress_config(
+    title_progress: &ConfigTitleProgressRaw,
+    regexes: &mut RegexCompiler,
+) -> TitleProgress {
+    let patterns = if title_progress.enabled {
+        generate_class_list_config("title_progress.patterns", &title_progress.patterns, regexes)
+    } else {
+        vec![]
+    };
+
+    TitleProgress {
+        patterns,
+        bar_width: title_progress.bar_width,
+    }
+}
+
+fn generate_media_config(media: &ConfigMediaRaw, regexes: &mut RegexCompiler) -> Media {
+    if !media.enabled {
+        return Media::default();
+    }
+    Media {
+        classes: generate_class_list_config("media.classes", &media.classes, regexes),
+        patterns: generate_class_list_config("media.patterns", &media.patterns, regexes),
+    }
+}
+
 /// Generates the workspaces id to name mapping
 fn generate_workspaces_name_config(
     workspaces_name: &HashMap<String, String>,
@@ -507,7 +1577,11 @@ mod tests {
         inner_map.insert("Title1".to_string(), "Icon1".to_string());
         title_icons_map.insert("Class1".to_string(), inner_map);
 
-        let title_config = generate_title_config(&title_icons_map);
+        let title_config = generate_title_config(
+            "title_in_class",
+            &title_icons_map,
+            &mut RegexCompiler::default(),
+        );
 
         assert_eq!(title_config.len(), 1);
         assert!(title_config[0].0.is_match("Class1"));
@@ -521,7 +1595,8 @@ mod tests {
         let mut list_class: HashMap<String, String> = HashMap::new();
         list_class.insert("Class1".to_string(), "Icon1".to_string());
 
-        let icons_config = generate_icon_config(&list_class);
+        let icons_config =
+            generate_icon_config("class", &list_class, &mut RegexCompiler::default());
 
         assert_eq!(icons_config.len(), 1);
         assert!(icons_config[0].0.is_match("Class1"));
@@ -533,23 +1608,128 @@ mod tests {
         let mut list_exclude: HashMap<String, String> = HashMap::new();
         list_exclude.insert("Class1".to_string(), "Title1".to_string());
 
-        let exclude_config = generate_exclude_config(&list_exclude);
+        let exclude_config = generate_exclude_config(&list_exclude, &mut RegexCompiler::default());
 
         assert_eq!(exclude_config.len(), 1);
         assert!(exclude_config[0].0.is_match("Class1"));
         assert!(exclude_config[0].1.is_match("Title1"));
     }
 
+    #[test]
+    fn test_quarantine() {
+        let config = read_config_str(
+            "[class]\n\"Class1[\" = \"a\"\nkitty = \"term\"\n\
+             [title_in_class.kitty]\n\"(vim\" = \"vim\"\n\
+             [exclude]\nsteam = \"*\"",
+            None,
+            false,
+            false,
+        )
+        .unwrap();
+        let mut quarantine = config.quarantine.clone();
+        quarantine.sort();
+        assert_eq!(
+            quarantine,
+            [
+                r#"class."Class1[": unclosed character class"#,
+                r#"exclude."steam"."*": repetition operator missing expression"#,
+                r#"title_in_class."kitty"."(vim": unclosed group"#,
+            ]
+        );
+        // The valid rules of the tables are kept
+        assert_eq!(config.class.len(), 1);
+        assert_eq!(config.title_in_class.len(), 1);
+    }
+
+    #[test]
+    fn test_rule_sources() {
+        let config_string = "[class]\nkitty = \"term\"\n\"Class1[\" = \"a\"\n\
+                             [title_in_class.firefox]\nGitHub = \"gh\"\n";
+        let (mut config, _) = parse_config(config_string).unwrap();
+        config.rule_sources = locate_rules(config_string, "config.toml");
+        let config = generate_config(config);
+
+        assert_eq!(
+            config.rule_source("kitty").unwrap(),
+            r#"class."kitty" (config.toml:2)"#
+        );
+        assert_eq!(
+            config.rule_source("GitHub").unwrap(),
+            r#"title_in_class."firefox"."GitHub" (config.toml:5)"#
+        );
+        assert_eq!(
+            config.quarantine,
+            [r#"class."Class1[" (config.toml:3): unclosed character class"#]
+        );
+        assert_eq!(config.rule_source("foot"), None);
+    }
+
+    #[test]
+    fn test_case_insensitive() {
+        let config = read_config_str(
+            "[matching]\ncase_insensitive = true\n[class]\nkitty = \"term\"\n\"(?-i)Code\" = \"code\"",
+            None,
+            false,
+            false,
+        )
+        .unwrap();
+        let icon = |class: &str| {
+            config
+                .class
+                .iter()
+                .find(|(re, _)| re.is_match(class))
+                .map(|(_, icon)| icon.as_str())
+        };
+        assert_eq!(icon("Kitty"), Some("term"));
+        assert_eq!(icon("Code"), Some("code"));
+        assert_eq!(icon("code"), None);
+    }
+
+    #[test]
+    fn test_builtin_exclude() {
+        let is_excluded = |config: &ConfigFile, class: &str| {
+            config
+                .exclude
+                .iter()
+                .any(|(re_class, re_title)| re_class.is_match(class) && re_title.is_match("Title"))
+        };
+
+        let config = read_config_str("[exclude]\nkitty = \".*\"", None, false, false).unwrap();
+        assert!(is_excluded(&config, "kitty"));
+        for class in [
+            "xdg-desktop-portal-gtk",
+            "polkit-gnome-authentication-agent-1",
+            "hyprpolkitagent",
+            "ibus-ui-gtk3",
+            "org.fcitx.",
+            "fcitx",
+        ] {
+            assert!(is_excluded(&config, class), "{class} is not excluded");
+        }
+        assert!(!is_excluded(&config, "firefox"));
+
+        let config =
+            read_config_str("[matching]\nexclude_builtin = false", None, false, false).unwrap();
+        assert!(!is_excluded(&config, "hyprpolkitagent"));
+    }
+
     #[test]
     fn test_regex_with_error_logging() {
         let valid_pattern = "Class1";
         let invalid_pattern = "Class1[";
 
-        assert!(regex_with_error_logging(valid_pattern).is_some());
-        assert!(regex_with_error_logging(invalid_pattern).is_none());
+        let mut regexes = RegexCompiler::default();
+
+        assert!(regex_with_error_logging("class", valid_pattern, &mut regexes).is_some());
+        assert!(regex_with_error_logging("class", invalid_pattern, &mut regexes).is_none());
+        assert_eq!(
+            regexes.quarantine,
+            [r#"class."Class1[": unclosed character class"#]
+        );
     }
 
     #[test]
+    #[allow(clippy::bool_assert_comparison)]
     fn test_config_new_and_read_again_then_compare_format() {
         let cfg_path = PathBuf::from("/tmp/hyprland-autoname-workspaces-test.toml");
         let config = Config::new(cfg_path.clone(), false, false);
@@ -561,4 +1741,85 @@ mod tests {
         let format2 = config2.format.clone();
         assert_eq!(format, format2);
     }
+
+    #[test]
+    fn test_config_from_toml() {
+        let config = Config::from_toml(
+            r#"
+            [format]
+            workspace = "{id}>{clients}"
+
+            [class]
+            "(?i)kitty" = "term"
+            "#,
+            false,
+        )
+        .unwrap();
+
+        assert_eq!(config.cfg_path, None);
+        assert_eq!(config.config.format.workspace, "{id}>{clients}");
+        assert_eq!(config.config.class.len(), 1);
+        assert!(Config::from_toml("[format", false).is_err());
+    }
+
+    #[test]
+    fn test_behavior_defaults() {
+        let config = read_config_file(None, false, false).unwrap();
+        assert!(config.behavior.cache);
+        assert_eq!(config.behavior.audit_log, None);
+
+        assert_eq!(config.behavior.debounce, 0);
+        assert!(config.behavior.rename_empty);
+        assert_eq!(config.behavior.backend, None);
+
+        let behavior: ConfigBehaviorRaw = toml::from_str("cache = false").unwrap();
+        assert!(!behavior.cache);
+        assert!(!behavior.notify_on_error);
+
+        let behavior: ConfigBehaviorRaw =
+            toml::from_str(r#"backend = "wlr-foreign-toplevel""#).unwrap();
+        assert_eq!(behavior.backend, Some(BackendKind::WlrForeignToplevel));
+    }
+
+    #[test]
+    fn test_lift_behavior_keys() {
+        let mut table: toml::Table =
+            toml::from_str("cache = false\ndebounce = 50\n[behavior]\ndebounce = 20").unwrap();
+        assert!(lift_behavior_keys(&mut table));
+        let config: ConfigFileRaw = table.try_into().unwrap();
+        assert!(!config.behavior.cache);
+        assert_eq!(config.behavior.debounce, 20);
+
+        let mut table: toml::Table = toml::from_str("[behavior]\ncache = false").unwrap();
+        assert!(!lift_behavior_keys(&mut table));
+
+        // Every key of the section can be lifted
+        let behavior = toml::Table::try_from(ConfigBehaviorRaw::default()).unwrap();
+        assert!(behavior
+            .keys()
+            .all(|key| BEHAVIOR_KEYS.contains(&key.as_str())));
+    }
+
+    #[test]
+    fn test_default_toml() {
+        let default_toml = ConfigFileRaw::default_toml().unwrap();
+        let config: ConfigFileRaw = toml::from_str(&default_toml).unwrap();
+        assert_eq!(config.version, VERSION);
+        assert_eq!(config.format, ConfigFormatRaw::default());
+        assert_eq!(config.behavior, ConfigBehaviorRaw::default());
+        assert_eq!(config.class, default_class());
+    }
+
+    #[test]
+    fn test_rules_count() {
+        let config = Config::from_toml(
+            "[class]\nkitty = \"term\"\n\"[invalid\" = \"x\"\n\n[title_in_class.kitty]\nvim = \"vim\"\nmutt = \"mail\"\n",
+            false,
+        )
+        .unwrap();
+        assert_eq!(config.config.rules_count(), 3);
+
+        let config = Config::from_toml("[class]\n\"[invalid\" = \"x\"\n", false).unwrap();
+        assert_eq!(config.config.rules_count(), 0);
+    }
 }
@@ -0,0 +1,263 @@
+use crate::config::sources::{locate_rules, RuleSource};
+use crate::config::ConfigFileRaw;
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::error::Error;
+use std::fs;
+use std::path::{Path, PathBuf};
+#[cfg(feature = "remote-packs")]
+use std::time::Duration;
+
+const BIN_NAME: &str = env!("CARGO_BIN_NAME");
+
+/// Icons shared by the community, in the same sections as the config file.
+/// Only the class tables are read from packs, not the title ones.
+#[derive(Deserialize, Default, Debug, Clone, PartialEq, Eq)]
+struct IconPackRaw {
+    #[serde(default)]
+    class: HashMap<String, String>,
+    #[serde(default)]
+    class_active: HashMap<String, String>,
+    #[serde(default)]
+    initial_class: HashMap<String, String>,
+    #[serde(default)]
+    initial_class_active: HashMap<String, String>,
+    #[serde(skip)]
+    sources: HashMap<String, RuleSource>,
+}
+
+/// A pack reference, e.g. `https://example.org/pack.toml#sha256=<hex>`.
+#[derive(Debug, PartialEq, Eq)]
+struct PackRef<'a> {
+    url: &'a str,
+    sha256: Option<String>,
+}
+
+impl<'a> PackRef<'a> {
+    fn parse(reference: &'a str) -> Self {
+        match reference.split_once("#sha256=") {
+            Some((url, sha256)) => PackRef {
+                url,
+                sha256: Some(sha256.to_lowercase()),
+            },
+            None => PackRef {
+                url: reference,
+                sha256: None,
+            },
+        }
+    }
+
+    fn verify(&self, content: &str) -> Result<(), Box<dyn Error>> {
+        match &self.sha256 {
+            Some(expected) if *expected != sha256_hex(content.as_bytes()) => {
+                Err(format!("checksum mismatch for {}", self.url).into())
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+/// The config file without its packs, and each of its packs, so a pack
+/// can be reloaded alone when its file changes.
+#[derive(Debug, Clone)]
+pub struct PackLayers {
+    base: ConfigFileRaw,
+    packs: Vec<(String, Option<IconPackRaw>)>,
+}
+
+impl PackLayers {
+    pub fn load(base: ConfigFileRaw) -> Self {
+        let packs = base
+            .packs
+            .iter()
+            .map(|reference| (reference.clone(), load_pack_logged(reference)))
+            .collect();
+        PackLayers { base, packs }
+    }
+
+    /// Reloads the packs read from this file, returns whether there was any.
+    pub fn reload(&mut self, path: &Path) -> bool {
+        let mut reloaded = false;
+        for (reference, pack) in &mut self.packs {
+            if local_path(reference).as_deref() == Some(path) {
+                *pack = load_pack_logged(reference);
+                reloaded = true;
+            }
+        }
+        reloaded
+    }
+
+    /// Returns the config with the icons of the packs. Icons of the config
+    /// file take precedence, then packs in their declaration order.
+    pub fn merged(&self) -> ConfigFileRaw {
+        let mut config = self.base.clone();
+        for (_, pack) in &self.packs {
+            if let Some(pack) = pack {
+                merge_pack(&mut config, pack.clone());
+            }
+        }
+        config
+    }
+}
+
+/// Errors are only printed, a missing pack must never prevent the start.
+fn load_pack_logged(reference: &str) -> Option<IconPackRaw> {
+    load_pack(&PackRef::parse(reference))
+        .map_err(|e| println!("Unable to load icon pack {reference}: {e}"))
+        .ok()
+}
+
+/// Returns the file of a `file://` pack, None for remote packs.
+pub fn local_path(reference: &str) -> Option<PathBuf> {
+    let pack = PackRef::parse(reference);
+    pack.url.strip_prefix("file://").map(PathBuf::from)
+}
+
+fn merge_pack(config: &mut ConfigFileRaw, pack: IconPackRaw) {
+    let sections = [
+        ("class", &mut config.class, pack.class),
+        ("class_active", &mut config.class_active, pack.class_active),
+        (
+            "initial_class",
+            &mut config.initial_class,
+            pack.initial_class,
+        ),
+        (
+            "initial_class_active",
+            &mut config.initial_class_active,
+            pack.initial_class_active,
+        ),
+    ];
+    for (table, icons, pack_icons) in sections {
+        for (rule, icon) in pack_icons {
+            if icons.contains_key(&rule) {
+                continue;
+            }
+            let path = format!("{table}.{rule:?}");
+            if let Some(source) = pack.sources.get(&path) {
+                config.rule_sources.insert(path, source.clone());
+            }
+            icons.insert(rule, icon);
+        }
+    }
+}
+
+fn load_pack(pack: &PackRef) -> Result<IconPackRaw, Box<dyn Error>> {
+    let content = match pack.url.strip_prefix("file://") {
+        Some(path) => fs::read_to_string(path)?,
+        None => fetch_pack(pack)?,
+    };
+    pack.verify(&content)?;
+    Ok(IconPackRaw {
+        sources: locate_rules(&content, pack.url),
+        ..toml::from_str(&content)?
+    })
+}
+
+/// Fetches the pack once, then reads it from the cache, so packs keep
+/// working offline.
+fn fetch_pack(pack: &PackRef) -> Result<String, Box<dyn Error>> {
+    let cache_path = get_cache_path(pack.url)?;
+    if let Ok(content) = fs::read_to_string(&cache_path) {
+        if pack.verify(&content).is_ok() {
+            return Ok(content);
+        }
+    }
+
+    let content = download(pack.url)?;
+    pack.verify(&content)?;
+    fs::write(&cache_path, &content)?;
+    Ok(content)
+}
+
+#[cfg(feature = "remote-packs")]
+fn download(url: &str) -> Result<String, Box<dyn Error>> {
+    let response = ureq::get(url).timeout(Duration::from_secs(10)).call()?;
+    Ok(response.into_string()?)
+}
+
+#[cfg(not(feature = "remote-packs"))]
+fn download(url: &str) -> Result<String, Box<dyn Error>> {
+    Err(format!("unable to fetch {url}, built without the remote-packs feature").into())
+}
+
+fn get_cache_path(url: &str) -> Result<PathBuf, Box<dyn Error>> {
+    let xdg_dirs = xdg::BaseDirectories::with_prefix(BIN_NAME)?;
+    let name = format!("packs/{}.toml", &sha256_hex(url.as_bytes())[..16]);
+    Ok(xdg_dirs.place_cache_file(name)?)
+}
+
+/// SHA-256 of the data, as lowercase hex.
+fn sha256_hex(data: &[u8]) -> String {
+    Sha256::digest(data)
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sha256_hex() {
+        assert_eq!(
+            sha256_hex(b""),
+            "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855"
+        );
+        assert_eq!(
+            sha256_hex(b"abc"),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+        assert_eq!(
+            sha256_hex(b"abcdbcdecdefdefgefghfghighijhijkijkljklmklmnlmnomnopnopq"),
+            "248d6a61d20638b8e5c026930c3e6039a33ce45964ff2167f6ecedd419db06c1"
+        );
+    }
+
+    #[test]
+    fn test_load_and_merge_packs() {
+        let path = "/tmp/hyprland-autoname-workspaces-pack-test.toml";
+        let content = "[class]\nkitty = \"pack term\"\nfoot = \"pack foot\"\n";
+        fs::write(path, content).unwrap();
+
+        let config: ConfigFileRaw = toml::from_str(&format!(
+            "packs = [\"file://{path}#sha256={}\"]\n[class]\nkitty = \"my term\"\n",
+            sha256_hex(content.as_bytes())
+        ))
+        .unwrap();
+        let config = PackLayers::load(config).merged();
+        assert_eq!(config.class["kitty"], "my term");
+        assert_eq!(config.class["foot"], "pack foot");
+        // Only the icons taken from the pack come from it
+        assert_eq!(
+            config.rule_sources[r#"class."foot""#].to_string(),
+            format!("file://{path}:3")
+        );
+        assert!(!config.rule_sources.contains_key(r#"class."kitty""#));
+
+        let pinned = format!("file://{path}#sha256={}", sha256_hex(b"other"));
+        assert!(load_pack(&PackRef::parse(&pinned)).is_err());
+    }
+
+    #[test]
+    fn test_reload_pack_layer() {
+        let path = "/tmp/hyprland-autoname-workspaces-pack-layer-test.toml";
+        fs::write(path, "[class]\nfoot = \"pack foot\"\n").unwrap();
+
+        let base: ConfigFileRaw =
+            toml::from_str(&format!("packs = [\"file://{path}\"]\n")).unwrap();
+        let mut layers = PackLayers::load(base);
+        assert_eq!(layers.merged().class["foot"], "pack foot");
+
+        fs::write(path, "[class]\nfoot = \"new foot\"\n").unwrap();
+        assert!(!layers.reload(Path::new("/tmp/other.toml")));
+        assert_eq!(layers.merged().class["foot"], "pack foot");
+        assert!(layers.reload(Path::new(path)));
+        assert_eq!(layers.merged().class["foot"], "new foot");
+
+        assert_eq!(local_path(&format!("file://{path}")), Some(path.into()));
+        assert_eq!(local_path("https://example.org/pack.toml"), None);
+    }
+}
@@ -0,0 +1,218 @@
+use crate::config::ConfigFileRaw;
+use serde_json::{json, Map, Value};
+
+const SCHEMA_DRAFT: &str = "https://json-schema.org/draft/2020-12/schema";
+
+/// Returns the JSON Schema of the config file, for editors autocompletion
+/// and validation.
+///
+/// Types and defaults are inferred from the default config, only the
+/// fields whose default can't tell their type are described by hand.
+pub fn json_schema() -> Value {
+    let defaults: ConfigFileRaw = toml::from_str("").expect("default config");
+    let defaults = serde_json::to_value(defaults).expect("serializable config");
+
+    let mut schema = infer_schema("", &defaults);
+    schema["$schema"] = json!(SCHEMA_DRAFT);
+    schema["title"] = json!("hyprland-autoname-workspaces config");
+    schema["properties"]["packs"] = json!({
+        "type": "array",
+        "items": { "type": "string" },
+        "description": "Icon packs, file:// or https:// URLs, optionally pinned with #sha256=<hex>",
+    });
+
+    // Former names, still accepted
+    let aliases = [
+        ("icons", "class"),
+        ("active_icons", "class_active"),
+        ("icons_active", "class_active"),
+        ("title_icons", "title_in_class"),
+        ("title_active_icons", "title_in_class_active"),
+    ];
+    for (alias, field) in aliases {
+        schema["properties"][alias] = schema["properties"][field].clone();
+    }
+    schema
+}
+
+/// Describes the fields whose default is empty or null.
+fn known_schema(path: &str) -> Option<Value> {
+    let icons = json!({
+        "type": "object",
+        "additionalProperties": { "type": "string" },
+    });
+    let title_icons = json!({
+        "type": "object",
+        "additionalProperties": icons,
+    });
+    let schema = match path {
+        "class"
+        | "class_active"
+        | "initial_class"
+        | "initial_class_active"
+        | "class_floating"
+        | "class_floating_active"
+        | "class_xwayland"
+        | "workspaces_name"
+        | "autospawn"
+        | "exclude"
+        | "fallback"
+        | "modified_markers"
+        | "client_format_per_class"
+        | "class_colors"
+        | "format.counter_glyphs" => icons,
+        "title_in_class"
+        | "title_in_class_active"
+        | "title_in_initial_class"
+        | "title_in_initial_class_active"
+        | "initial_title_in_class"
+        | "initial_title_in_class_active"
+        | "initial_title_in_initial_class"
+        | "initial_title_in_initial_class_active"
+        | "title_history_in_class"
+        | "title_history_in_class_active" => title_icons,
+        "min_count_per_class" | "format.max_clients_per_monitor" => json!({
+            "type": "object",
+            "additionalProperties": { "type": "integer", "minimum": 0 },
+        }),
+        "format.max_clients" | "format.pad_clients_to" | "format.total_max_width" => {
+            json!({ "type": "integer", "minimum": 0 })
+        }
+        "format.dedup_by" => json!({
+            "type": "array",
+            "items": { "enum": ["icon", "class", "title", "fullscreen", "active"] },
+            "default": ["icon", "active", "fullscreen"],
+        }),
+        "format.active_fallback" => json!({
+            "enum": ["wrap", "use_inactive", "default"],
+            "default": "wrap",
+        }),
+        "format.dominant_by" => json!({
+            "enum": ["area", "focused"],
+            "default": "area",
+        }),
+        "format.client_sort" => json!({
+            "enum": ["default", "position", "alpha"],
+            "default": "default",
+        }),
+        "behavior.on_conflict" => json!({
+            "enum": ["overwrite", "skip", "warn"],
+            "default": "overwrite",
+        }),
+        "behavior.backend" => json!({
+            "enum": ["hyprland", "wlr-foreign-toplevel"],
+        }),
+        "behavior.audit_log"
+        | "behavior.rename_cmd"
+        | "behavior.mirror_env"
+        | "behavior.mirror_file"
+        | "format.special_prefix"
+        | "format.workspace_special"
+        | "format.workspace_special_empty"
+        | "format.workspace_persistent_empty"
+        | "format.workspace_empty_active"
+        | "format.workspace_transition"
+        | "format.client_pinned" => {
+            json!({ "type": "string" })
+        }
+        "singleton"
+        | "no_dedup"
+        | "matching.strip_class_prefixes"
+        | "matching.continue_rules"
+        | "behavior.monitors" => {
+            json!({
+                "type": "array",
+                "items": { "type": "string" },
+                "default": [],
+            })
+        }
+        _ => return None,
+    };
+    Some(schema)
+}
+
+fn infer_schema(path: &str, value: &Value) -> Value {
+    if let Some(schema) = known_schema(path) {
+        return schema;
+    }
+
+    match value {
+        Value::Object(fields) => {
+            let properties: Map<String, Value> = fields
+                .iter()
+                .map(|(key, value)| {
+                    let path = match path {
+                        "" => key.to_string(),
+                        _ => format!("{path}.{key}"),
+                    };
+                    (key.to_string(), infer_schema(&path, value))
+                })
+                .collect();
+            json!({
+                "type": "object",
+                "properties": properties,
+                "additionalProperties": false,
+            })
+        }
+        Value::Array(items) => {
+            let item_schema = items
+                .first()
+                .map_or(json!({}), |item| infer_schema("", item));
+            json!({ "type": "array", "items": item_schema, "default": value })
+        }
+        Value::String(_) => json!({ "type": "string", "default": value }),
+        Value::Bool(_) => json!({ "type": "boolean", "default": value }),
+        Value::Number(n) if n.is_f64() => json!({ "type": "number", "default": value }),
+        Value::Number(_) => json!({ "type": "integer", "default": value }),
+        Value::Null => json!({}),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_json_schema() {
+        let schema = json_schema();
+        let properties = &schema["properties"];
+
+        assert_eq!(schema["$schema"], SCHEMA_DRAFT);
+        assert_eq!(
+            properties["class"]["additionalProperties"]["type"],
+            "string"
+        );
+        assert_eq!(
+            properties["title_in_class"]["additionalProperties"]["additionalProperties"]["type"],
+            "string"
+        );
+        assert_eq!(
+            properties["format"]["properties"]["dedup"]["type"],
+            "boolean"
+        );
+        assert_eq!(
+            properties["format"]["properties"]["workspace"]["default"],
+            "{id}:{delim}{clients}"
+        );
+        assert_eq!(
+            properties["behavior"]["properties"]["audit_log_max_size"]["type"],
+            "integer"
+        );
+        assert_eq!(properties["packs"]["type"], "array");
+        assert_eq!(properties["icons"], properties["class"]);
+    }
+
+    #[test]
+    fn test_json_schema_describes_every_field() {
+        fn assert_typed(path: &str, schema: &Value) {
+            let has_type = schema.get("type").is_some() || schema.get("enum").is_some();
+            assert!(has_type, "no type for {path}");
+            if let Some(Value::Object(properties)) = schema.get("properties") {
+                for (key, property) in properties {
+                    assert_typed(&format!("{path}.{key}"), property);
+                }
+            }
+        }
+        assert_typed("config", &json_schema());
+    }
+}
@@ -0,0 +1,100 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt;
+
+/// Where a rule is defined: the config file or a pack, and the line.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct RuleSource {
+    pub file: String,
+    pub line: usize,
+}
+
+impl fmt::Display for RuleSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", self.file, self.line)
+    }
+}
+
+/// Finds the line of every key of the TOML text, by its path in the config,
+/// e.g. `class."(?i)kitty"` or `title_in_class."kitty"."GitHub"`, the way
+/// invalid regexes are reported. Values spanning several lines, and inline
+/// tables, are not located.
+pub fn locate_rules(text: &str, file: &str) -> HashMap<String, RuleSource> {
+    let mut sources = HashMap::new();
+    let mut table = String::new();
+    for (index, line) in text.lines().enumerate() {
+        let trimmed = line.trim();
+        if trimmed.starts_with("[[") {
+            table.clear();
+            continue;
+        }
+        let Ok(parsed) = toml::from_str::<toml::Table>(trimmed) else {
+            continue;
+        };
+        let Some(keys) = key_path(&parsed) else {
+            continue;
+        };
+        let quoted = |keys: &[&String]| {
+            keys.iter()
+                .map(|key| format!(".{key:?}"))
+                .collect::<String>()
+        };
+        if trimmed.starts_with('[') {
+            table = format!("{}{}", keys[0], quoted(&keys[1..]));
+        } else if !table.is_empty() {
+            let source = RuleSource {
+                file: file.to_string(),
+                line: index + 1,
+            };
+            sources.insert(format!("{table}{}", quoted(&keys)), source);
+        }
+    }
+    sources
+}
+
+/// Returns the keys of the single key of the table, down its nested
+/// tables, e.g. `title_in_class`, `kitty` for `[title_in_class.kitty]`.
+fn key_path(table: &toml::Table) -> Option<Vec<&String>> {
+    let mut keys = Vec::new();
+    let mut table = table;
+    loop {
+        let [(key, value)] = table.iter().collect::<Vec<_>>()[..] else {
+            return None;
+        };
+        keys.push(key);
+        match value.as_table() {
+            Some(nested) if nested.len() == 1 => table = nested,
+            _ => return Some(keys),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_locate_rules() {
+        let sources = locate_rules(
+            "version = \"1.1.15\"\n\
+             [class] # icons\n\
+             \"(?i)kitty\" = \"term\"\n\
+             firefox = \"web\"\n\
+             \n\
+             [title_in_class.\"(?i)firefox\"]\n\
+             GitHub = \"gh\"\n\
+             [[clients]]\n\
+             class = \"kitty\"",
+            "config.toml",
+        );
+        let line = |path: &str| sources.get(path).map(|source| source.to_string());
+        assert_eq!(line(r#"class."(?i)kitty""#).unwrap(), "config.toml:3");
+        assert_eq!(line(r#"class."firefox""#).unwrap(), "config.toml:4");
+        assert_eq!(
+            line(r#"title_in_class."(?i)firefox"."GitHub""#).unwrap(),
+            "config.toml:7"
+        );
+        assert_eq!(line("version"), None);
+        assert_eq!(sources.len(), 3);
+    }
+}
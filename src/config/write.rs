@@ -0,0 +1,144 @@
+use std::error::Error;
+use std::fs::{self, File};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Replaces the config file atomically: the content is written to a
+/// temporary file next to it, then renamed over it, so a crash mid-write
+/// leaves the previous config intact. The previous config, if any, is
+/// first copied to a timestamped backup, whose path is returned.
+///
+/// A symlinked config is written through the link, which is kept.
+pub fn write_config(path: &Path, content: &str) -> Result<Option<PathBuf>, Box<dyn Error>> {
+    let path = fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+
+    let backup = match path.exists() {
+        true => {
+            let backup = backup_path(&path, SystemTime::now());
+            fs::copy(&path, &backup)?;
+            Some(backup)
+        }
+        false => None,
+    };
+
+    let tmp_path = with_suffix(&path, ".tmp");
+    let written = File::create(&tmp_path).and_then(|mut file| {
+        file.write_all(content.as_bytes())?;
+        file.sync_all()
+    });
+    if let Err(e) = written.and_then(|_| fs::rename(&tmp_path, &path)) {
+        _ = fs::remove_file(&tmp_path);
+        return Err(e.into());
+    }
+    Ok(backup)
+}
+
+/// Sets the icon of the pattern in the `[class]` table of the config file,
+/// keeping its comments and order, and returns the backup path.
+#[cfg_attr(not(feature = "tui"), allow(dead_code))]
+pub fn add_class_rule(
+    path: &Path,
+    pattern: &str,
+    icon: &str,
+) -> Result<Option<PathBuf>, Box<dyn Error>> {
+    let content = fs::read_to_string(path)?;
+    write_config(path, &with_class_rule(&content, pattern, icon))
+}
+
+/// Replaces the line of the pattern in the `[class]` table, or inserts it
+/// first in the table, which is appended if missing.
+#[cfg_attr(not(feature = "tui"), allow(dead_code))]
+fn with_class_rule(content: &str, pattern: &str, icon: &str) -> String {
+    let rule = format!(
+        "{} = {}",
+        toml::Value::String(pattern.to_string()),
+        toml::Value::String(icon.to_string())
+    );
+    let mut lines: Vec<&str> = content.lines().collect();
+    let Some(header) = lines.iter().position(|line| line.trim() == "[class]") else {
+        let separator = match content.is_empty() || content.ends_with("\n\n") {
+            true => "",
+            false if content.ends_with('\n') => "\n",
+            false => "\n\n",
+        };
+        return format!("{content}{separator}[class]\n{rule}\n");
+    };
+    let table_end = lines[header + 1..]
+        .iter()
+        .position(|line| line.trim_start().starts_with('['))
+        .map_or(lines.len(), |end| header + 1 + end);
+    let existing = (header + 1..table_end).find(|&i| {
+        toml::from_str::<toml::Table>(lines[i]).is_ok_and(|line| line.contains_key(pattern))
+    });
+    match existing {
+        Some(i) => lines[i] = &rule,
+        None => lines.insert(header + 1, &rule),
+    }
+    let mut content_with_rule = lines.join("\n");
+    if content.ends_with('\n') {
+        content_with_rule.push('\n');
+    }
+    content_with_rule
+}
+
+/// Returns `config.toml.<seconds since epoch>.bak`, next to the config.
+fn backup_path(path: &Path, now: SystemTime) -> PathBuf {
+    let secs = now.duration_since(UNIX_EPOCH).unwrap_or_default().as_secs();
+    with_suffix(path, &format!(".{secs}.bak"))
+}
+
+fn with_suffix(path: &Path, suffix: &str) -> PathBuf {
+    let mut file_name = path.file_name().unwrap_or_default().to_os_string();
+    file_name.push(suffix);
+    path.with_file_name(file_name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn test_backup_path() {
+        let now = UNIX_EPOCH + Duration::from_secs(1700000000);
+        assert_eq!(
+            backup_path(Path::new("/home/me/config.toml"), now),
+            PathBuf::from("/home/me/config.toml.1700000000.bak")
+        );
+    }
+
+    #[test]
+    fn test_with_class_rule() {
+        let config =
+            "version = \"1.1.0\"\n\n[class]\n# terminals\nkitty = \"k\"\n\n[title_in_class]\n";
+        assert_eq!(
+            with_class_rule(config, "^mpv$", "video"),
+            "version = \"1.1.0\"\n\n[class]\n\"^mpv$\" = \"video\"\n# terminals\nkitty = \"k\"\n\n[title_in_class]\n"
+        );
+        assert_eq!(
+            with_class_rule(config, "kitty", "term"),
+            "version = \"1.1.0\"\n\n[class]\n# terminals\n\"kitty\" = \"term\"\n\n[title_in_class]\n"
+        );
+        assert_eq!(
+            with_class_rule("version = \"1.1.0\"\n", "^mpv$", "video"),
+            "version = \"1.1.0\"\n\n[class]\n\"^mpv$\" = \"video\"\n"
+        );
+    }
+
+    #[test]
+    fn test_write_config() {
+        let dir = std::env::temp_dir().join("hyprland-autoname-workspaces-test-write");
+        _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("config.toml");
+
+        assert_eq!(write_config(&path, "version = \"1.0.0\"").unwrap(), None);
+        assert_eq!(fs::read_to_string(&path).unwrap(), "version = \"1.0.0\"");
+
+        let backup = write_config(&path, "version = \"1.1.0\"").unwrap().unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "version = \"1.1.0\"");
+        assert_eq!(fs::read_to_string(backup).unwrap(), "version = \"1.0.0\"");
+        assert!(!with_suffix(&path, ".tmp").exists());
+    }
+}
@@ -0,0 +1,263 @@
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::sync::Mutex;
+use std::time::Duration;
+
+const BIN_NAME: &str = env!("CARGO_BIN_NAME");
+/// How long a client may take to send its command.
+const READ_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// Commands accepted on the control socket, one per line.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ControlCommand {
+    FlushCache,
+    ToggleDedup,
+    SetClientIcon {
+        address: String,
+        icon: String,
+    },
+    LockWorkspace(i32),
+    UnlockWorkspace(i32),
+    Status,
+    /// Keeps the connection open to stream the renames, as JSON lines.
+    Subscribe,
+    /// Pauses the renames while the session is idle, e.g. from hypridle.
+    Idle,
+    /// Renames every workspace once, and follows the events again.
+    Resume,
+}
+
+impl FromStr for ControlCommand {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let args: Vec<&str> = s.split_whitespace().collect();
+        match args.as_slice() {
+            ["flush-cache"] => Ok(ControlCommand::FlushCache),
+            ["toggle-dedup"] => Ok(ControlCommand::ToggleDedup),
+            ["status"] => Ok(ControlCommand::Status),
+            ["subscribe"] => Ok(ControlCommand::Subscribe),
+            ["idle"] => Ok(ControlCommand::Idle),
+            ["resume"] => Ok(ControlCommand::Resume),
+            ["set-client-icon", address, icon @ ..] if !icon.is_empty() => {
+                Ok(ControlCommand::SetClientIcon {
+                    address: address.to_string(),
+                    icon: icon.join(" "),
+                })
+            }
+            ["lock-workspace", id] => Ok(ControlCommand::LockWorkspace(parse_id(id)?)),
+            ["unlock-workspace", id] => Ok(ControlCommand::UnlockWorkspace(parse_id(id)?)),
+            [] => Err("empty command".to_string()),
+            _ => Err(format!("unknown command '{s}'")),
+        }
+    }
+}
+
+fn parse_id(id: &str) -> Result<i32, String> {
+    id.parse()
+        .map_err(|_| format!("invalid workspace id '{id}'"))
+}
+
+impl fmt::Display for ControlCommand {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ControlCommand::FlushCache => write!(f, "flush-cache"),
+            ControlCommand::ToggleDedup => write!(f, "toggle-dedup"),
+            ControlCommand::SetClientIcon { address, icon } => {
+                write!(f, "set-client-icon {address} {icon}")
+            }
+            ControlCommand::LockWorkspace(id) => write!(f, "lock-workspace {id}"),
+            ControlCommand::UnlockWorkspace(id) => write!(f, "unlock-workspace {id}"),
+            ControlCommand::Status => write!(f, "status"),
+            ControlCommand::Subscribe => write!(f, "subscribe"),
+            ControlCommand::Idle => write!(f, "idle"),
+            ControlCommand::Resume => write!(f, "resume"),
+        }
+    }
+}
+
+pub fn get_socket_path() -> Result<PathBuf, Box<dyn Error>> {
+    let xdg_dirs = xdg::BaseDirectories::with_prefix(BIN_NAME)?;
+    Ok(xdg_dirs.place_runtime_file("control.sock")?)
+}
+
+/// Sends a command to the running daemon and returns its answer, which
+/// may span several lines.
+pub fn send_command(command: &str) -> Result<String, Box<dyn Error>> {
+    let mut stream = UnixStream::connect(get_socket_path()?)
+        .map_err(|e| format!("Unable to reach the daemon, is it running? {e}"))?;
+    writeln!(stream, "{command}")?;
+
+    let mut answer = String::new();
+    stream.read_to_string(&mut answer)?;
+    Ok(answer.trim_end().to_string())
+}
+
+/// Subscribes to the renames of the running daemon, and calls `on_event`
+/// with each of them, until the daemon stops.
+pub fn subscribe<F>(mut on_event: F) -> Result<(), Box<dyn Error>>
+where
+    F: FnMut(&str),
+{
+    let mut stream = UnixStream::connect(get_socket_path()?)
+        .map_err(|e| format!("Unable to reach the daemon, is it running? {e}"))?;
+    writeln!(stream, "{}", ControlCommand::Subscribe)?;
+
+    for line in BufReader::new(stream).lines() {
+        on_event(&line?);
+    }
+    Ok(())
+}
+
+/// Connections subscribed to the renames.
+#[derive(Debug, Default)]
+pub struct Subscribers {
+    streams: Mutex<Vec<UnixStream>>,
+}
+
+impl Subscribers {
+    pub fn add(&self, stream: UnixStream) {
+        // A stuck subscriber must not block the renames for long
+        _ = stream.set_write_timeout(Some(Duration::from_millis(100)));
+        if let Ok(mut streams) = self.streams.lock() {
+            streams.push(stream);
+        }
+    }
+
+    /// Sends the line to every subscriber, and forgets the disconnected ones.
+    pub fn publish(&self, line: &str) {
+        if let Ok(mut streams) = self.streams.lock() {
+            streams.retain(|mut stream| writeln!(stream, "{line}").is_ok());
+        }
+    }
+}
+
+/// Listens on the control socket and answers each command with the handler.
+/// Subscriptions are handed over to the subscribers.
+pub fn listen<F>(handler: F, subscribers: &Subscribers) -> Result<(), Box<dyn Error>>
+where
+    F: Fn(ControlCommand) -> Result<String, String>,
+{
+    let socket_path = get_socket_path()?;
+    // We are the single instance, so any existing socket is stale.
+    _ = fs::remove_file(&socket_path);
+    let listener = UnixListener::bind(&socket_path)?;
+
+    for stream in listener.incoming().flatten() {
+        // Commands are answered one at a time, so a silent client must not
+        // block the socket
+        _ = stream.set_read_timeout(Some(READ_TIMEOUT));
+        let mut line = String::new();
+        let mut reader = BufReader::new(&stream);
+        if reader.read_line(&mut line).is_err() {
+            continue;
+        }
+
+        let answer = match line.trim().parse::<ControlCommand>() {
+            Ok(ControlCommand::Subscribe) => {
+                subscribers.add(stream);
+                continue;
+            }
+            Ok(command) => handler(command).unwrap_or_else(|e| format!("error: {e}")),
+            Err(e) => format!("error: {e}"),
+        };
+        _ = writeln!(&stream, "{answer}");
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_control_command() {
+        assert_eq!(
+            "flush-cache".parse::<ControlCommand>(),
+            Ok(ControlCommand::FlushCache)
+        );
+        assert_eq!(
+            " flush-cache \n".parse::<ControlCommand>(),
+            Ok(ControlCommand::FlushCache)
+        );
+        assert!("".parse::<ControlCommand>().is_err());
+        assert!("flush".parse::<ControlCommand>().is_err());
+        assert_eq!(
+            "toggle-dedup".parse::<ControlCommand>(),
+            Ok(ControlCommand::ToggleDedup)
+        );
+        assert_eq!(ControlCommand::FlushCache.to_string(), "flush-cache");
+        assert_eq!(ControlCommand::ToggleDedup.to_string(), "toggle-dedup");
+        assert_eq!(
+            "status".parse::<ControlCommand>(),
+            Ok(ControlCommand::Status)
+        );
+
+        let set_client_icon = ControlCommand::SetClientIcon {
+            address: "0x55d2f0".to_string(),
+            icon: "my term".to_string(),
+        };
+        assert_eq!(
+            "set-client-icon 0x55d2f0 my term".parse::<ControlCommand>(),
+            Ok(set_client_icon.clone())
+        );
+        assert_eq!(
+            set_client_icon.to_string(),
+            "set-client-icon 0x55d2f0 my term"
+        );
+        assert!("set-client-icon 0x55d2f0"
+            .parse::<ControlCommand>()
+            .is_err());
+
+        assert_eq!(
+            "lock-workspace 3".parse::<ControlCommand>(),
+            Ok(ControlCommand::LockWorkspace(3))
+        );
+        assert_eq!(
+            "unlock-workspace 3".parse::<ControlCommand>(),
+            Ok(ControlCommand::UnlockWorkspace(3))
+        );
+        assert_eq!(
+            ControlCommand::LockWorkspace(3).to_string(),
+            "lock-workspace 3"
+        );
+        assert!("lock-workspace three".parse::<ControlCommand>().is_err());
+        assert_eq!(
+            "subscribe".parse::<ControlCommand>(),
+            Ok(ControlCommand::Subscribe)
+        );
+        assert_eq!("idle".parse::<ControlCommand>(), Ok(ControlCommand::Idle));
+        assert_eq!(
+            "resume".parse::<ControlCommand>(),
+            Ok(ControlCommand::Resume)
+        );
+        assert_eq!(ControlCommand::Resume.to_string(), "resume");
+    }
+
+    #[test]
+    fn test_subscribers() {
+        let subscribers = Subscribers::default();
+        let (first, first_peer) = UnixStream::pair().unwrap();
+        let (second, second_peer) = UnixStream::pair().unwrap();
+        subscribers.add(first);
+        subscribers.add(second);
+
+        subscribers.publish(r#"{"workspace":1}"#);
+        drop(second_peer);
+        subscribers.publish(r#"{"workspace":2}"#);
+        assert_eq!(subscribers.streams.lock().unwrap().len(), 1);
+
+        drop(subscribers);
+        let lines: Vec<String> = BufReader::new(first_peer)
+            .lines()
+            .map(Result::unwrap)
+            .collect();
+        assert_eq!(lines, [r#"{"workspace":1}"#, r#"{"workspace":2}"#]);
+    }
+}
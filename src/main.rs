@@ -1,9 +1,14 @@
 mod config;
+mod control;
 mod params;
 mod renamer;
+mod state;
+#[cfg(feature = "tui")]
+mod tui;
 
 use crate::config::Config;
-use crate::params::Args;
+use crate::control::ControlCommand;
+use crate::params::{Args, BackendKind, Command, ExportCommand, LegendFormat};
 use crate::renamer::*;
 
 use clap::Parser;
@@ -11,12 +16,94 @@ use config::get_config_path;
 use signal_hook::consts::{SIGINT, SIGTERM};
 use signal_hook::iterator::Signals;
 use single_instance::SingleInstance;
+use std::sync::Arc;
 use std::{process, thread};
 
 fn main() {
     let args = Args::parse();
-    let cfg_path = get_config_path(&args.config).expect("Can't get config path");
-    let cfg = Config::new(cfg_path, args.dump, args.migrate_config).expect("Unable to read config");
+
+    let command = match &args.command {
+        Some(Command::Ctl { command }) => Some(command.join(" ")),
+        Some(Command::Status) => Some(ControlCommand::Status.to_string()),
+        Some(Command::RenderFixture { path }) => return render_fixture(path),
+        Some(Command::Render { clients }) => return render_clients(clients, &args.config),
+        Some(Command::Export {
+            command: ExportCommand::Legend { format },
+        }) => return export_legend(*format, &args.config),
+        #[cfg(feature = "tui")]
+        Some(Command::Tui) => {
+            let result = get_config_path(&args.config).and_then(|path| tui::run(&path));
+            if let Err(e) = result {
+                eprintln!("{e}");
+                process::exit(1);
+            }
+            return;
+        }
+        None => None,
+    };
+    if command == Some(ControlCommand::Subscribe.to_string()) {
+        if let Err(e) = control::subscribe(|event| println!("{event}")) {
+            eprintln!("{e}");
+            process::exit(1);
+        }
+        return;
+    }
+    if let Some(command) = command {
+        match control::send_command(&command) {
+            Ok(answer) => println!("{answer}"),
+            Err(e) => {
+                eprintln!("{e}");
+                process::exit(1);
+            }
+        }
+        return;
+    }
+
+    if let Some(pattern) = &args.validate_regex {
+        match validate_regex(pattern) {
+            Ok(report) => println!("{report}"),
+            Err(e) => {
+                eprintln!("{e}");
+                process::exit(1);
+            }
+        }
+        return;
+    }
+
+    if args.schema {
+        let schema = config::schema::json_schema();
+        println!("{}", serde_json::to_string_pretty(&schema).unwrap());
+        return;
+    }
+
+    if args.print_default_config {
+        match config::ConfigFileRaw::default_toml() {
+            Ok(default_config) => print!("{default_config}"),
+            Err(e) => {
+                eprintln!("{e}");
+                process::exit(1);
+            }
+        }
+        return;
+    }
+
+    if args.check_config {
+        let issues = get_config_path(&args.config).and_then(|path| config::check_config(&path));
+        match issues {
+            Ok(issues) if issues.is_empty() => println!("No issue found"),
+            Ok(issues) => {
+                eprintln!("{}", issues.join("\n"));
+                process::exit(1);
+            }
+            Err(e) => {
+                eprintln!("{e}");
+                process::exit(1);
+            }
+        }
+        return;
+    }
+
+    let (cfg, pack_layers) = config::read_daemon_config(&args).expect("Unable to read config");
 
     let instance = SingleInstance::new("Hyprland-autoname-workspaces").unwrap();
     if !instance.is_single() {
@@ -24,10 +111,37 @@ fn main() {
         process::exit(1);
     }
 
+    let shadow_cfg = args.shadow.as_ref().map(|path| {
+        config::read_config_file(Some(path.into()), false, false)
+            .expect("Unable to read shadow config")
+    });
+
+    let state = state::StateDir::new(args.state_dir.as_ref().map(Into::into));
+    if let Err(e) = state.write_pid_file() {
+        eprintln!("Unable to write pid file: {e}");
+    }
+
     // Init
-    let renamer = Renamer::new(cfg.clone(), args);
+    let backend = args
+        .backend
+        .or(cfg.config.behavior.backend)
+        .unwrap_or_default();
+    let toplevels =
+        (backend == BackendKind::WlrForeignToplevel).then(|| Arc::new(ToplevelBackend::default()));
+    let renamer = match &toplevels {
+        Some(toplevels) => Renamer::with_backend(cfg, args, toplevels.clone()),
+        None => Renamer::with_backend(cfg, args, Arc::new(HyprlandBackend::detect())),
+    };
     renamer
-        .rename_workspace()
+        .set_pack_layers(pack_layers)
+        .expect("Unable to set pack layers");
+    if let Some(shadow_cfg) = shadow_cfg {
+        renamer
+            .set_shadow_config(shadow_cfg)
+            .expect("Unable to set shadow config");
+    }
+    renamer
+        .rename_workspace("startup")
         .expect("App can't rename workspaces on start");
 
     // Handle unix signals
@@ -36,20 +150,88 @@ fn main() {
 
     thread::spawn(move || {
         if signals.forever().next().is_some() {
-            match final_renamer.reset_workspaces(cfg.config) {
+            match final_renamer.shutdown() {
                 Err(_) => println!("Workspaces name can't be cleared"),
                 Ok(_) => println!("Workspaces name cleared, bye"),
             };
+            _ = state.remove_pid_file();
             process::exit(0);
         }
     });
 
-    let config_renamer = renamer.clone();
+    let control_renamer = renamer.clone();
     thread::spawn(move || {
-        config_renamer
-            .watch_config_changes(cfg.cfg_path)
-            .expect("Unable to watch for config changes")
+        let handler = |command| control_renamer.handle_command(command);
+        if let Err(e) = control::listen(handler, control_renamer.subscribers()) {
+            eprintln!("Unable to listen on control socket: {e}");
+        }
     });
 
-    renamer.start_listeners()
+    match toplevels {
+        Some(toplevels) => renamer.start_toplevel_listener(&toplevels),
+        None => renamer.start_listeners(),
+    }
+}
+
+/// Prints the icons of the rules of the config, merged with its packs.
+fn export_legend(format: LegendFormat, config: &Option<String>) {
+    let legend = get_config_path(config).and_then(|cfg_path| {
+        let config = Config::new(cfg_path, false, false)?;
+        config::render_legend(&config.config, format)
+    });
+    match legend {
+        Ok(legend) => println!("{}", legend.trim_end()),
+        Err(e) => {
+            eprintln!("{e}");
+            process::exit(1);
+        }
+    }
+}
+
+/// Prints the names the config gives to the windows of a clients dump.
+fn render_clients(clients_path: &str, config: &Option<String>) {
+    let names = get_config_path(config).and_then(|cfg_path| {
+        let config = std::fs::read_to_string(cfg_path)?;
+        let clients = std::fs::read_to_string(clients_path)?;
+        render_clients_dump(&config, &clients)
+    });
+    match names {
+        Ok(names) => {
+            for (id, name) in names {
+                println!("{id}: {name}");
+            }
+        }
+        Err(e) => {
+            eprintln!("{e}");
+            process::exit(1);
+        }
+    }
+}
+
+/// Prints the names of the fixture, and exits with an error if they are
+/// not the expected ones.
+fn render_fixture(path: &str) {
+    let names = Fixture::from_file(path.as_ref()).and_then(|fixture| {
+        let names = fixture.render()?;
+        let mismatches = fixture.mismatches(&names);
+        Ok((fixture.description, mismatches, names))
+    });
+    match names {
+        Ok((description, mismatches, names)) => {
+            if !description.is_empty() {
+                println!("# {description}");
+            }
+            for (id, name) in names {
+                println!("{id}: {name}");
+            }
+            if !mismatches.is_empty() {
+                eprintln!("{}", mismatches.join("\n"));
+                process::exit(1);
+            }
+        }
+        Err(e) => {
+            eprintln!("{e}");
+            process::exit(1);
+        }
+    }
 }
@@ -1,16 +1,181 @@
-use clap::Parser;
+use clap::{Parser, Subcommand, ValueEnum};
+use serde::{Deserialize, Serialize};
 
-#[derive(Parser)]
+#[derive(Parser, Default)]
 #[command(author, version, about, long_about = None)]
 pub struct Args {
     #[arg(short, long)]
     pub verbose: bool,
     #[arg(short, long)]
     pub debug: bool,
+    /// Print debug output of these modules only, e.g.
+    /// `renamer::formatter,config`, implies --debug
+    #[arg(long, value_name = "MODULES", value_parser = parse_debug_filter)]
+    pub debug_filter: Option<DebugFilter>,
     #[arg(long)]
     pub dump: bool,
+    /// Print the JSON Schema of the config file, for editors
+    #[arg(long)]
+    pub schema: bool,
+    /// Print the default config, with every option, and exit
+    #[arg(long)]
+    pub print_default_config: bool,
     #[arg(long)]
     pub migrate_config: bool,
+    /// Report the icons which would render badly in waybar, and exit
+    #[arg(long)]
+    pub check_config: bool,
+    /// Show the open windows the pattern matches, on class and title, with
+    /// the captures, to try a rule
+    #[arg(long, value_name = "PATTERN")]
+    pub validate_regex: Option<String>,
     #[arg(short, long, default_value = None)]
     pub config: Option<String>,
+    /// Read the whole config from stdin, it is not watched for changes
+    #[arg(long, conflicts_with_all = ["config", "config_inline", "migrate_config"])]
+    pub config_from_stdin: bool,
+    /// Use this TOML string as config, it is not watched for changes
+    #[arg(long, value_name = "TOML", conflicts_with_all = ["config", "migrate_config"])]
+    pub config_inline: Option<String>,
+    /// Log the names a candidate config would produce, without using it
+    #[arg(long, value_name = "CONFIG")]
+    pub shadow: Option<String>,
+    /// Directory of the state files, defaults to $XDG_STATE_HOME/hyprland-autoname-workspaces
+    #[arg(long, value_name = "DIR")]
+    pub state_dir: Option<String>,
+    /// Where the windows come from, wlr-foreign-toplevel (experimental) prints
+    /// waybar JSON lines instead of renaming workspaces, defaults to
+    /// behavior.backend of the config
+    #[arg(long, value_enum)]
+    pub backend: Option<BackendKind>,
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+impl Args {
+    /// Tells if the debug output of the module, given as `module_path!()`,
+    /// is enabled.
+    pub fn debug(&self, module_path: &str) -> bool {
+        let module = module_path
+            .split_once("::")
+            .map_or("", |(_, module)| module);
+        match &self.debug_filter {
+            Some(filter) => filter.enabled(module),
+            None => self.debug,
+        }
+    }
+}
+
+/// The modules whose debug output is shown, all of them when empty. A
+/// module includes its submodules.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct DebugFilter {
+    modules: Vec<String>,
+}
+
+impl DebugFilter {
+    fn enabled(&self, module: &str) -> bool {
+        self.modules.is_empty()
+            || self.modules.iter().any(|filter| {
+                module
+                    .strip_prefix(filter.as_str())
+                    .is_some_and(|rest| rest.is_empty() || rest.starts_with("::"))
+            })
+    }
+}
+
+fn parse_debug_filter(modules: &str) -> Result<DebugFilter, String> {
+    let modules = modules
+        .split(',')
+        .map(str::trim)
+        .filter(|module| !module.is_empty())
+        .map(String::from)
+        .collect();
+    Ok(DebugFilter { modules })
+}
+
+#[derive(ValueEnum, Deserialize, Serialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum BackendKind {
+    #[default]
+    Hyprland,
+    WlrForeignToplevel,
+}
+
+#[derive(Subcommand)]
+pub enum Command {
+    /// Send a command to the running daemon (flush-cache, toggle-dedup, set-client-icon <address> <icon>, lock-workspace <id>, unlock-workspace <id>, subscribe, idle, resume)
+    Ctl {
+        #[arg(required = true, trailing_var_arg = true)]
+        command: Vec<String>,
+    },
+    /// Show the health of the running daemon
+    Status,
+    /// Print the names of a fixture file (config, windows and expected
+    /// names), e.g. to reproduce a bug report, fails if not as expected
+    RenderFixture {
+        #[arg(value_name = "FIXTURE")]
+        path: String,
+    },
+    /// Print the names the config (-c) gives to the windows of a
+    /// `hyprctl clients -j` dump, e.g. attached to a bug report
+    Render {
+        #[arg(long, value_name = "CLIENTS")]
+        clients: String,
+    },
+    /// Browse the open windows with the rule matching each, and give a
+    /// window class an icon, written to the config (-c)
+    #[cfg(feature = "tui")]
+    Tui,
+    /// Export data generated from the config (-c), merged with its packs
+    Export {
+        #[command(subcommand)]
+        command: ExportCommand,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum ExportCommand {
+    /// Print the icon of every class and title rule, e.g. for a cheat sheet
+    Legend {
+        #[arg(long, value_enum, default_value_t)]
+        format: LegendFormat,
+    },
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum LegendFormat {
+    /// A Markdown table
+    #[default]
+    Md,
+    Json,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_debug_filter() {
+        let args = Args::parse_from(["hyprland-autoname-workspaces"]);
+        assert!(!args.debug("hyprland_autoname_workspaces::renamer"));
+
+        let args = Args::parse_from(["hyprland-autoname-workspaces", "--debug"]);
+        assert!(args.debug("hyprland_autoname_workspaces::renamer::formatter"));
+
+        let args = Args::parse_from([
+            "hyprland-autoname-workspaces",
+            "--debug-filter",
+            "renamer::formatter,config",
+        ]);
+        assert!(args.debug("hyprland_autoname_workspaces::renamer::formatter"));
+        assert!(args.debug("hyprland_autoname_workspaces::config::legend"));
+        assert!(!args.debug("hyprland_autoname_workspaces::renamer"));
+        assert!(!args.debug("hyprland_autoname_workspaces::configuration"));
+
+        // Subcommands are never taken for modules
+        let args = Args::parse_from(["hyprland-autoname-workspaces", "-d", "status"]);
+        assert!(args.debug("hyprland_autoname_workspaces::renamer"));
+        assert!(matches!(args.command, Some(Command::Status)));
+    }
 }
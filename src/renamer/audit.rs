@@ -0,0 +1,125 @@
+use crate::config::ConfigBehaviorRaw;
+use crate::state::StateDir;
+use std::error::Error;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Appends one line per workspace rename to the audit log, if configured.
+///
+/// A relative path is placed in the state directory. Errors are only
+/// printed, the audit log must never prevent a rename.
+pub fn audit_dispatch(
+    behavior: &ConfigBehaviorRaw,
+    state: &StateDir,
+    id: i32,
+    old: Option<&str>,
+    new: &str,
+    event: &str,
+) {
+    if let Some(path) = &behavior.audit_log {
+        let line = format!(
+            "{} event={event} id={id} old={:?} new={:?}",
+            now_rfc3339(),
+            old.unwrap_or(""),
+            new
+        );
+        let appended = state
+            .place_file(path)
+            .and_then(|path| append_line(&path, behavior.audit_log_max_size, &line));
+        if let Err(e) = appended {
+            eprintln!("Unable to write audit log {path:?}: {e:?}");
+        }
+    }
+}
+
+fn append_line(path: &Path, max_size: u64, line: &str) -> Result<(), Box<dyn Error>> {
+    rotate_if_needed(path, max_size)?;
+    let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+    writeln!(file, "{line}")?;
+    Ok(())
+}
+
+/// Moves the log to `<path>.1` once it reaches `max_size` bytes,
+/// a `max_size` of 0 disables the rotation.
+fn rotate_if_needed(path: &Path, max_size: u64) -> Result<(), Box<dyn Error>> {
+    let size = fs::metadata(path).map(|m| m.len()).unwrap_or(0);
+    if max_size > 0 && size >= max_size {
+        let mut rotated = PathBuf::from(path).into_os_string();
+        rotated.push(".1");
+        fs::rename(path, rotated)?;
+    }
+    Ok(())
+}
+
+fn now_rfc3339() -> String {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default();
+    to_rfc3339(now.as_secs(), now.subsec_millis())
+}
+
+/// Formats a unix timestamp as an UTC RFC 3339 date.
+fn to_rfc3339(secs: u64, millis: u32) -> String {
+    let days = (secs / 86400) as i64;
+    let rem = secs % 86400;
+    let (hour, min, sec) = (rem / 3600, (rem % 3600) / 60, rem % 60);
+
+    // Civil from days, see http://howardhinnant.github.io/date_algorithms.html
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z.rem_euclid(146097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+
+    format!("{year:04}-{month:02}-{day:02}T{hour:02}:{min:02}:{sec:02}.{millis:03}Z")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_to_rfc3339() {
+        assert_eq!(to_rfc3339(0, 0), "1970-01-01T00:00:00.000Z");
+        assert_eq!(to_rfc3339(1697371234, 42), "2023-10-15T12:00:34.042Z");
+        assert_eq!(to_rfc3339(951782400, 0), "2000-02-29T00:00:00.000Z");
+    }
+
+    #[test]
+    fn test_audit_dispatch_and_rotation() {
+        let path = PathBuf::from("/tmp/hyprland-autoname-workspaces-audit-test.log");
+        let rotated = PathBuf::from("/tmp/hyprland-autoname-workspaces-audit-test.log.1");
+        _ = fs::remove_file(&path);
+        _ = fs::remove_file(&rotated);
+
+        let behavior = ConfigBehaviorRaw {
+            audit_log: Some(path.clone()),
+            audit_log_max_size: 64,
+            ..Default::default()
+        };
+
+        let state = StateDir::default();
+        audit_dispatch(&behavior, &state, 1, None, "1: term", "window_opened");
+        let content = fs::read_to_string(&path).unwrap();
+        assert!(content.ends_with("event=window_opened id=1 old=\"\" new=\"1: term\"\n"));
+
+        audit_dispatch(
+            &behavior,
+            &state,
+            1,
+            Some("1: term"),
+            "1: term term",
+            "window_opened",
+        );
+        assert!(rotated.exists());
+        let content = fs::read_to_string(&path).unwrap();
+        assert_eq!(content.lines().count(), 1);
+        assert!(content.contains("old=\"1: term\" new=\"1: term term\""));
+    }
+}
@@ -0,0 +1,87 @@
+use crate::renamer::backend::ActiveWorkspace;
+use std::collections::HashMap;
+use std::process::Command;
+use std::thread;
+
+/// Starts the default app of a workspace once it is focused and empty,
+/// with the commands of `[autospawn]`.
+#[derive(Debug, Default)]
+pub struct AutoSpawner {
+    /// The workspace an app was started on, until it is left or filled, so
+    /// an app slow to open is not started twice
+    spawned_on: Option<i32>,
+}
+
+impl AutoSpawner {
+    /// Returns the command to run, if the focused workspace just became
+    /// empty and has one.
+    pub fn on_active_workspace<'a>(
+        &mut self,
+        workspace: Option<&ActiveWorkspace>,
+        commands: &'a HashMap<i32, String>,
+    ) -> Option<&'a str> {
+        let Some(workspace) = workspace.filter(|w| w.windows == 0) else {
+            self.spawned_on = None;
+            return None;
+        };
+        if self.spawned_on == Some(workspace.id) {
+            return None;
+        }
+        self.spawned_on = commands.contains_key(&workspace.id).then_some(workspace.id);
+        commands.get(&workspace.id).map(String::as_str)
+    }
+}
+
+/// Runs the command with `sh -c`, without waiting for it.
+pub fn spawn(command: &str) {
+    match Command::new("sh").args(["-c", command]).spawn() {
+        // Reap the shell once it exits
+        Ok(mut child) => {
+            thread::spawn(move || child.wait());
+        }
+        Err(e) => eprintln!("Unable to spawn {command:?}: {e}"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_on_active_workspace() {
+        let commands = HashMap::from([(1, "firefox".to_string())]);
+        let workspace = |id, windows| ActiveWorkspace {
+            id,
+            monitor: "DP-1".to_string(),
+            windows,
+        };
+        let mut spawner = AutoSpawner::default();
+
+        assert_eq!(
+            spawner.on_active_workspace(Some(&workspace(1, 0)), &commands),
+            Some("firefox")
+        );
+        // Not again while the app opens
+        assert_eq!(
+            spawner.on_active_workspace(Some(&workspace(1, 0)), &commands),
+            None
+        );
+        // Once filled, or left, it is started again when empty
+        spawner.on_active_workspace(Some(&workspace(1, 1)), &commands);
+        assert_eq!(
+            spawner.on_active_workspace(Some(&workspace(1, 0)), &commands),
+            Some("firefox")
+        );
+        spawner.on_active_workspace(Some(&workspace(2, 0)), &commands);
+        assert_eq!(
+            spawner.on_active_workspace(Some(&workspace(1, 0)), &commands),
+            Some("firefox")
+        );
+
+        assert_eq!(spawner.on_active_workspace(None, &commands), None);
+        assert_eq!(
+            spawner.on_active_workspace(Some(&workspace(2, 0)), &commands),
+            None
+        );
+    }
+}
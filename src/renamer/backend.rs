@@ -0,0 +1,226 @@
+use crate::renamer::compat::HyprlandCompat;
+use hyprland::data::{Client, Monitors, Workspace, WorkspaceRules, Workspaces};
+use hyprland::dispatch::*;
+use hyprland::keyword::Keyword;
+use hyprland::prelude::*;
+use hyprland::shared::{Address, MonitorId};
+use std::collections::{HashMap, HashSet};
+use std::error::Error;
+
+/// The focused workspace, to rename it when it is empty.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ActiveWorkspace {
+    pub id: i32,
+    pub monitor: String,
+    pub windows: u16,
+}
+
+/// Where the windows come from, and where the workspace names go.
+///
+/// Windows are described with the Hyprland data model, other compositors
+/// adapt theirs to it.
+pub trait Backend: Send + Sync {
+    fn clients(&self) -> Result<Vec<Client>, Box<dyn Error>>;
+
+    /// Address of the focused window, `0x0` if there is none.
+    fn active_client(&self) -> String;
+
+    fn active_workspace(&self) -> Option<ActiveWorkspace>;
+
+    /// The focused workspace, and the workspace shown on every monitor.
+    fn visible_workspaces(&self) -> (Option<i32>, Vec<i32>);
+
+    /// The monitor of every workspace, None if they can't be listed.
+    fn workspace_monitors(&self) -> Option<Vec<(i32, String)>>;
+
+    /// The id of every monitor, by name, None if they can't be listed.
+    fn monitor_ids(&self) -> Option<HashMap<String, MonitorId>>;
+
+    /// The number of windows of every workspace, as counted by the
+    /// compositor, None if it doesn't count them.
+    fn workspace_windows(&self) -> Option<HashMap<i32, u16>>;
+
+    /// Workspaces configured persistent, which exist even when empty.
+    fn persistent_workspaces(&self) -> HashSet<i32>;
+
+    fn rename_workspace(&self, id: i32, name: &str);
+
+    /// Replaces the tag of a window, where tags are supported.
+    fn tag_window(&self, address: &str, previous: Option<&str>, tag: &str);
+
+    /// Sets an environment variable of the compositor, where supported.
+    fn set_env(&self, name: &str, value: &str);
+}
+
+/// Talks to Hyprland over its IPC socket.
+#[derive(Debug, Default)]
+pub struct HyprlandBackend {
+    compat: HyprlandCompat,
+}
+
+impl HyprlandBackend {
+    /// Adapts the backend to the running Hyprland version.
+    pub fn detect() -> Self {
+        HyprlandBackend {
+            compat: HyprlandCompat::detect(),
+        }
+    }
+}
+
+impl Backend for HyprlandBackend {
+    fn clients(&self) -> Result<Vec<Client>, Box<dyn Error>> {
+        self.compat.get_clients()
+    }
+
+    fn active_client(&self) -> String {
+        Client::get_active()
+            .unwrap_or(None)
+            .map(|x| x.address)
+            .unwrap_or(Address::new("0"))
+            .to_string()
+    }
+
+    fn active_workspace(&self) -> Option<ActiveWorkspace> {
+        let workspace = Workspace::get_active().ok()?;
+        Some(ActiveWorkspace {
+            id: workspace.id,
+            monitor: workspace.monitor,
+            windows: workspace.windows,
+        })
+    }
+
+    fn visible_workspaces(&self) -> (Option<i32>, Vec<i32>) {
+        let focused_id = Workspace::get_active().ok().map(|w| w.id);
+        let visible_ids = Monitors::get()
+            .map(|monitors| {
+                monitors
+                    .into_iter()
+                    .map(|m| m.active_workspace.id)
+                    .collect()
+            })
+            .unwrap_or_default();
+        (focused_id, visible_ids)
+    }
+
+    fn workspace_monitors(&self) -> Option<Vec<(i32, String)>> {
+        let workspaces = Workspaces::get().ok()?;
+        Some(workspaces.into_iter().map(|w| (w.id, w.monitor)).collect())
+    }
+
+    fn monitor_ids(&self) -> Option<HashMap<String, MonitorId>> {
+        let monitors = Monitors::get().ok()?;
+        Some(monitors.into_iter().map(|m| (m.name, m.id)).collect())
+    }
+
+    fn workspace_windows(&self) -> Option<HashMap<i32, u16>> {
+        let workspaces = Workspaces::get().ok()?;
+        Some(workspaces.into_iter().map(|w| (w.id, w.windows)).collect())
+    }
+
+    fn persistent_workspaces(&self) -> HashSet<i32> {
+        // Only rules on a workspace id, e.g. `workspace = 1, persistent:true`
+        WorkspaceRules::get()
+            .map(|rules| {
+                rules
+                    .into_iter()
+                    .filter(|rule| rule.persistent == Some(true))
+                    .filter_map(|rule| rule.workspace_string.parse().ok())
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    fn rename_workspace(&self, id: i32, name: &str) {
+        let _ = hyprland::dispatch!(RenameWorkspace, id, Some(name));
+    }
+
+    fn tag_window(&self, address: &str, previous: Option<&str>, tag: &str) {
+        if let Some(previous) = previous {
+            let args = format!("-{previous} address:{address}");
+            let _ = Dispatch::call(DispatchType::Custom("tagwindow", &args));
+        }
+        let args = format!("+{tag} address:{address}");
+        let _ = Dispatch::call(DispatchType::Custom("tagwindow", &args));
+    }
+
+    fn set_env(&self, name: &str, value: &str) {
+        let _ = Keyword::set("env", format!("{name},{value}"));
+    }
+}
+
+#[cfg(test)]
+pub use mock::MockBackend;
+
+#[cfg(test)]
+mod mock {
+    use super::*;
+    use std::sync::Mutex;
+
+    /// Serves the given windows, and records the renames.
+    #[derive(Debug, Default)]
+    pub struct MockBackend {
+        pub clients: Mutex<Vec<Client>>,
+        pub active_client: Mutex<String>,
+        pub renames: Mutex<Vec<(i32, String)>>,
+        pub persistent: Mutex<HashSet<i32>>,
+        pub tags: Mutex<Vec<(String, String)>>,
+        pub visible: Mutex<Vec<i32>>,
+        pub windows: Mutex<Option<HashMap<i32, u16>>>,
+        pub envs: Mutex<Vec<(String, String)>>,
+        pub monitors: Mutex<Vec<(i32, String)>>,
+        pub monitor_ids: Mutex<HashMap<String, MonitorId>>,
+    }
+
+    impl Backend for MockBackend {
+        fn clients(&self) -> Result<Vec<Client>, Box<dyn Error>> {
+            Ok(self.clients.lock().unwrap().clone())
+        }
+
+        fn active_client(&self) -> String {
+            self.active_client.lock().unwrap().clone()
+        }
+
+        fn active_workspace(&self) -> Option<ActiveWorkspace> {
+            None
+        }
+
+        fn visible_workspaces(&self) -> (Option<i32>, Vec<i32>) {
+            let visible = self.visible.lock().unwrap().clone();
+            (visible.first().copied(), visible)
+        }
+
+        fn workspace_monitors(&self) -> Option<Vec<(i32, String)>> {
+            Some(self.monitors.lock().unwrap().clone())
+        }
+
+        fn monitor_ids(&self) -> Option<HashMap<String, MonitorId>> {
+            Some(self.monitor_ids.lock().unwrap().clone())
+        }
+
+        fn workspace_windows(&self) -> Option<HashMap<i32, u16>> {
+            self.windows.lock().unwrap().clone()
+        }
+
+        fn persistent_workspaces(&self) -> HashSet<i32> {
+            self.persistent.lock().unwrap().clone()
+        }
+
+        fn rename_workspace(&self, id: i32, name: &str) {
+            self.renames.lock().unwrap().push((id, name.to_string()));
+        }
+
+        fn tag_window(&self, address: &str, _previous: Option<&str>, tag: &str) {
+            self.tags
+                .lock()
+                .unwrap()
+                .push((address.to_string(), tag.to_string()));
+        }
+
+        fn set_env(&self, name: &str, value: &str) {
+            self.envs
+                .lock()
+                .unwrap()
+                .push((name.to_string(), value.to_string()));
+        }
+    }
+}
@@ -0,0 +1,82 @@
+use crate::renamer::formatter::{truncate_to, visual_width};
+use std::collections::HashMap;
+
+/// Truncates the workspaces names so their total visible width stays under
+/// `max_total_width`.
+///
+/// Every workspace gets a share of the width proportional to its name, and
+/// what is left by the rounding goes to the most recently used workspaces,
+/// so the least recently used ones are shortened first. Each workspace keeps
+/// at least one character.
+pub fn balance_widths(
+    workspaces_names: &HashMap<i32, String>,
+    max_total_width: usize,
+    last_used: &HashMap<i32, u64>,
+) -> HashMap<i32, String> {
+    let widths: HashMap<i32, usize> = workspaces_names
+        .iter()
+        .map(|(&id, name)| (id, visual_width(name)))
+        .collect();
+    let total_width: usize = widths.values().sum();
+    if total_width <= max_total_width {
+        return workspaces_names.clone();
+    }
+
+    let mut targets: HashMap<i32, usize> = widths
+        .iter()
+        .map(|(&id, &width)| {
+            (
+                id,
+                (width * max_total_width / total_width).clamp(1, width.max(1)),
+            )
+        })
+        .collect();
+
+    // Most recently used first, never used workspaces last
+    let mut ids: Vec<i32> = widths.keys().copied().collect();
+    ids.sort_by_key(|id| (std::cmp::Reverse(last_used.get(id).copied()), *id));
+
+    let mut remaining = max_total_width.saturating_sub(targets.values().sum());
+    for id in ids {
+        let target = targets.get_mut(&id).expect("target of a known workspace");
+        let extra = (widths[&id] - *target).min(remaining);
+        *target += extra;
+        remaining -= extra;
+    }
+
+    workspaces_names
+        .iter()
+        .map(|(&id, name)| (id, truncate_to(name, targets[&id])))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_balance_widths() {
+        let names = HashMap::from([
+            (1, "1: aaaaaaaaaa".to_string()),
+            (2, "2: bbbbbbbbbb".to_string()),
+            (3, "3: cc".to_string()),
+        ]);
+
+        // Enough room, nothing changes
+        assert_eq!(balance_widths(&names, 100, &HashMap::new()), names);
+
+        // Workspace 1 is the least recently used, so it is shortened first
+        let last_used = HashMap::from([(1, 1), (2, 3), (3, 2)]);
+        let balanced = balance_widths(&names, 20, &last_used);
+        let total: usize = balanced.values().map(|n| visual_width(n)).sum();
+        assert_eq!(total, 20);
+        assert_eq!(balanced[&1], "1: aaaa…");
+        assert_eq!(balanced[&2], "2: bbbbb…");
+        assert_eq!(balanced[&3], "3:…");
+
+        // Each workspace keeps at least one character
+        let balanced = balance_widths(&names, 1, &last_used);
+        assert_eq!(balanced[&1], "1");
+        assert!(balanced.values().all(|n| visual_width(n) == 1));
+    }
+}
@@ -0,0 +1,188 @@
+use hyprland::data::{Client, Clients};
+use hyprland::shared::HyprData;
+use semver::Version;
+use serde_json::Value;
+use std::env;
+use std::error::Error;
+use std::io::{Read, Write};
+use std::os::unix::net::UnixStream;
+use std::path::PathBuf;
+
+/// Hyprland 0.42 replaced the `fullscreen` bool of clients by the fullscreen
+/// state, and added the client requested state in `fullscreenClient`.
+const FULLSCREEN_STATE_VERSION: Version = Version::new(0, 42, 0);
+
+/// Adapts how we talk to Hyprland to the running version.
+#[derive(Default, Debug, Clone, PartialEq, Eq)]
+pub struct HyprlandCompat {
+    pub version: Option<Version>,
+    pub legacy_fullscreen: bool,
+}
+
+impl HyprlandCompat {
+    /// Asks the running Hyprland for its version, and logs the features
+    /// we can't support with it. Unknown versions are handled as the latest.
+    pub fn detect() -> Self {
+        let version = hyprctl_raw("j/version")
+            .ok()
+            .and_then(|json| parse_version(&json));
+
+        let compat = Self::from_version(version);
+        match &compat.version {
+            Some(version) => println!("Hyprland {version} detected"),
+            None => println!("Unable to detect Hyprland version, assuming the latest"),
+        }
+        if compat.legacy_fullscreen {
+            println!(
+                "Hyprland < {FULLSCREEN_STATE_VERSION}: the client fullscreen state is unavailable"
+            );
+        }
+        compat
+    }
+
+    pub fn from_version(version: Option<Version>) -> Self {
+        let is_older = |min: &Version| version.as_ref().is_some_and(|v| v < min);
+        HyprlandCompat {
+            legacy_fullscreen: is_older(&FULLSCREEN_STATE_VERSION),
+            version,
+        }
+    }
+
+    pub fn get_clients(&self) -> Result<Vec<Client>, Box<dyn Error>> {
+        if self.legacy_fullscreen {
+            let json = hyprctl_raw("j/clients")?;
+            parse_legacy_clients(&json)
+        } else {
+            Ok(Clients::get()?.into_iter().collect())
+        }
+    }
+}
+
+/// Reads the version from `hyprctl version -j`, e.g. `"tag": "v0.41.2-3-gabc"`.
+fn parse_version(json: &str) -> Option<Version> {
+    let value: Value = serde_json::from_str(json).ok()?;
+    let tag = value
+        .get("version")
+        .and_then(Value::as_str)
+        .or_else(|| value.get("tag").and_then(Value::as_str))?;
+    let tag = tag.trim_start_matches('v');
+    let tag = tag.split('-').next()?;
+    Version::parse(tag).ok()
+}
+
+/// Converts clients from Hyprland < 0.42, where `fullscreen` is a bool and
+/// `fullscreenMode` is 0 for fullscreen or 1 for maximized, the clients of
+/// later versions are kept as they are.
+pub fn parse_legacy_clients(json: &str) -> Result<Vec<Client>, Box<dyn Error>> {
+    let mut clients: Vec<Value> = serde_json::from_str(json)?;
+    for client in clients.iter_mut() {
+        if let Some(object) = client.as_object_mut() {
+            let fullscreen = match object.get("fullscreen") {
+                Some(Value::Bool(true)) => match object.get("fullscreenMode") {
+                    Some(mode) if mode.as_u64() == Some(1) => 1,
+                    _ => 2,
+                },
+                Some(Value::Number(n)) => n.as_u64().unwrap_or(0),
+                _ => 0,
+            };
+            object.insert("fullscreen".to_string(), fullscreen.into());
+            object
+                .entry("fullscreenClient")
+                .or_insert_with(|| fullscreen.into());
+            object.entry("focusHistoryID").or_insert_with(|| 0.into());
+        }
+    }
+    Ok(serde_json::from_value(Value::Array(clients))?)
+}
+
+/// Sends a raw command to the Hyprland socket, where it is on older versions too.
+fn hyprctl_raw(command: &str) -> Result<String, Box<dyn Error>> {
+    let signature = env::var("HYPRLAND_INSTANCE_SIGNATURE")?;
+    let runtime_dir = env::var("XDG_RUNTIME_DIR").unwrap_or_default();
+
+    let socket_path = [
+        PathBuf::from(runtime_dir).join("hypr"),
+        PathBuf::from("/tmp/hypr"),
+    ]
+    .into_iter()
+    .map(|dir| dir.join(&signature).join(".socket.sock"))
+    .find(|path| path.exists())
+    .ok_or("Hyprland socket not found")?;
+
+    let mut stream = UnixStream::connect(socket_path)?;
+    stream.write_all(command.as_bytes())?;
+    let mut response = String::new();
+    stream.read_to_string(&mut response)?;
+    Ok(response)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use hyprland::data::FullscreenMode;
+
+    #[test]
+    fn test_parse_version() {
+        assert_eq!(
+            parse_version(r#"{"tag": "v0.41.2-3-gabcdef"}"#),
+            Some(Version::new(0, 41, 2))
+        );
+        assert_eq!(
+            parse_version(r#"{"version": "0.45.0", "tag": "v0.45.0"}"#),
+            Some(Version::new(0, 45, 0))
+        );
+        assert_eq!(parse_version(r#"{"tag": "unknown"}"#), None);
+        assert_eq!(parse_version("ok"), None);
+    }
+
+    #[test]
+    fn test_from_version() {
+        let compat = HyprlandCompat::from_version(Some(Version::new(0, 41, 2)));
+        assert!(compat.legacy_fullscreen);
+
+        let compat = HyprlandCompat::from_version(Some(Version::new(0, 42, 0)));
+        assert!(!compat.legacy_fullscreen);
+
+        assert_eq!(
+            HyprlandCompat::from_version(None),
+            HyprlandCompat::default()
+        );
+    }
+
+    #[test]
+    fn test_parse_legacy_clients() {
+        let json = r#"[{
+            "address": "0x1", "mapped": true, "hidden": false,
+            "at": [0, 0], "size": [100, 100],
+            "workspace": {"id": 1, "name": "1"},
+            "floating": false, "monitor": 0,
+            "class": "kitty", "title": "zsh",
+            "initialClass": "kitty", "initialTitle": "zsh",
+            "pid": 42, "xwayland": false, "pinned": false,
+            "fullscreen": true, "fullscreenMode": 0, "fakeFullscreen": false,
+            "grouped": [], "swallowing": "0x0"
+        }]"#;
+
+        let clients = parse_legacy_clients(json).unwrap();
+        assert_eq!(clients.len(), 1);
+        assert_eq!(clients[0].class, "kitty");
+        assert_eq!(clients[0].fullscreen, FullscreenMode::Fullscreen);
+        assert_eq!(clients[0].fullscreen_client, FullscreenMode::Fullscreen);
+
+        // Maximized
+        let clients =
+            parse_legacy_clients(&json.replace(r#""fullscreenMode": 0"#, r#""fullscreenMode": 1"#))
+                .unwrap();
+        assert_eq!(clients[0].fullscreen, FullscreenMode::Maximized);
+        assert_eq!(clients[0].fullscreen_client, FullscreenMode::Maximized);
+
+        // fullscreenMode is only meaningful while fullscreen
+        let clients = parse_legacy_clients(
+            &json
+                .replace(r#""fullscreen": true"#, r#""fullscreen": false"#)
+                .replace(r#""fullscreenMode": 0"#, r#""fullscreenMode": 1"#),
+        )
+        .unwrap();
+        assert_eq!(clients[0].fullscreen, FullscreenMode::None);
+    }
+}
@@ -0,0 +1,282 @@
+use inotify::{Inotify, WatchDescriptor, WatchMask};
+use std::collections::BTreeSet;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Editors often write the file several times in a row, the config is
+/// reloaded once no write happened for that long.
+const DEBOUNCE: Duration = Duration::from_millis(300);
+const MIN_BACKOFF: Duration = Duration::from_secs(1);
+const MAX_BACKOFF: Duration = Duration::from_secs(60);
+
+/// Watches the config file, and the local icon packs, with a single
+/// inotify instance.
+///
+/// The directories are watched rather than the files, so editors replacing
+/// the file on save (write then rename) are followed too.
+pub struct ConfigWatcher {
+    config: PathBuf,
+    packs: Vec<PathBuf>,
+    inotify: Option<Inotify>,
+    dirs: Vec<(WatchDescriptor, PathBuf)>,
+    changed: BTreeSet<PathBuf>,
+    last_change: Option<Instant>,
+    failures: u32,
+}
+
+impl ConfigWatcher {
+    pub fn new(path: &Path) -> Self {
+        ConfigWatcher {
+            config: path.to_path_buf(),
+            packs: Vec::new(),
+            inotify: None,
+            dirs: Vec::new(),
+            changed: BTreeSet::new(),
+            last_change: None,
+            failures: 0,
+        }
+    }
+
+    /// Watches these icon pack files too, instead of the previous ones.
+    pub fn set_packs(&mut self, packs: Vec<PathBuf>) {
+        if packs != self.packs {
+            self.packs = packs;
+            self.inotify = None;
+        }
+    }
+
+    /// Blocks until a watched file changes, and returns the files to reload
+    /// once they are no longer being written.
+    ///
+    /// On error, the watcher is recreated on the next wait.
+    pub fn wait(&mut self) -> io::Result<Vec<PathBuf>> {
+        loop {
+            let block = match self.last_change {
+                Some(last_change) => {
+                    thread::sleep(
+                        (last_change + DEBOUNCE).saturating_duration_since(Instant::now()),
+                    );
+                    false
+                }
+                None => true,
+            };
+            let changed = self.read(block, Instant::now())?;
+            if !changed.is_empty() {
+                return Ok(changed);
+            }
+        }
+    }
+
+    /// Reads the pending events, and returns the files to reload now, none
+    /// while they are still being written.
+    fn read(&mut self, block: bool, now: Instant) -> io::Result<Vec<PathBuf>> {
+        match self.read_changes(block) {
+            Ok(changed) => {
+                self.failures = 0;
+                if changed {
+                    self.last_change = Some(now);
+                }
+            }
+            Err(e) => {
+                self.inotify = None;
+                self.failures += 1;
+                return Err(e);
+            }
+        }
+        match self.on_tick(now) {
+            true => Ok(std::mem::take(&mut self.changed).into_iter().collect()),
+            false => Ok(Vec::new()),
+        }
+    }
+
+    pub fn is_config(&self, path: &Path) -> bool {
+        path == self.config
+    }
+
+    /// How long to wait after an error, doubling with each failure in a row.
+    pub fn backoff(&self) -> Duration {
+        let exponent = self.failures.saturating_sub(1).min(6);
+        (MIN_BACKOFF * 2u32.pow(exponent)).min(MAX_BACKOFF)
+    }
+
+    fn on_tick(&mut self, now: Instant) -> bool {
+        match self.last_change {
+            Some(last_change) if now.duration_since(last_change) >= DEBOUNCE => {
+                self.last_change = None;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Watches the directory of the config, which must exist, and the ones
+    /// of the packs, which may not.
+    fn init_watches(&mut self) -> io::Result<Inotify> {
+        let inotify = Inotify::init()?;
+        let mask =
+            WatchMask::MODIFY | WatchMask::CLOSE_WRITE | WatchMask::CREATE | WatchMask::MOVED_TO;
+        self.dirs.clear();
+
+        let config_dir = parent_dir(&self.config);
+        let wd = inotify.watches().add(&config_dir, mask)?;
+        self.dirs.push((wd, config_dir));
+        for pack in &self.packs {
+            let dir = parent_dir(pack);
+            if let Ok(wd) = inotify.watches().add(&dir, mask) {
+                self.dirs.push((wd, dir));
+            }
+        }
+        Ok(inotify)
+    }
+
+    /// Reads the pending events, waiting for one first if `block`.
+    fn read_changes(&mut self, block: bool) -> io::Result<bool> {
+        if self.inotify.is_none() {
+            self.inotify = Some(self.init_watches()?);
+        }
+        let Some(inotify) = self.inotify.as_mut() else {
+            return Ok(false);
+        };
+
+        let mut changed = false;
+        let mut buffer = [0; 1024];
+        let mut block = block;
+        loop {
+            let events = match std::mem::take(&mut block) {
+                true => inotify.read_events_blocking(&mut buffer),
+                false => inotify.read_events(&mut buffer),
+            };
+            match events {
+                Ok(events) => {
+                    for event in events {
+                        let Some(name) = event.name else {
+                            continue;
+                        };
+                        // Directories watched twice share their descriptor
+                        let paths = self
+                            .dirs
+                            .iter()
+                            .filter(|(wd, _)| *wd == event.wd)
+                            .map(|(_, dir)| dir.join(name));
+                        for path in paths {
+                            if path == self.config || self.packs.contains(&path) {
+                                self.changed.insert(path);
+                                changed = true;
+                            }
+                        }
+                    }
+                }
+                Err(e) if e.kind() == io::ErrorKind::WouldBlock => return Ok(changed),
+                Err(e) => return Err(e),
+            }
+        }
+    }
+}
+
+fn parent_dir(path: &Path) -> PathBuf {
+    match path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir.to_path_buf(),
+        _ => PathBuf::from("."),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn test_debounce() {
+        let mut watcher = ConfigWatcher::new(Path::new("/tmp/config.toml"));
+        let start = Instant::now();
+
+        assert!(!watcher.on_tick(start));
+        watcher.last_change = Some(start);
+        assert!(!watcher.on_tick(start + Duration::from_millis(100)));
+        watcher.last_change = Some(start + Duration::from_millis(200));
+        assert!(!watcher.on_tick(start + Duration::from_millis(400)));
+        assert!(watcher.on_tick(start + Duration::from_millis(500)));
+        assert!(!watcher.on_tick(start + Duration::from_millis(900)));
+    }
+
+    #[test]
+    fn test_backoff() {
+        let mut watcher = ConfigWatcher::new(Path::new("config.toml"));
+        assert_eq!(parent_dir(&watcher.config), PathBuf::from("."));
+
+        watcher.failures = 1;
+        assert_eq!(watcher.backoff(), Duration::from_secs(1));
+        watcher.failures = 3;
+        assert_eq!(watcher.backoff(), Duration::from_secs(4));
+        watcher.failures = 20;
+        assert_eq!(watcher.backoff(), MAX_BACKOFF);
+    }
+
+    #[test]
+    fn test_read() {
+        let dir = std::env::temp_dir().join("hyprland-autoname-workspaces-watch-test");
+        _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("config.toml");
+        fs::write(&path, "").unwrap();
+
+        let mut watcher = ConfigWatcher::new(&path);
+        let start = Instant::now();
+        assert!(watcher.read(false, start).unwrap().is_empty());
+
+        fs::write(dir.join("other.toml"), "").unwrap();
+        assert!(watcher.read(false, start).unwrap().is_empty());
+        assert!(watcher.read(false, start + DEBOUNCE).unwrap().is_empty());
+
+        // Replaced on save, like most editors do
+        fs::write(dir.join("config.toml.tmp"), "[class]").unwrap();
+        fs::rename(dir.join("config.toml.tmp"), &path).unwrap();
+        assert!(watcher.read(false, start).unwrap().is_empty());
+        assert_eq!(
+            watcher.read(false, start + DEBOUNCE).unwrap(),
+            [path.as_path()]
+        );
+
+        // Packs are watched too, even next to the config
+        let pack = dir.join("pack.toml");
+        watcher.set_packs(vec![pack.clone()]);
+        assert!(watcher.read(false, start).unwrap().is_empty());
+        fs::write(&pack, "[class]").unwrap();
+        assert!(watcher.read(false, start).unwrap().is_empty());
+        assert_eq!(
+            watcher.read(false, start + DEBOUNCE).unwrap(),
+            [pack.as_path()]
+        );
+        assert!(!watcher.is_config(&pack));
+        assert!(watcher.is_config(&path));
+
+        let mut watcher = ConfigWatcher::new(&dir.join("missing/config.toml"));
+        assert!(watcher.read(false, start).is_err());
+        assert_eq!(watcher.backoff(), Duration::from_secs(1));
+    }
+
+    #[test]
+    fn test_wait() {
+        let dir = std::env::temp_dir().join("hyprland-autoname-workspaces-wait-test");
+        _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("config.toml");
+        fs::write(&path, "").unwrap();
+
+        let mut watcher = ConfigWatcher::new(&path);
+        assert!(watcher.read(false, Instant::now()).unwrap().is_empty());
+
+        let written = path.clone();
+        let writer = thread::spawn(move || {
+            thread::sleep(Duration::from_millis(50));
+            fs::write(&written, "[class]").unwrap();
+            fs::write(&written, "[class]\nkitty = \"term\"").unwrap();
+        });
+        let start = Instant::now();
+        assert_eq!(watcher.wait().unwrap(), [path.as_path()]);
+        assert!(start.elapsed() >= DEBOUNCE);
+        writer.join().unwrap();
+    }
+}
@@ -0,0 +1,27 @@
+/// Invisible mark put at the end of the names we dispatch, to tell them
+/// from the names of other tools.
+const SIGNATURE: char = '\u{2063}';
+
+pub fn sign(name: &str) -> String {
+    format!("{name}{SIGNATURE}")
+}
+
+/// Tells if another tool named the workspace: the name is not ours, nor
+/// the one Hyprland gives, the id or `special:<name>` for scratchpads.
+pub fn is_foreign_name(id: i32, name: &str) -> bool {
+    !name.ends_with(SIGNATURE) && name != id.to_string() && !(id < 0 && name.starts_with("special"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_foreign_name() {
+        assert!(!is_foreign_name(1, "1"));
+        assert!(!is_foreign_name(1, &sign("1: term")));
+        assert!(!is_foreign_name(-98, "special:scratch"));
+        assert!(is_foreign_name(1, "1: web"));
+        assert!(is_foreign_name(-98, "scratch"));
+    }
+}
@@ -0,0 +1,200 @@
+use crate::config::{ConfigFormatRaw, DedupField};
+use crate::renamer::icon::IconStatus;
+use crate::renamer::AppClient;
+use hyprland::data::FullscreenMode;
+
+/// What makes two clients the same client, when deduplicating them.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DedupPolicy {
+    icon: bool,
+    by_icon_value: bool,
+    class: bool,
+    title: bool,
+    fullscreen: bool,
+    active: bool,
+}
+
+/// Clients with the same key are grouped together.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DedupKey {
+    icon: Option<IconStatus>,
+    icon_value: Option<String>,
+    class: Option<String>,
+    title: Option<String>,
+    fullscreen: Option<FullscreenMode>,
+    active: Option<bool>,
+}
+
+impl Default for DedupPolicy {
+    /// Same matched icon, same activity and same fullscreen state.
+    fn default() -> Self {
+        DedupPolicy::new(&[DedupField::Icon, DedupField::Active, DedupField::Fullscreen])
+    }
+}
+
+impl DedupPolicy {
+    pub fn new(fields: &[DedupField]) -> Self {
+        DedupPolicy {
+            icon: fields.contains(&DedupField::Icon),
+            by_icon_value: false,
+            class: fields.contains(&DedupField::Class),
+            title: fields.contains(&DedupField::Title),
+            fullscreen: fields.contains(&DedupField::Fullscreen),
+            active: fields.contains(&DedupField::Active),
+        }
+    }
+
+    pub fn from_format(format: &ConfigFormatRaw) -> Self {
+        DedupPolicy {
+            by_icon_value: format.dedup_by_icon,
+            ..DedupPolicy::new(&format.dedup_by)
+        }
+    }
+
+    /// Builds the grouping key of the client. The fullscreen state never
+    /// participates when inactive fullscreen clients are deduplicated.
+    ///
+    /// The icon is compared by rule, unless `by_icon_value` is set, then
+    /// clients of different rules rendering the same glyph are grouped.
+    pub fn key(&self, client: &AppClient) -> DedupKey {
+        let with_fullscreen = self.fullscreen && !client.is_dedup_inactive_fullscreen;
+        let (by_rule, by_value) = (
+            self.icon && !self.by_icon_value,
+            self.icon && self.by_icon_value,
+        );
+        DedupKey {
+            icon: by_rule.then(|| client.matched_rule.clone()),
+            icon_value: by_value.then(|| client.matched_rule.icon()),
+            class: self.class.then(|| client.class.clone()),
+            title: self.title.then(|| client.title.clone()),
+            fullscreen: with_fullscreen.then_some(client.is_fullscreen),
+            active: self.active.then_some(client.is_active),
+        }
+    }
+
+    pub fn same(&self, a: &AppClient, b: &AppClient) -> bool {
+        self.key(a) == self.key(b)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::renamer::icon::IconConfig::Class;
+    use crate::renamer::icon::IconStatus::{Active, Inactive};
+
+    fn client(
+        class: &str,
+        title: &str,
+        is_active: bool,
+        is_fullscreen: FullscreenMode,
+    ) -> AppClient {
+        let rule = Class("term".to_string(), "term".to_string());
+        AppClient {
+            initial_class: class.to_string(),
+            class: class.to_string(),
+            title: title.to_string(),
+            initial_title: title.to_string(),
+            is_active,
+            is_fullscreen,
+            matched_rule: if is_active {
+                Active(rule)
+            } else {
+                Inactive(rule)
+            },
+            is_dedup_inactive_fullscreen: false,
+            size: (0, 0),
+            urgent_for: None,
+            is_unmanaged: false,
+            is_floating: false,
+            is_pinned: false,
+            is_xwayland: false,
+            position: (0, 0),
+            is_fullscreen_client: FullscreenMode::None,
+        }
+    }
+
+    #[test]
+    fn test_default_policy() {
+        let policy = DedupPolicy::default();
+        let kitty = client("kitty", "zsh", false, FullscreenMode::None);
+
+        assert!(policy.same(&kitty, &client("foot", "vim", false, FullscreenMode::None)));
+        assert!(!policy.same(&kitty, &client("kitty", "zsh", true, FullscreenMode::None)));
+        assert!(!policy.same(
+            &kitty,
+            &client("kitty", "zsh", false, FullscreenMode::Fullscreen)
+        ));
+
+        let mut fullscreen = client("kitty", "zsh", false, FullscreenMode::Fullscreen);
+        fullscreen.is_dedup_inactive_fullscreen = true;
+        let mut other = kitty.clone();
+        other.is_dedup_inactive_fullscreen = true;
+        assert!(policy.same(&other, &fullscreen));
+    }
+
+    #[test]
+    fn test_icon_value_policy() {
+        let kitty = client("kitty", "zsh", false, FullscreenMode::None);
+        let mut foot = client("foot", "zsh", false, FullscreenMode::None);
+        foot.matched_rule = Inactive(Class("foot".to_string(), "term".to_string()));
+
+        let mut format: ConfigFormatRaw = toml::from_str("").unwrap();
+        assert!(!DedupPolicy::from_format(&format).same(&kitty, &foot));
+
+        format.dedup_by_icon = true;
+        let policy = DedupPolicy::from_format(&format);
+        assert!(policy.same(&kitty, &foot));
+        foot.matched_rule = Inactive(Class("foot".to_string(), "foot".to_string()));
+        assert!(!policy.same(&kitty, &foot));
+    }
+
+    #[test]
+    fn test_class_policy() {
+        let policy = DedupPolicy::new(&[DedupField::Class]);
+        let kitty = client("kitty", "zsh", false, FullscreenMode::None);
+
+        assert!(policy.same(
+            &kitty,
+            &client("kitty", "vim", true, FullscreenMode::Fullscreen)
+        ));
+        assert!(!policy.same(&kitty, &client("foot", "zsh", false, FullscreenMode::None)));
+    }
+
+    #[test]
+    fn test_title_policy() {
+        let policy = DedupPolicy::new(&[DedupField::Icon, DedupField::Title]);
+        let kitty = client("kitty", "zsh", false, FullscreenMode::None);
+
+        assert!(policy.same(
+            &kitty,
+            &client("foot", "zsh", false, FullscreenMode::Maximized)
+        ));
+        assert!(!policy.same(&kitty, &client("kitty", "vim", false, FullscreenMode::None)));
+    }
+
+    #[test]
+    fn test_activity_and_fullscreen_policy() {
+        let policy = DedupPolicy::new(&[DedupField::Active]);
+        let kitty = client("kitty", "zsh", false, FullscreenMode::None);
+        assert!(policy.same(
+            &kitty,
+            &client("foot", "vim", false, FullscreenMode::Fullscreen)
+        ));
+        assert!(!policy.same(&kitty, &client("kitty", "zsh", true, FullscreenMode::None)));
+
+        let policy = DedupPolicy::new(&[DedupField::Fullscreen]);
+        assert!(policy.same(&kitty, &client("foot", "vim", true, FullscreenMode::None)));
+        assert!(!policy.same(
+            &kitty,
+            &client("kitty", "zsh", false, FullscreenMode::Maximized)
+        ));
+
+        // Without any field, all clients are grouped
+        let policy = DedupPolicy::new(&[]);
+        assert!(policy.same(
+            &kitty,
+            &client("foot", "vim", true, FullscreenMode::Fullscreen)
+        ));
+    }
+}
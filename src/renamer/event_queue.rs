@@ -0,0 +1,114 @@
+use std::collections::VecDeque;
+
+/// How many events may wait for a rename, older ones are dropped beyond.
+pub const CAPACITY: usize = 64;
+
+/// The events dropped by the queue, reported by `status`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct QueueStats {
+    pub queued: u64,
+    /// Replaced by a later event about the same workspace or window
+    pub stale: u64,
+    /// The oldest events, dropped while the queue was full
+    pub overflowed: u64,
+}
+
+impl std::fmt::Display for QueueStats {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} queued, {} stale dropped, {} dropped when full",
+            self.queued, self.stale, self.overflowed
+        )
+    }
+}
+
+/// Events waiting for a rename, between the event listener and the renames,
+/// so a storm of events (e.g. a window updating its title every frame)
+/// doesn't pile up renames.
+///
+/// Every rename renders all the workspaces, so only the latest event about
+/// a workspace, or a window for the events without one, is kept.
+#[derive(Debug)]
+pub struct EventQueue {
+    pending: VecDeque<(String, String)>,
+    capacity: usize,
+    stats: QueueStats,
+}
+
+impl Default for EventQueue {
+    fn default() -> Self {
+        EventQueue::new(CAPACITY)
+    }
+}
+
+impl EventQueue {
+    pub fn new(capacity: usize) -> Self {
+        EventQueue {
+            pending: VecDeque::new(),
+            capacity: capacity.max(1),
+            stats: QueueStats::default(),
+        }
+    }
+
+    /// Queues the event about the subject, a workspace or a window,
+    /// dropping the pending event of the same kind about it.
+    pub fn push(&mut self, event: &str, subject: &str) {
+        self.stats.queued += 1;
+        if let Some(index) = self
+            .pending
+            .iter()
+            .position(|(e, s)| e == event && s == subject)
+        {
+            self.pending.remove(index);
+            self.stats.stale += 1;
+        } else if self.pending.len() >= self.capacity {
+            self.pending.pop_front();
+            self.stats.overflowed += 1;
+        }
+        self.pending
+            .push_back((event.to_string(), subject.to_string()));
+    }
+
+    /// Returns the oldest pending event.
+    pub fn pop(&mut self) -> Option<String> {
+        self.pending.pop_front().map(|(event, _)| event)
+    }
+
+    pub fn stats(&self) -> QueueStats {
+        self.stats
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_event_queue() {
+        let mut queue = EventQueue::new(3);
+        queue.push("window_title_changed", "0x1");
+        queue.push("window_opened", "1");
+        queue.push("window_title_changed", "0x1");
+        queue.push("window_title_changed", "0x2");
+        assert_eq!(
+            queue.stats(),
+            QueueStats {
+                queued: 4,
+                stale: 1,
+                overflowed: 0
+            }
+        );
+
+        queue.push("workspace_changed", "2");
+        assert_eq!(queue.stats().overflowed, 1);
+        assert_eq!(queue.pop().as_deref(), Some("window_title_changed"));
+        assert_eq!(queue.pop().as_deref(), Some("window_title_changed"));
+        assert_eq!(queue.pop().as_deref(), Some("workspace_changed"));
+        assert_eq!(queue.pop(), None);
+        assert_eq!(
+            queue.stats().to_string(),
+            "5 queued, 1 stale dropped, 1 dropped when full"
+        );
+    }
+}
@@ -0,0 +1,348 @@
+use crate::config::Config;
+use crate::params::Args;
+use crate::renamer::backend::{ActiveWorkspace, Backend};
+use crate::renamer::compat::parse_legacy_clients;
+use crate::renamer::Renamer;
+use hyprland::data::Client;
+use hyprland::shared::MonitorId;
+use serde::Deserialize;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::error::Error;
+use std::fs;
+use std::path::Path;
+use std::sync::Mutex;
+
+/// A rendering scenario: a config, the windows, and the expected names.
+///
+/// ```toml
+/// config = """
+/// [class]
+/// kitty = "term"
+/// """
+///
+/// [[clients]]
+/// workspace = 1
+/// class = "kitty"
+/// active = true
+///
+/// [expected]
+/// 1 = "1: *term*"
+/// ```
+#[derive(Deserialize, Debug)]
+pub struct Fixture {
+    #[serde(default)]
+    pub description: String,
+    #[serde(default)]
+    pub config: String,
+    #[serde(default)]
+    pub clients: Vec<FixtureClient>,
+    /// Names by workspace id, nothing is checked without it
+    #[serde(default, deserialize_with = "deserialize_expected")]
+    pub expected: Option<BTreeMap<i32, String>>,
+}
+
+/// A window, only its workspace and class are required.
+#[derive(Deserialize, Debug)]
+pub struct FixtureClient {
+    pub workspace: i32,
+    /// Name of the workspace, e.g. `special:scratch`, the id by default
+    #[serde(default)]
+    pub workspace_name: Option<String>,
+    pub class: String,
+    #[serde(default)]
+    pub title: String,
+    #[serde(default)]
+    pub initial_class: Option<String>,
+    #[serde(default)]
+    pub initial_title: Option<String>,
+    #[serde(default)]
+    pub active: bool,
+    /// 0 none, 1 maximized, 2 fullscreen
+    #[serde(default)]
+    pub fullscreen: u8,
+    #[serde(default)]
+    pub floating: bool,
+    #[serde(default)]
+    pub pinned: bool,
+    #[serde(default)]
+    pub xwayland: bool,
+    #[serde(default)]
+    pub at: (i16, i16),
+    #[serde(default)]
+    pub size: (i16, i16),
+}
+
+/// TOML keys are strings, the ids are parsed from them.
+fn deserialize_expected<'de, D>(deserializer: D) -> Result<Option<BTreeMap<i32, String>>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    let expected: Option<BTreeMap<String, String>> = Option::deserialize(deserializer)?;
+    expected
+        .map(|expected| {
+            expected
+                .into_iter()
+                .map(|(id, name)| match id.parse() {
+                    Ok(id) => Ok((id, name)),
+                    Err(_) => Err(serde::de::Error::custom(format!(
+                        "invalid workspace id {id:?}"
+                    ))),
+                })
+                .collect()
+        })
+        .transpose()
+}
+
+impl Fixture {
+    pub fn from_file(path: &Path) -> Result<Fixture, Box<dyn Error>> {
+        let content = fs::read_to_string(path)?;
+        Ok(toml::from_str(&content).map_err(|e| format!("Unable to parse fixture: {e}"))?)
+    }
+
+    /// Renames the workspaces once, like at startup, and returns the names.
+    pub fn render(&self) -> Result<BTreeMap<i32, String>, Box<dyn Error>> {
+        render(&self.config, FixtureBackend::new(self)?)
+    }
+
+    /// Returns a line per workspace whose name isn't the expected one.
+    pub fn mismatches(&self, names: &BTreeMap<i32, String>) -> Vec<String> {
+        let Some(expected) = &self.expected else {
+            return vec![];
+        };
+        let ids: BTreeMap<_, _> = expected
+            .keys()
+            .chain(names.keys())
+            .map(|id| (id, ()))
+            .collect();
+        ids.into_keys()
+            .filter(|id| expected.get(id) != names.get(id))
+            .map(|id| {
+                format!(
+                    "workspace {id}: expected {:?}, got {:?}",
+                    expected.get(id),
+                    names.get(id)
+                )
+            })
+            .collect()
+    }
+}
+
+/// Renders the names of the windows of `hyprctl clients -j`, e.g. attached
+/// to a bug report. The focused window is the first of the focus history.
+pub fn render_clients_dump(
+    config: &str,
+    clients_json: &str,
+) -> Result<BTreeMap<i32, String>, Box<dyn Error>> {
+    let clients =
+        parse_legacy_clients(clients_json).map_err(|e| format!("Unable to parse clients: {e}"))?;
+    let active_client = clients
+        .iter()
+        .find(|client| client.focus_history_id == 0)
+        .map_or("0x0".to_string(), |client| client.address.to_string());
+    render(config, FixtureBackend::from_clients(clients, active_client))
+}
+
+/// Renames the workspaces of the backend once, like at startup, and returns
+/// the names.
+fn render(config: &str, backend: FixtureBackend) -> Result<BTreeMap<i32, String>, Box<dyn Error>> {
+    let backend = std::sync::Arc::new(backend);
+    let cfg = Config::from_toml(config, false)?;
+    let renamer = Renamer::with_backend(cfg, Args::default(), backend.clone());
+    renamer
+        .rename_workspace("fixture")
+        .map_err(|e| e.to_string())?;
+    let names = backend.names.lock().map_err(|e| e.to_string())?.clone();
+    Ok(names)
+}
+
+/// Serves the windows of a fixture, and keeps the last name of each
+/// workspace.
+struct FixtureBackend {
+    clients: Vec<Client>,
+    active_client: String,
+    names: Mutex<BTreeMap<i32, String>>,
+}
+
+impl FixtureBackend {
+    fn new(fixture: &Fixture) -> Result<Self, Box<dyn Error>> {
+        let clients = fixture
+            .clients
+            .iter()
+            .enumerate()
+            .map(|(index, client)| client.to_client(index))
+            .collect::<Result<Vec<_>, _>>()?;
+        let active_client = fixture
+            .clients
+            .iter()
+            .zip(&clients)
+            .find(|(fixture_client, _)| fixture_client.active)
+            .map_or("0x0".to_string(), |(_, client)| client.address.to_string());
+        Ok(FixtureBackend::from_clients(clients, active_client))
+    }
+
+    fn from_clients(clients: Vec<Client>, active_client: String) -> Self {
+        FixtureBackend {
+            clients,
+            active_client,
+            names: Mutex::new(BTreeMap::new()),
+        }
+    }
+}
+
+impl FixtureClient {
+    fn to_client(&self, index: usize) -> Result<Client, serde_json::Error> {
+        serde_json::from_value(serde_json::json!({
+            "address": format!("0x{:x}", index + 1),
+            "at": [self.at.0, self.at.1],
+            "size": [self.size.0, self.size.1],
+            "workspace": {
+                "id": self.workspace,
+                "name": self.workspace_name.clone().unwrap_or(self.workspace.to_string()),
+            },
+            "floating": self.floating,
+            "fullscreen": self.fullscreen,
+            "fullscreenClient": 0,
+            "monitor": 0,
+            "initialClass": self.initial_class.as_ref().unwrap_or(&self.class),
+            "class": self.class,
+            "initialTitle": self.initial_title.as_ref().unwrap_or(&self.title),
+            "title": self.title,
+            "pid": 1,
+            "xwayland": self.xwayland,
+            "pinned": self.pinned,
+            "grouped": [],
+            "mapped": true,
+            "swallowing": null,
+            "focusHistoryID": index,
+        }))
+    }
+}
+
+impl Backend for FixtureBackend {
+    fn clients(&self) -> Result<Vec<Client>, Box<dyn Error>> {
+        Ok(self.clients.clone())
+    }
+
+    fn active_client(&self) -> String {
+        self.active_client.clone()
+    }
+
+    fn active_workspace(&self) -> Option<ActiveWorkspace> {
+        None
+    }
+
+    fn visible_workspaces(&self) -> (Option<i32>, Vec<i32>) {
+        (None, vec![])
+    }
+
+    fn workspace_monitors(&self) -> Option<Vec<(i32, String)>> {
+        Some(vec![])
+    }
+
+    fn monitor_ids(&self) -> Option<HashMap<String, MonitorId>> {
+        None
+    }
+
+    fn workspace_windows(&self) -> Option<HashMap<i32, u16>> {
+        None
+    }
+
+    fn persistent_workspaces(&self) -> HashSet<i32> {
+        HashSet::new()
+    }
+
+    fn rename_workspace(&self, id: i32, name: &str) {
+        if let Ok(mut names) = self.names.lock() {
+            names.insert(id, name.to_string());
+        }
+    }
+
+    fn tag_window(&self, _address: &str, _previous: Option<&str>, _tag: &str) {}
+
+    fn set_env(&self, _name: &str, _value: &str) {}
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Runs every scenario of `src/renamer/fixtures`, add one there to cover
+    /// a rendering case.
+    #[test]
+    fn test_fixtures() {
+        let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("src/renamer/fixtures");
+        let mut paths: Vec<_> = fs::read_dir(dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().path())
+            .collect();
+        paths.sort();
+        assert!(!paths.is_empty());
+
+        for path in paths {
+            let fixture = Fixture::from_file(&path).unwrap();
+            assert!(fixture.expected.is_some(), "{path:?} expects nothing");
+            let names = fixture.render().unwrap();
+            let mismatches = fixture.mismatches(&names);
+            assert!(
+                mismatches.is_empty(),
+                "{path:?} ({}):\n{}",
+                fixture.description,
+                mismatches.join("\n")
+            );
+        }
+    }
+
+    #[test]
+    fn test_render_clients_dump() {
+        let client = |address: &str, workspace: i32, class: &str, focus: i8| {
+            serde_json::json!({
+                "address": address, "mapped": true, "hidden": false,
+                "at": [0, 0], "size": [800, 600],
+                "workspace": {"id": workspace, "name": workspace.to_string()},
+                "floating": false, "pseudo": false, "monitor": 0,
+                "class": class, "title": class, "initialClass": class, "initialTitle": class,
+                "pid": 1000, "xwayland": false, "pinned": false,
+                "fullscreen": 0, "fullscreenClient": 0,
+                "grouped": [], "tags": [], "swallowing": "0x0",
+                "focusHistoryID": focus, "inhibitingIdle": false
+            })
+        };
+        let dump = serde_json::json!([
+            client("0x55d1", 1, "kitty", 1),
+            client("0x55d2", 2, "firefox", 0),
+        ])
+        .to_string();
+        let config = "[class]\nkitty = \"term\"\nfirefox = \"web\"\n";
+
+        let names = render_clients_dump(config, &dump).unwrap();
+        assert_eq!(
+            names,
+            BTreeMap::from([(1, "1: term".to_string()), (2, "2: *web*".to_string())])
+        );
+        assert!(render_clients_dump(config, "{}").is_err());
+    }
+
+    #[test]
+    fn test_mismatches() {
+        let fixture: Fixture = toml::from_str(
+            r#"
+            [[clients]]
+            workspace = 1
+            class = "kitty"
+
+            [expected]
+            1 = "1: kitty"
+            2 = "2"
+            "#,
+        )
+        .unwrap();
+        let names = BTreeMap::from([(1, "1: kitty".to_string()), (3, "3".to_string())]);
+        assert_eq!(
+            fixture.mismatches(&names),
+            [
+                "workspace 2: expected Some(\"2\"), got None",
+                "workspace 3: expected None, got Some(\"3\")",
+            ]
+        );
+    }
+}
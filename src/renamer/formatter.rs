@@ -1,7 +1,14 @@
+use crate::config::{ActiveFallback, ClientSort, DominantBy};
+use crate::renamer::dedup::DedupPolicy;
+use crate::renamer::media::parse_media;
+use crate::renamer::progress::{parse_progress, to_progress_bar};
+use crate::renamer::urgency::urgency_vars;
 use crate::renamer::ConfigFile;
-use crate::renamer::IconStatus::*;
+use crate::renamer::IconStatus::{self, *};
 use crate::{AppClient, Renamer};
 use hyprland::data::FullscreenMode;
+use regex::Regex;
+use std::cmp::Reverse;
 use std::collections::HashMap;
 use strfmt::strfmt;
 
@@ -9,11 +16,17 @@ use strfmt::strfmt;
 pub struct AppWorkspace {
     pub id: i32,
     pub clients: Vec<AppClient>,
+    /// Clients left out by the exclude rules
+    pub excluded_count: usize,
 }
 
 impl AppWorkspace {
     pub fn new(id: i32, clients: Vec<AppClient>) -> Self {
-        AppWorkspace { id, clients }
+        AppWorkspace {
+            id,
+            clients,
+            excluded_count: 0,
+        }
     }
 }
 
@@ -24,31 +37,120 @@ impl Renamer {
         config: &ConfigFile,
     ) -> HashMap<i32, String> {
         let vars = HashMap::from([("delim".to_string(), config.format.delim.to_string())]);
+        let dedup_policy = DedupPolicy::from_format(&config.format);
         workspaces
             .iter()
             .map(|workspace| {
-                let mut counted =
-                    generate_counted_clients(workspace.clients.clone(), config.format.dedup);
-
-                let workspace_output = counted
-                    .iter_mut()
-                    .map(|(client, counter)| self.handle_new_client(client, *counter, config))
-                    .take(
-                        config
-                            .format
-                            .max_clients
-                            .map_or(usize::MAX, |max| max as usize),
-                    )
-                    .collect::<Vec<String>>();
-
-                let delimiter = formatter("{delim}", &vars);
-                let joined_string = workspace_output.join(&delimiter);
-
-                (workspace.id, joined_string)
+                let counted = generate_counted_clients(
+                    sort_clients(workspace.clients.clone(), config.format.client_sort),
+                    config.format.dedup && !config.format.dominant,
+                    &dedup_policy,
+                    &config.min_count_per_class,
+                    &config.no_dedup,
+                );
+                let mut counted = self.sort_rendered_clients(counted, config);
+
+                let joined_string = if config.format.dominant {
+                    let clients: Vec<AppClient> = counted.into_iter().map(|(c, _)| c).collect();
+                    self.render_dominant(&clients, config)
+                } else {
+                    let workspace_output = counted
+                        .iter_mut()
+                        .map(|(client, counter)| self.handle_new_client(client, *counter, config))
+                        .take(self.max_clients(workspace.id, config))
+                        .collect::<Vec<String>>();
+
+                    let delimiter = formatter("{delim}", &vars, config.format.placeholder_depth);
+                    workspace_output.join(&delimiter)
+                };
+                let padded_string = match config.format.pad_clients_to {
+                    Some(width) if !joined_string.is_empty() => {
+                        pad_to(&joined_string, width, &config.format.pad_char)
+                    }
+                    _ => joined_string,
+                };
+
+                (workspace.id, padded_string)
             })
             .collect()
     }
 
+    /// Renders the dominant client of the workspace only, and the number
+    /// of the other clients with `dominant_rest`.
+    fn render_dominant(&self, clients: &[AppClient], config: &ConfigFile) -> String {
+        let Some(dominant) = dominant_client(clients, config.format.dominant_by) else {
+            return String::new();
+        };
+        let client = self.handle_new_client(dominant, 1, config);
+        let rest = clients.len() - 1;
+        if rest == 0 {
+            return client;
+        }
+
+        let vars = HashMap::from([
+            ("delim".to_string(), config.format.delim.to_string()),
+            ("rest".to_string(), rest.to_string()),
+        ]);
+        let rest = formatter(
+            &config.format.dominant_rest,
+            &vars,
+            config.format.placeholder_depth,
+        );
+        client + &rest
+    }
+
+    /// Sorts the clients by their rendered string with `client_sort = "alpha"`,
+    /// the other orders are applied before counting them.
+    fn sort_rendered_clients(
+        &self,
+        mut counted: Vec<(AppClient, i32)>,
+        config: &ConfigFile,
+    ) -> Vec<(AppClient, i32)> {
+        if config.format.client_sort == ClientSort::Alpha {
+            counted.sort_by_cached_key(|(client, counter)| {
+                collation_key(&self.handle_new_client(client, *counter, config))
+            });
+        }
+        counted
+    }
+
+    /// Returns how many clients the workspace shows: the `max_clients` of
+    /// its monitor in `max_clients_per_monitor`, else `max_clients`.
+    fn max_clients(&self, id: i32, config: &ConfigFile) -> usize {
+        let per_monitor = self.workspace_monitors.lock().ok().and_then(|monitors| {
+            let monitor = monitors.get(&id)?;
+            config.format.max_clients_per_monitor.get(monitor).copied()
+        });
+        per_monitor
+            .or(config.format.max_clients)
+            .map_or(usize::MAX, |max| max as usize)
+    }
+
+    /// Returns the 1-based position of the active client in the rendered
+    /// clients of the workspace (0 if it is not there), and their number.
+    pub fn client_positions(
+        &self,
+        workspace: &AppWorkspace,
+        config: &ConfigFile,
+    ) -> (usize, usize) {
+        let max_clients = self.max_clients(workspace.id, config);
+        let counted = generate_counted_clients(
+            sort_clients(workspace.clients.clone(), config.format.client_sort),
+            config.format.dedup,
+            &DedupPolicy::from_format(&config.format),
+            &config.min_count_per_class,
+            &config.no_dedup,
+        );
+        let counted = self.sort_rendered_clients(counted, config);
+        let rendered = &counted[..counted.len().min(max_clients)];
+
+        let index_active = rendered
+            .iter()
+            .position(|(client, _)| client.is_active)
+            .map_or(0, |i| i + 1);
+        (index_active, rendered.len())
+    }
+
     fn handle_new_client(&self, client: &AppClient, counter: i32, config: &ConfigFile) -> String {
         let config_format = &config.format;
         let client = client.clone();
@@ -56,91 +158,409 @@ impl Renamer {
         let is_dedup = config_format.dedup && (counter > 1);
         let is_dedup_inactive_fullscreen = config_format.dedup_inactive_fullscreen;
 
-        let counter_sup = to_superscript(counter);
+        let counter_sup = to_superscript(counter, &config_format.counter_glyphs);
         let prev_counter = (counter - 1).to_string();
-        let prev_counter_sup = to_superscript(counter - 1);
+        let prev_counter_sup = to_superscript(counter - 1, &config_format.counter_glyphs);
         let delim = &config_format.delim.to_string();
 
-        let fmt_client = &config_format.client.to_string();
         let fmt_client_active = &config_format.client_active.to_string();
+        let fmt_client_modified = &config_format.client_modified.to_string();
+        let fmt_client_unmanaged = &config_format.client_unmanaged.to_string();
         let fmt_client_fullscreen = &config_format.client_fullscreen.to_string();
+        let fmt_client_floating = &config_format.client_floating.to_string();
+        let fmt_client_pinned = &config_format
+            .client_pinned
+            .as_ref()
+            .unwrap_or(&config_format.client_floating)
+            .to_string();
         let fmt_client_dup = &config_format.client_dup.to_string();
         let fmt_client_dup_fullscreen = &config_format.client_dup_fullscreen.to_string();
+        let fmt_client_maximized = &config_format.client_maximized.to_string();
+        let fmt_client_dup_maximized = &config_format.client_dup_maximized.to_string();
+        let fmt_client_active_fullscreen = &config_format.client_active_fullscreen.to_string();
+        let fmt_client_dup_active_fullscreen =
+            &config_format.client_dup_active_fullscreen.to_string();
+
+        let (artist, track) =
+            parse_media(&client.class, &client.title, &config.media).unwrap_or_default();
+        let progress = parse_progress(&client.title, &config.title_progress);
+        let progress_bar = progress
+            .map(|p| to_progress_bar(p, config.title_progress.bar_width))
+            .unwrap_or_default();
+
+        let modified = config
+            .modified_markers
+            .iter()
+            .find(|(rule, _)| rule.is_match(&client.title))
+            .map(|(_, marker)| marker.to_string());
 
         let mut vars = HashMap::from([
-            ("title".to_string(), client.title.clone()),
-            ("class".to_string(), client.class.clone()),
+            ("title".to_string(), escape_braces(&client.title)),
+            ("class".to_string(), escape_braces(&client.class)),
             ("counter".to_string(), counter.to_string()),
+            (
+                "has_active".to_string(),
+                match client.is_active {
+                    true => config_format.active_marker.to_string(),
+                    false => String::new(),
+                },
+            ),
             ("counter_unfocused".to_string(), prev_counter),
             ("counter_sup".to_string(), counter_sup),
             ("counter_unfocused_sup".to_string(), prev_counter_sup),
             ("delim".to_string(), delim.to_string()),
+            (
+                "progress".to_string(),
+                progress.map(|p| p.to_string()).unwrap_or_default(),
+            ),
+            ("progress_bar".to_string(), progress_bar),
+            ("artist".to_string(), escape_braces(&artist)),
+            ("track".to_string(), escape_braces(&track)),
+            ("modified".to_string(), modified.clone().unwrap_or_default()),
+            (
+                "xwayland".to_string(),
+                match client.is_xwayland {
+                    true => config_format.xwayland_marker.to_string(),
+                    false => String::new(),
+                },
+            ),
+            (
+                "class_color".to_string(),
+                class_color(&client.class, &config.class_colors).to_string(),
+            ),
+            (
+                "fullscreen".to_string(),
+                fullscreen_mode_name(client.is_fullscreen).to_string(),
+            ),
+            (
+                "fullscreen_client".to_string(),
+                fullscreen_mode_name(client.is_fullscreen_client).to_string(),
+            ),
         ]);
 
+        vars.extend(urgency_vars(
+            client.urgent_for,
+            &config_format.urgent_marker,
+        ));
+
         // get regex captures and merge them with vars
         if let Some(re_captures) = client.matched_rule.captures() {
+            let re_captures = re_captures
+                .into_iter()
+                .map(|(name, value)| (name, escape_braces(&value)))
+                .collect();
             merge_vars(&mut vars, re_captures);
         };
 
-        let icon = match (client.is_active, client.matched_rule.clone()) {
-            (true, c @ Inactive(_)) => {
-                vars.insert("default_icon".to_string(), c.icon());
+        let rule_icon = |c: IconStatus| {
+            if config_format.ascii_fallback {
+                ascii_icon(&c.icon(), &client.class, &config.fallback)
+            } else {
+                c.icon()
+            }
+        };
+
+        // Focused clients matching only an inactive rule
+        let active_default = match config_format.active_fallback {
+            ActiveFallback::Default => self
+                .find_icon("DEFAULT", "DEFAULT", "", "", true, config)
+                .filter(|c| matches!(c, Active(_))),
+            _ => None,
+        };
+        let use_inactive = config_format.active_fallback == ActiveFallback::UseInactive;
+        let icon = match (
+            client.is_active,
+            client.matched_rule.clone(),
+            active_default,
+        ) {
+            (true, c @ Inactive(_), _) if use_inactive => rule_icon(c),
+            (true, Inactive(_), Some(default)) => rule_icon(default),
+            (true, c @ Inactive(_), _) => {
+                vars.insert("default_icon".to_string(), rule_icon(c));
                 formatter(
                     &fmt_client_active.replace("{icon}", "{default_icon}"),
                     &vars,
+                    config_format.placeholder_depth,
                 )
             }
-            (_, c) => c.icon(),
+            (_, c, _) => rule_icon(c),
         };
 
         vars.insert("icon".to_string(), icon);
+
+        let fmt_client = config
+            .client_format_per_class
+            .iter()
+            .find(|(rule, _)| rule.is_match(&client.class))
+            .map_or(&config_format.client, |(_, format)| format);
         vars.insert("client".to_string(), fmt_client.to_string());
         vars.insert("client_dup".to_string(), fmt_client_dup.to_string());
         vars.insert(
             "client_fullscreen".to_string(),
             fmt_client_fullscreen.to_string(),
         );
+        vars.insert(
+            "client_dup_fullscreen".to_string(),
+            fmt_client_dup_fullscreen.to_string(),
+        );
+        vars.insert(
+            "client_floating".to_string(),
+            fmt_client_floating.to_string(),
+        );
 
-        if self.args.debug {
+        if self.args.debug(module_path!()) {
             println!("client: {client:#?}\nformatter vars => {vars:#?}");
+            if let Some(source) = config.rule_source(&client.matched_rule.rule()) {
+                println!("matched rule: {source}");
+            }
         }
 
-        let is_grouped = client.is_fullscreen != FullscreenMode::None
-            && (client.is_active || !is_dedup_inactive_fullscreen);
+        let is_fullscreen = client.is_fullscreen != FullscreenMode::None;
+        let is_maximized = client.is_fullscreen == FullscreenMode::Maximized;
+        let is_active_fullscreen = client.is_active && is_fullscreen && !is_maximized;
+        let is_grouped = is_fullscreen && (client.is_active || !is_dedup_inactive_fullscreen);
 
-        match (is_grouped, is_dedup) {
-            (true, true) => formatter(fmt_client_dup_fullscreen, &vars),
-            (false, true) => formatter(fmt_client_dup, &vars),
-            (true, false) => formatter(fmt_client_fullscreen, &vars),
-            (false, false) => formatter(fmt_client, &vars),
+        // Active fullscreen clients have their own formats, which default
+        // to the fullscreen ones, so they always take precedence. Active
+        // maximized clients keep the maximized formats.
+        let fmt_client = match (is_active_fullscreen, is_grouped, is_dedup) {
+            (true, _, true) => fmt_client_dup_active_fullscreen,
+            (true, _, false) => fmt_client_active_fullscreen,
+            (false, true, true) if is_maximized => fmt_client_dup_maximized,
+            (false, true, true) => fmt_client_dup_fullscreen,
+            (false, false, true) => fmt_client_dup,
+            (false, true, false) if is_maximized => fmt_client_maximized,
+            (false, true, false) => fmt_client_fullscreen,
+            (false, false, false) if client.is_unmanaged => fmt_client_unmanaged,
+            (false, false, false) if client.is_pinned => fmt_client_pinned,
+            (false, false, false) if client.is_floating => fmt_client_floating,
+            (false, false, false) if modified.is_some() => fmt_client_modified,
+            (false, false, false) => fmt_client,
+        };
+        let client_string = formatter(fmt_client, &vars, config_format.placeholder_depth);
+        if config_format.ascii_fallback {
+            to_ascii(&client_string)
+        } else {
+            client_string
         }
     }
 }
 
-pub fn formatter(fmt: &str, vars: &HashMap<String, String>) -> String {
+/// The color of the first `[class_colors]` rule matching the class, else
+/// the one of the `DEFAULT` rule, like icons.
+fn class_color<'a>(class: &str, colors: &'a [(Regex, String)]) -> &'a str {
+    [class, "DEFAULT"]
+        .iter()
+        .find_map(|class| colors.iter().find(|(rule, _)| rule.is_match(class)))
+        .map_or("", |(_, color)| color)
+}
+
+/// Braces of window data (titles, classes, captures) are swapped with
+/// these until the name is rendered, so they are never placeholders.
+const OPEN_BRACE: char = '\u{100000}';
+const CLOSE_BRACE: char = '\u{100001}';
+
+pub fn escape_braces(s: &str) -> String {
+    s.replace('{', &OPEN_BRACE.to_string())
+        .replace('}', &CLOSE_BRACE.to_string())
+}
+
+pub fn unescape_braces(s: &str) -> String {
+    s.replace(OPEN_BRACE, "{").replace(CLOSE_BRACE, "}")
+}
+
+/// Replaces the placeholders of the format with the vars.
+///
+/// Vars may contain placeholders too, e.g. `{client}` is `{icon}` by
+/// default, they are expanded up to `depth` times, so formats referencing
+/// each other can't loop forever.
+pub fn formatter(fmt: &str, vars: &HashMap<String, String>, depth: usize) -> String {
     let mut result = fmt.to_owned();
-    let mut i = 0;
-    loop {
+    for _ in 0..depth {
         if !(result.contains('{') && result.contains('}')) {
-            break result;
+            return result;
         }
         let formatted = strfmt(&result, vars).unwrap_or_else(|_| result.clone());
         if formatted == result {
-            break result;
+            return result;
         }
         result = formatted;
-        i += 1;
-        if i > 3 {
-            eprintln!("placeholders loop, aborting");
-            break result;
+    }
+    if strfmt(&result, vars).is_ok_and(|formatted| formatted != result) {
+        eprintln!("placeholders nested deeper than {depth}, aborting");
+    }
+    result
+}
+
+/// Returns the displayed length of a string, ignoring Pango markup tags
+/// and counting entities like `&amp;` as one character.
+pub fn visual_width(s: &str) -> usize {
+    let mut width = 0;
+    let mut in_tag = false;
+    let mut in_entity = false;
+    for c in s.chars() {
+        match (c, in_tag, in_entity) {
+            ('<', false, _) => in_tag = true,
+            ('>', true, _) => in_tag = false,
+            (_, true, _) => {}
+            ('&', _, false) => {
+                in_entity = true;
+                width += 1;
+            }
+            (';', _, true) => in_entity = false,
+            (_, _, true) => {}
+            _ => width += 1,
         }
     }
+    width
+}
+
+/// Pads the string on the right with `pad_char` up to `width` visible characters.
+pub fn pad_to(s: &str, width: usize, pad_char: &str) -> String {
+    let missing = width.saturating_sub(visual_width(s));
+    format!("{s}{}", pad_char.repeat(missing))
+}
+
+/// Truncates the string to `width` visible characters, ending with an ellipsis.
+///
+/// Pango tags are kept, so the markup stays balanced.
+pub fn truncate_to(s: &str, width: usize) -> String {
+    if visual_width(s) <= width {
+        return s.to_string();
+    }
+
+    // A single character is more useful than a lonely ellipsis
+    let keep = if width >= 2 { width - 1 } else { width };
+    let mut truncated = String::new();
+    let mut visible = 0;
+    let mut in_tag = false;
+    let mut in_entity = false;
+    let mut ellipsis_added = width < 2;
+    for c in s.chars() {
+        let is_visible = match (c, in_tag, in_entity) {
+            ('<', false, _) => {
+                in_tag = true;
+                false
+            }
+            ('>', true, _) => {
+                in_tag = false;
+                false
+            }
+            (_, true, _) => false,
+            ('&', _, false) => {
+                in_entity = true;
+                true
+            }
+            (';', _, true) => {
+                in_entity = false;
+                false
+            }
+            (_, _, true) => false,
+            _ => true,
+        };
+
+        let is_markup = in_tag || c == '>';
+        if is_visible {
+            visible += 1;
+        }
+        if visible <= keep || is_markup {
+            truncated.push(c);
+        } else if !ellipsis_added {
+            truncated.push('…');
+            ellipsis_added = true;
+        }
+    }
+    truncated
+}
+
+/// Returns the icon if it is plain ASCII, otherwise the fallback of the
+/// first matching class rule, or the class itself.
+pub fn ascii_icon(icon: &str, class: &str, fallback: &[(regex::Regex, String)]) -> String {
+    if icon.is_ascii() {
+        return icon.to_string();
+    }
+    fallback
+        .iter()
+        .find(|(rule, _)| rule.is_match(class))
+        .map_or(class, |(_, fallback)| fallback)
+        .to_string()
+}
+
+/// Replaces superscripts by digits, spaces by ASCII spaces and progress bar
+/// blocks by `#` and `.`, then drops the other non-ASCII glyphs.
+pub fn to_ascii(s: &str) -> String {
+    s.chars()
+        .filter_map(|c| match c {
+            c if c.is_ascii() => Some(c),
+            OPEN_BRACE | CLOSE_BRACE => Some(c),
+            '⁰' => Some('0'),
+            '¹' => Some('1'),
+            '²' => Some('2'),
+            '³' => Some('3'),
+            '⁴'..='⁹' => char::from_digit(c as u32 - '⁰' as u32, 10),
+            '░' => Some('.'),
+            '█'..='▏' => Some('#'),
+            c if c.is_whitespace() => Some(' '),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Returns a key sorting strings in a human order: letters and digits
+/// first, ignoring case and accents, then the raw string on ties. Strings
+/// without any letter nor digit, e.g. only icons, come last.
+pub fn collation_key(s: &str) -> (bool, String, String) {
+    let folded: String = s
+        .chars()
+        .filter(|c| c.is_alphanumeric() || c.is_whitespace())
+        .flat_map(char::to_lowercase)
+        .map(fold_accent)
+        .collect::<String>()
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ");
+    (folded.is_empty(), folded, s.to_string())
+}
+
+/// Folds the accented latin letters to their base letter.
+fn fold_accent(c: char) -> char {
+    match c {
+        'à'..='å' | 'ā' | 'ă' | 'ą' => 'a',
+        'ç' | 'ć' | 'č' => 'c',
+        'ď' | 'đ' => 'd',
+        'è'..='ë' | 'ē' | 'ė' | 'ę' | 'ě' => 'e',
+        'ğ' => 'g',
+        'ì'..='ï' | 'ī' | 'į' | 'ı' => 'i',
+        'ł' | 'ľ' => 'l',
+        'ñ' | 'ń' | 'ň' => 'n',
+        'ò'..='ö' | 'ø' | 'ō' | 'ő' => 'o',
+        'ř' => 'r',
+        'ś' | 'š' | 'ş' => 's',
+        'ť' | 'ţ' => 't',
+        'ù'..='ü' | 'ū' | 'ů' | 'ű' | 'ų' => 'u',
+        'ý' | 'ÿ' => 'y',
+        'ź' | 'ż' | 'ž' => 'z',
+        c => c,
+    }
+}
+
+pub fn sort_clients(mut clients: Vec<AppClient>, client_sort: ClientSort) -> Vec<AppClient> {
+    if client_sort == ClientSort::Position {
+        clients.sort_by_key(|c| c.position);
+    }
+    clients
 }
 
 pub fn generate_counted_clients(
     clients: Vec<AppClient>,
     need_dedup: bool,
+    policy: &DedupPolicy,
+    min_counts: &[(Regex, usize)],
+    no_dedup: &[Regex],
 ) -> Vec<(AppClient, i32)> {
+    let clients = filter_min_count(clients, min_counts);
+    // Clients of a `no_dedup` class are never grouped, with any client
+    let is_dedup = |client: &AppClient| !no_dedup.iter().any(|re| re.is_match(&client.class));
     if need_dedup {
         let mut sorted_clients = clients;
         sorted_clients.sort_by(|a, b| {
@@ -148,12 +568,14 @@ pub fn generate_counted_clients(
             let af = a.is_fullscreen != FullscreenMode::None;
             bf.cmp(&af)
         });
-        sorted_clients.sort_by(|a, b| b.is_active.cmp(&a.is_active));
+        sorted_clients.sort_by_key(|c| Reverse(c.is_active));
 
         sorted_clients
             .into_iter()
             .fold(vec![], |mut state, client| {
-                match state.iter_mut().find(|(c, _)| c == &client) {
+                let same =
+                    |c: &AppClient| is_dedup(c) && is_dedup(&client) && policy.same(c, &client);
+                match state.iter_mut().find(|(c, _)| same(c)) {
                     Some(c) => c.1 += 1,
                     None => state.push((client, 1)),
                 }
@@ -164,11 +586,59 @@ pub fn generate_counted_clients(
     }
 }
 
+/// Returns the client a workspace is named after, the first one on ties.
+fn dominant_client(clients: &[AppClient], dominant_by: DominantBy) -> Option<&AppClient> {
+    let largest = || {
+        clients
+            .iter()
+            .rev()
+            .max_by_key(|c| i32::from(c.size.0) * i32::from(c.size.1))
+    };
+    match dominant_by {
+        DominantBy::Area => largest(),
+        DominantBy::Focused => clients.iter().find(|c| c.is_active).or_else(largest),
+    }
+}
+
+/// Drops the clients whose class matches a `[min_count_per_class]` rule
+/// when the workspace has fewer clients matching it than its minimum.
+fn filter_min_count(clients: Vec<AppClient>, min_counts: &[(Regex, usize)]) -> Vec<AppClient> {
+    let too_few: Vec<&Regex> = min_counts
+        .iter()
+        .filter(|(re, min)| clients.iter().filter(|c| re.is_match(&c.class)).count() < *min)
+        .map(|(re, _)| re)
+        .collect();
+
+    clients
+        .into_iter()
+        .filter(|c| !too_few.iter().any(|re| re.is_match(&c.class)))
+        .collect()
+}
+
+pub fn fullscreen_mode_name(mode: FullscreenMode) -> &'static str {
+    match mode {
+        FullscreenMode::None => "none",
+        FullscreenMode::Maximized => "maximized",
+        FullscreenMode::Fullscreen => "fullscreen",
+        FullscreenMode::MaximizedFullscreen => "maximized_fullscreen",
+    }
+}
+
+/// Adds the regex captures to the vars, built-in vars take precedence.
 fn merge_vars(map1: &mut HashMap<String, String>, map2: HashMap<String, String>) {
-    map1.extend(map2);
+    for (key, value) in map2 {
+        map1.entry(key).or_insert(value);
+    }
 }
 
-pub fn to_superscript(number: i32) -> String {
+/// Renders the number with the glyph of the whole number if there is one,
+/// else with the glyphs of its digits, superscript by default.
+pub fn to_superscript(number: i32, glyphs: &HashMap<String, String>) -> String {
+    let number = number.to_string();
+    if let Some(glyph) = glyphs.get(&number) {
+        return glyph.to_string();
+    }
+
     let m: HashMap<_, _> = [
         ('0', "⁰"),
         ('1', "¹"),
@@ -184,7 +654,13 @@ pub fn to_superscript(number: i32) -> String {
     .into_iter()
     .collect();
 
-    number.to_string().chars().map(|c| m[&c]).collect()
+    number
+        .chars()
+        .map(|c| match glyphs.get(&c.to_string()) {
+            Some(glyph) => glyph.as_str(),
+            None => m.get(&c).copied().unwrap_or_default(),
+        })
+        .collect()
 }
 
 #[cfg(test)]
@@ -193,6 +669,7 @@ mod tests {
     use crate::renamer::IconConfig::*;
 
     #[test]
+    #[allow(clippy::bool_assert_comparison)]
     fn test_app_workspace_new() {
         let client = AppClient {
             class: String::from("Class"),
@@ -203,6 +680,14 @@ mod tests {
             is_fullscreen: FullscreenMode::Fullscreen,
             matched_rule: Inactive(Default(String::from("DefaultIcon"))),
             is_dedup_inactive_fullscreen: false,
+            size: (0, 0),
+            urgent_for: None,
+            is_unmanaged: false,
+            is_floating: false,
+            is_pinned: false,
+            is_xwayland: false,
+            position: (0, 0),
+            is_fullscreen_client: FullscreenMode::None,
         };
 
         let workspace = AppWorkspace::new(1, vec![client]);
@@ -221,4 +706,149 @@ mod tests {
             _ => panic!("Unexpected IconConfig value"),
         };
     }
+
+    #[test]
+    fn test_dominant_client() {
+        let client = |class: &str, size, is_active| AppClient {
+            class: class.to_string(),
+            initial_class: class.to_string(),
+            title: String::new(),
+            initial_title: String::new(),
+            is_active,
+            is_fullscreen: FullscreenMode::None,
+            matched_rule: Inactive(Default(class.to_string())),
+            is_dedup_inactive_fullscreen: false,
+            size,
+            urgent_for: None,
+            is_unmanaged: false,
+            is_floating: false,
+            is_pinned: false,
+            is_xwayland: false,
+            position: (0, 0),
+            is_fullscreen_client: FullscreenMode::None,
+        };
+        let clients = [
+            client("small", (10, 10), true),
+            client("large", (100, 50), false),
+            client("wide", (500, 10), false),
+        ];
+
+        let dominant = |dominant_by| dominant_client(&clients, dominant_by).map(|c| &c.class);
+        assert_eq!(dominant(DominantBy::Area).unwrap(), "large");
+        assert_eq!(dominant(DominantBy::Focused).unwrap(), "small");
+        assert_eq!(
+            dominant_client(&clients[1..], DominantBy::Focused).map(|c| c.class.as_str()),
+            Some("large")
+        );
+        assert!(dominant_client(&[], DominantBy::Area).is_none());
+    }
+
+    #[test]
+    fn test_collation_key() {
+        let mut names = vec!["zathura", "󰈹 Firefox", "", "émacs", "Emacs", "alacritty"];
+        names.sort_by_key(|s| collation_key(s));
+        assert_eq!(
+            names,
+            ["alacritty", "Emacs", "émacs", "󰈹 Firefox", "zathura", ""]
+        );
+        assert_eq!(collation_key("*Škoda*").1, "skoda");
+    }
+
+    #[test]
+    fn test_ascii_fallback() {
+        let fallback = vec![(regex::Regex::new("(?i)kitty").unwrap(), "T".to_string())];
+        assert_eq!(ascii_icon("term", "kitty", &fallback), "term");
+        assert_eq!(ascii_icon("\u{f120}", "kitty", &fallback), "T");
+        assert_eq!(ascii_icon("\u{f269}", "firefox", &fallback), "firefox");
+
+        assert_eq!(to_ascii("term²\u{202f}web¹⁰"), "term2 web10");
+        assert_eq!(to_ascii("██▌░░"), "###..");
+        assert_eq!(to_ascii("<b>\u{f120}</b>"), "<b></b>");
+    }
+
+    #[test]
+    fn test_visual_width_pad_to_and_truncate_to() {
+        assert_eq!(visual_width("term"), 4);
+        assert_eq!(visual_width("<span color='red'>term</span>"), 4);
+        assert_eq!(visual_width("a &amp; b"), 5);
+        assert_eq!(visual_width("²"), 1);
+
+        assert_eq!(pad_to("term", 6, " "), "term  ");
+        assert_eq!(pad_to("<b>term</b>", 6, "_"), "<b>term</b>__");
+        assert_eq!(pad_to("term term", 6, " "), "term term");
+
+        assert_eq!(truncate_to("term", 4), "term");
+        assert_eq!(truncate_to("terminal", 5), "term…");
+        assert_eq!(truncate_to("terminal", 1), "t");
+        assert_eq!(truncate_to("terminal", 0), "");
+        assert_eq!(truncate_to("<b>terminal</b>", 3), "<b>te…</b>");
+        assert_eq!(truncate_to("a &amp; b", 4), "a &amp;…");
+        assert_eq!(truncate_to("a &amp; b", 3), "a …");
+    }
+
+    #[test]
+    fn test_formatter_depth() {
+        let vars = HashMap::from([
+            ("client".to_string(), "{icon}".to_string()),
+            ("icon".to_string(), "{class}".to_string()),
+            ("class".to_string(), "kitty".to_string()),
+            ("loop".to_string(), "{loop}{loop}".to_string()),
+        ]);
+        assert_eq!(formatter("[{client}]", &vars, 4), "[kitty]");
+        assert_eq!(formatter("[{client}]", &vars, 1), "[{icon}]");
+        assert_eq!(formatter("[{client}]", &vars, 0), "[{client}]");
+        assert_eq!(formatter("{loop}", &vars, 2).len(), "{loop}".len() * 4);
+        assert_eq!(formatter("no placeholder", &vars, 4), "no placeholder");
+    }
+
+    #[test]
+    fn test_escaped_braces_are_literal() {
+        let vars = HashMap::from([
+            ("icon".to_string(), "term".to_string()),
+            ("title".to_string(), escape_braces("{icon} {unknown} }{")),
+        ]);
+        let formatted = formatter("{icon}: {title}", &vars, 4);
+        assert_eq!(unescape_braces(&formatted), "term: {icon} {unknown} }{");
+        assert_eq!(
+            unescape_braces(&to_ascii(&formatted)),
+            "term: {icon} {unknown} }{"
+        );
+    }
+
+    /// Renders random formats and titles, made of braces, placeholders
+    /// and text: the formatter must never panic nor expand the titles.
+    #[test]
+    fn test_formatter_fuzz() {
+        const PIECES: [&str; 10] = [
+            "{", "}", "{{", "}}", "{icon}", "{title}", "{client}", "{x", "a", "é",
+        ];
+        let mut seed: u64 = 0x2545f4914f6cdd1d;
+        let mut random = move |max: usize| {
+            // xorshift64
+            seed ^= seed << 13;
+            seed ^= seed >> 7;
+            seed ^= seed << 17;
+            (seed % max as u64) as usize
+        };
+        let random_string = |random: &mut dyn FnMut(usize) -> usize| {
+            (0..random(12))
+                .map(|_| PIECES[random(PIECES.len())])
+                .collect::<String>()
+        };
+
+        for _ in 0..2000 {
+            let fmt = random_string(&mut random);
+            let title = random_string(&mut random);
+            let vars = HashMap::from([
+                ("icon".to_string(), "term".to_string()),
+                ("client".to_string(), "{icon}".to_string()),
+                ("title".to_string(), escape_braces(&title)),
+            ]);
+
+            // Random formats may be invalid, they are kept as is then
+            formatter(&fmt, &vars, 4);
+            let with_title = unescape_braces(&formatter("<{title}>", &vars, 4));
+            assert_eq!(with_title, format!("<{title}>"));
+        }
+    }
 }
@@ -1,3 +1,4 @@
+use crate::config::{with_case_flag, ConfigMatchingRaw};
 use crate::renamer::IconConfig::*;
 use crate::renamer::IconStatus::*;
 use crate::renamer::{ConfigFile, Renamer};
@@ -33,6 +34,21 @@ impl IconConfig {
         captures
     }
 
+    /// Returns the same kind of match, with another icon and captures.
+    fn with(&self, icon: Icon, captures: Captures) -> IconConfig {
+        match self {
+            Default(_) => Default(icon),
+            Class(rule, _) => Class(rule.clone(), icon),
+            InitialClass(rule, _) => InitialClass(rule.clone(), icon),
+            TitleInClass(rule, _, _) => TitleInClass(rule.clone(), icon, captures),
+            TitleInInitialClass(rule, _, _) => TitleInInitialClass(rule.clone(), icon, captures),
+            InitialTitleInClass(rule, _, _) => InitialTitleInClass(rule.clone(), icon, captures),
+            InitialTitleInInitialClass(rule, _, _) => {
+                InitialTitleInInitialClass(rule.clone(), icon, captures)
+            }
+        }
+    }
+
     pub fn get(&self) -> (Rule, Icon, Captures) {
         match &self {
             Default(icon) => ("DEFAULT".to_string(), icon.to_string(), None),
@@ -67,10 +83,35 @@ impl IconStatus {
             Active(config) | Inactive(config) => config.captures(),
         }
     }
+
+    pub fn rule(&self) -> Rule {
+        match self {
+            Active(config) | Inactive(config) => config.get().0,
+        }
+    }
+
+    /// Puts the icon of this non-terminal match before the icon of the
+    /// next match, keeping the kind of this one. Captures of both are kept.
+    fn combine(self, next: IconStatus) -> IconStatus {
+        let icon = format!("{}{}", self.icon(), next.icon());
+        let captures = match (self.captures(), next.captures()) {
+            (Some(mut captures), Some(next)) => {
+                for (key, value) in next {
+                    captures.entry(key).or_insert(value);
+                }
+                Some(captures)
+            }
+            (captures, next) => captures.or(next),
+        };
+        match self {
+            Active(config) => Active(config.with(icon, captures)),
+            Inactive(config) => Inactive(config.with(icon, captures)),
+        }
+    }
 }
 
 impl Renamer {
-    fn find_icon(
+    pub fn find_icon(
         &self,
         initial_class: &str,
         class: &str,
@@ -106,72 +147,122 @@ impl Renamer {
             )
         };
 
-        find_icon_helper(
-            is_active,
-            Some(list_initial_title_in_initial_class),
-            None,
-            IconParams {
-                class: None,
-                title: None,
-                initial_class: Some(initial_class),
-                initial_title: Some(initial_title),
-            },
-        )
-        .or(find_icon_helper(
-            is_active,
-            Some(list_initial_title_in_class),
-            None,
-            IconParams {
-                class: Some(class),
-                title: None,
-                initial_class: None,
-                initial_title: Some(initial_title),
-            },
-        )
-        .or(find_icon_helper(
-            is_active,
-            Some(list_title_in_initial_class),
-            None,
-            IconParams {
-                class: None,
-                title: Some(title),
-                initial_class: Some(initial_class),
-                initial_title: None,
-            },
-        )
-        .or(find_icon_helper(
-            is_active,
-            Some(list_title_in_class),
-            None,
-            IconParams {
-                class: Some(class),
-                title: Some(title),
-                initial_class: None,
-                initial_title: None,
-            },
-        )
-        .or(find_icon_helper(
-            is_active,
-            None,
-            Some(list_initial_class),
-            IconParams {
-                class: None,
-                title: None,
-                initial_class: Some(initial_class),
-                initial_title: None,
-            },
-        ))
-        .or(find_icon_helper(
-            is_active,
-            None,
-            Some(list_class),
-            IconParams {
-                class: Some(class),
-                title: None,
-                initial_class: None,
-                initial_title: None,
-            },
-        )))))
+        let find_initial_title_in_initial_class = || {
+            find_icon_helper(
+                is_active,
+                Some(list_initial_title_in_initial_class),
+                None,
+                IconParams {
+                    class: None,
+                    title: None,
+                    initial_class: Some(initial_class),
+                    initial_title: Some(initial_title),
+                },
+            )
+        };
+        let find_initial_title_in_class = || {
+            find_icon_helper(
+                is_active,
+                Some(list_initial_title_in_class),
+                None,
+                IconParams {
+                    class: Some(class),
+                    title: None,
+                    initial_class: None,
+                    initial_title: Some(initial_title),
+                },
+            )
+        };
+        let find_title_in_initial_class = || {
+            find_icon_helper(
+                is_active,
+                Some(list_title_in_initial_class),
+                None,
+                IconParams {
+                    class: None,
+                    title: Some(title),
+                    initial_class: Some(initial_class),
+                    initial_title: None,
+                },
+            )
+        };
+        let find_title_in_class = || {
+            find_icon_helper(
+                is_active,
+                Some(list_title_in_class),
+                None,
+                IconParams {
+                    class: Some(class),
+                    title: Some(title),
+                    initial_class: None,
+                    initial_title: None,
+                },
+            )
+        };
+        let find_initial_class = || {
+            find_icon_helper(
+                is_active,
+                None,
+                Some(list_initial_class),
+                IconParams {
+                    class: None,
+                    title: None,
+                    initial_class: Some(initial_class),
+                    initial_title: None,
+                },
+            )
+        };
+        let find_class = || {
+            find_icon_helper(
+                is_active,
+                None,
+                Some(list_class),
+                IconParams {
+                    class: Some(class),
+                    title: None,
+                    initial_class: None,
+                    initial_title: None,
+                },
+            )
+        };
+
+        // With prefer_initial, rules on values that never change come first.
+        let cascade: [&dyn Fn() -> Option<IconStatus>; 6] = if config.matching.prefer_initial {
+            [
+                &find_initial_title_in_initial_class,
+                &find_initial_class,
+                &find_initial_title_in_class,
+                &find_title_in_initial_class,
+                &find_title_in_class,
+                &find_class,
+            ]
+        } else {
+            [
+                &find_initial_title_in_initial_class,
+                &find_initial_title_in_class,
+                &find_title_in_initial_class,
+                &find_title_in_class,
+                &find_initial_class,
+                &find_class,
+            ]
+        };
+
+        // A match of a continue rule is combined with the next match
+        let mut combined: Option<IconStatus> = None;
+        for status in cascade.iter().filter_map(|find| find()) {
+            let rule = status.rule();
+            let terminal = !config.matching.continue_rules.iter().any(|continue_rule| {
+                with_case_flag(continue_rule, config.matching.case_insensitive) == rule
+            });
+            combined = Some(match combined {
+                Some(previous) => previous.combine(status),
+                None => status,
+            });
+            if terminal {
+                break;
+            }
+        }
+        combined
     }
 
     pub fn parse_icon(
@@ -183,17 +274,43 @@ impl Renamer {
         is_active: bool,
         config: &ConfigFile,
     ) -> IconStatus {
-        let icon = self.find_icon(
-            &initial_class,
-            &class,
-            &initial_title,
-            &title,
-            false,
-            config,
-        );
-
-        let icon_active =
-            self.find_icon(&initial_class, &class, &initial_title, &title, true, config);
+        let short_initial_class = normalize_class(&initial_class, &config.matching);
+        let short_class = normalize_class(&class, &config.matching);
+        let find_icon = |is_active| {
+            self.find_icon(
+                &initial_class,
+                &class,
+                &initial_title,
+                &title,
+                is_active,
+                config,
+            )
+            .or_else(|| {
+                if short_class != class || short_initial_class != initial_class {
+                    self.find_icon(
+                        &short_initial_class,
+                        &short_class,
+                        &initial_title,
+                        &title,
+                        is_active,
+                        config,
+                    )
+                } else {
+                    None
+                }
+            })
+        };
+
+        let icon = find_icon(false);
+
+        let icon_active = find_icon(true);
+
+        // Classes no rule matches are shown by their first letter
+        if config.format.letter_fallback && icon.is_none() && icon_active.is_none() {
+            if let Some(letter) = letter_icon(&short_class, &config.format.letter_fallback_format) {
+                return Inactive(Default(letter));
+            }
+        }
 
         let icon_default = self
             .find_icon("DEFAULT", "DEFAULT", "", "", false, config)
@@ -219,6 +336,199 @@ impl Renamer {
     }
 }
 
+/// Returns the icon of a class from its first letter, in the format with
+/// `{letter}` (K for kitty) and `{letter_circled}` (Ⓚ).
+fn letter_icon(class: &str, format: &str) -> Option<String> {
+    let letter = class.chars().next()?.to_uppercase().next()?;
+    let circled = match letter {
+        'A'..='Z' => char::from_u32(0x24B6 + (letter as u32 - 'A' as u32)),
+        '1'..='9' => char::from_u32(0x2460 + (letter as u32 - '1' as u32)),
+        '0' => Some('⓪'),
+        _ => None,
+    }
+    .unwrap_or(letter);
+    Some(
+        format
+            .replace("{letter}", &letter.to_string())
+            .replace("{letter_circled}", &circled.to_string()),
+    )
+}
+
+/// Tells if the icon was matched on the class only, or is the default one,
+/// so a rule on something else than the title may replace it.
+fn is_class_icon(icon: &IconStatus, config: &ConfigFile) -> bool {
+    match icon {
+        Active(config_icon) | Inactive(config_icon) => match config_icon {
+            Class(..) | Default(_) => true,
+            InitialClass(..) => !config.matching.prefer_initial,
+            _ => false,
+        },
+    }
+}
+
+/// Returns the icon of the first `class_floating` rule matching the class
+/// of a floating window, `class_floating_active` first if it is focused.
+///
+/// Like the title history rules, they only replace an icon matched on the
+/// class, or the default one.
+pub fn floating_icon(
+    icon: IconStatus,
+    class: &str,
+    is_floating: bool,
+    is_active: bool,
+    config: &ConfigFile,
+) -> IconStatus {
+    if !is_floating {
+        return icon;
+    }
+    class_override_icon(
+        icon,
+        class,
+        is_active,
+        &[
+            (true, &config.class_floating_active),
+            (false, &config.class_floating),
+        ],
+        config,
+    )
+}
+
+/// Returns the icon of the first `class_xwayland` rule matching the class
+/// of an XWayland window, applied before the floating rules.
+pub fn xwayland_icon(
+    icon: IconStatus,
+    class: &str,
+    is_xwayland: bool,
+    is_active: bool,
+    config: &ConfigFile,
+) -> IconStatus {
+    if !is_xwayland {
+        return icon;
+    }
+    class_override_icon(
+        icon,
+        class,
+        is_active,
+        &[(false, &config.class_xwayland)],
+        config,
+    )
+}
+
+/// Replaces an icon matched on the class, or the default one, with the
+/// icon of the first rule of the lists matching the class, the active
+/// lists being skipped for unfocused windows.
+fn class_override_icon(
+    icon: IconStatus,
+    class: &str,
+    is_active: bool,
+    lists: &[(bool, &Vec<(regex::Regex, Icon)>)],
+    config: &ConfigFile,
+) -> IconStatus {
+    if !is_class_icon(&icon, config) {
+        return icon;
+    }
+
+    let short_class = normalize_class(class, &config.matching);
+    lists
+        .iter()
+        .filter(|(is_active_list, _)| is_active || !is_active_list)
+        .find_map(|&(is_active_list, list)| {
+            [class, short_class.as_str()].into_iter().find_map(|class| {
+                find_icon_helper(
+                    is_active_list,
+                    None,
+                    Some(list),
+                    IconParams {
+                        class: Some(class),
+                        title: None,
+                        initial_class: None,
+                        initial_title: None,
+                    },
+                )
+            })
+        })
+        .unwrap_or(icon)
+}
+
+/// Returns the icon of the first `title_history_in_class` rule matching a
+/// recent title of the window, the most recent first.
+///
+/// Those rules come after the rules on the current title: the icon only
+/// replaces an icon matched on the class, or the default one.
+pub fn title_history_icon(
+    icon: IconStatus,
+    class: &str,
+    history: &[String],
+    is_active: bool,
+    config: &ConfigFile,
+) -> IconStatus {
+    if !is_class_icon(&icon, config) || history.is_empty() {
+        return icon;
+    }
+
+    let short_class = normalize_class(class, &config.matching);
+    let lists = [
+        (true, &config.title_history_in_class_active),
+        (false, &config.title_history_in_class),
+    ];
+    lists
+        .into_iter()
+        .filter(|(is_active_list, _)| is_active || !is_active_list)
+        .find_map(|(is_active_list, list)| {
+            [class, short_class.as_str()].into_iter().find_map(|class| {
+                history.iter().rev().find_map(|title| {
+                    find_icon_helper(
+                        is_active_list,
+                        Some(list),
+                        None,
+                        IconParams {
+                            class: Some(class),
+                            title: Some(title),
+                            initial_class: None,
+                            initial_title: None,
+                        },
+                    )
+                })
+            })
+        })
+        .unwrap_or(icon)
+}
+
+/// Returns the class without the configured prefixes, and reduced to its
+/// short name when it looks like a reverse DNS name (`org.telegram.desktop`).
+pub fn normalize_class(class: &str, matching: &ConfigMatchingRaw) -> String {
+    let stripped = matching
+        .strip_class_prefixes
+        .iter()
+        .find_map(|prefix| class.strip_prefix(prefix.as_str()))
+        .unwrap_or(class);
+
+    if !matching.reverse_dns_short_name {
+        return stripped.to_string();
+    }
+
+    let segments: Vec<&str> = class.split('.').collect();
+    let is_reverse_dns = segments.len() > 2
+        && segments.iter().all(|s| {
+            !s.is_empty()
+                && s.chars()
+                    .all(|c| c.is_alphanumeric() || c == '-' || c == '_')
+        });
+
+    if !is_reverse_dns {
+        return stripped.to_string();
+    }
+
+    // Generic last segments don't say anything about the app.
+    const GENERIC: [&str; 5] = ["desktop", "app", "application", "client", "gui"];
+    segments
+        .iter()
+        .rev()
+        .find(|s| !GENERIC.contains(&s.to_lowercase().as_str()))
+        .map_or(stripped, |s| s)
+        .to_string()
+}
+
 pub struct IconParams<'a> {
     class: Option<&'a str>,
     title: Option<&'a str>,
@@ -303,10 +613,13 @@ fn find_icon_helper(
     }
 }
 
-fn get_captures(title: Option<&str>, rule: &regex::Regex) -> Captures {
+/// Returns the captures as `match0`, `match1`... and by name for named
+/// groups, e.g. `(?P<pkg>.+)` as `pkg`. A named group never replaces a
+/// `matchN` capture.
+pub fn get_captures(title: Option<&str>, rule: &regex::Regex) -> Captures {
     match title {
         Some(t) => rule.captures(t).map(|re_captures| {
-            re_captures
+            let mut captures: HashMap<String, String> = re_captures
                 .iter()
                 .enumerate()
                 .map(|(k, v)| {
@@ -315,8 +628,44 @@ fn get_captures(title: Option<&str>, rule: &regex::Regex) -> Captures {
                         v.map_or("", |m| m.as_str()).to_string(),
                     )
                 })
-                .collect()
+                .collect();
+
+            for name in rule.capture_names().flatten() {
+                let value = re_captures.name(name).map_or("", |m| m.as_str());
+                captures
+                    .entry(name.to_string())
+                    .or_insert_with(|| value.to_string());
+            }
+            captures
         }),
         _ => None,
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_letter_icon() {
+        assert_eq!(letter_icon("kitty", "{letter_circled}").unwrap(), "Ⓚ");
+        assert_eq!(letter_icon("zoom", "[{letter}]").unwrap(), "[Z]");
+        assert_eq!(letter_icon("1password", "{letter_circled}").unwrap(), "①");
+        assert_eq!(letter_icon("évince", "{letter_circled}").unwrap(), "É");
+        assert_eq!(letter_icon("", "{letter}"), None);
+    }
+
+    #[test]
+    fn test_get_captures() {
+        let rule =
+            regex::Regex::new(r"emerge: \((?P<current>\d+) of (?P<match1>\d+)\) (?P<pkg>.+)")
+                .unwrap();
+        let captures = get_captures(Some("emerge: (13 of 20) dev-lang/rust"), &rule).unwrap();
+
+        assert_eq!(captures["match1"], "13");
+        assert_eq!(captures["match2"], "20");
+        assert_eq!(captures["current"], "13");
+        assert_eq!(captures["pkg"], "dev-lang/rust");
+        assert_eq!(get_captures(Some("zsh"), &rule), None);
+    }
+}
@@ -1,15 +1,22 @@
-/// Renames the workspace if the given events occur.
+/// Queues an event for the renames when the given events occur.
 ///
 /// # Arguments
 ///
-/// * `$self` - The main struct containing the renameworkspace method.
+/// * `$self` - The main struct containing the queue_event method.
 /// * `$ev` - The event manager to attach event handlers.
-/// * `$x` - A list of events to attach the handlers to.
-macro_rules! rename_workspace_if {
-    ( $self: ident, $ev: ident, $( $x:ident ), * ) => {
+/// * `$x => |$data| $subject` - The events to attach the handlers to, and
+///   the workspace or window each event is about, from its data.
+macro_rules! queue_event_if {
+    ( $self: ident, $ev: ident, $( $x:ident => |$data:pat_param| $subject:expr ), * ) => {
         $(
         let this = $self.clone();
-        $ev.$x(move |_| _ = this.rename_workspace());
+        $ev.$x(move |$data| {
+            let event = stringify!($x)
+                .trim_start_matches("add_")
+                .trim_end_matches("_handler");
+            let subject = $subject;
+            _ = this.queue_event(event, AsRef::<str>::as_ref(&subject))
+        });
         )*
     };
 }
@@ -0,0 +1,51 @@
+use crate::config::Media;
+
+/// Extracts the artist and the track from the title of a media player,
+/// using the first matching pattern, with `artist` and `track` named groups.
+///
+/// A pattern without an `artist` group gives an empty artist, e.g. for
+/// `(?P<track>.+) - mpv`.
+pub fn parse_media(class: &str, title: &str, media: &Media) -> Option<(String, String)> {
+    if !media.classes.iter().any(|re| re.is_match(class)) {
+        return None;
+    }
+    media.patterns.iter().find_map(|re| {
+        let captures = re.captures(title)?;
+        let group = |name| {
+            captures
+                .name(name)
+                .map_or("", |m| m.as_str())
+                .trim()
+                .to_string()
+        };
+        Some((group("artist"), group("track")))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use regex::Regex;
+
+    #[test]
+    fn test_parse_media() {
+        let media = Media {
+            classes: vec![Regex::new("(?i)spotify|mpv").unwrap()],
+            patterns: vec![
+                Regex::new("^(?P<track>.+) - mpv$").unwrap(),
+                Regex::new("^(?P<artist>.+?) [-–—] (?P<track>.+)$").unwrap(),
+            ],
+        };
+
+        assert_eq!(
+            parse_media("Spotify", "Daft Punk – Around the World", &media),
+            Some(("Daft Punk".to_string(), "Around the World".to_string()))
+        );
+        assert_eq!(
+            parse_media("mpv", "holidays.mkv - mpv", &media),
+            Some((String::new(), "holidays.mkv".to_string()))
+        );
+        assert_eq!(parse_media("Spotify", "Spotify Premium", &media), None);
+        assert_eq!(parse_media("kitty", "Daft Punk - Da Funk", &media), None);
+    }
+}
@@ -0,0 +1,65 @@
+use crate::config::ConfigBehaviorRaw;
+use crate::renamer::backend::Backend;
+use crate::state::StateDir;
+use std::fs;
+
+/// The name of the focused workspace last mirrored outside the bar, to
+/// only write it again once it changed.
+#[derive(Debug, Default)]
+pub struct NameMirror {
+    last: Option<String>,
+}
+
+impl NameMirror {
+    /// Mirrors the name to the Hyprland env variable of
+    /// `behavior.mirror_env` and to the file of `behavior.mirror_file`,
+    /// e.g. for hyprlock labels. A relative file is placed in the state
+    /// directory.
+    pub fn mirror(
+        &mut self,
+        behavior: &ConfigBehaviorRaw,
+        state: &StateDir,
+        backend: &dyn Backend,
+        name: &str,
+    ) {
+        if behavior.mirror_env.is_none() && behavior.mirror_file.is_none() {
+            return;
+        }
+        if !self.changed(name) {
+            return;
+        }
+        if let Some(var) = &behavior.mirror_env {
+            backend.set_env(var, name);
+        }
+        if let Some(path) = &behavior.mirror_file {
+            let written = state
+                .place_file(path)
+                .and_then(|path| Ok(fs::write(path, format!("{name}\n"))?));
+            if let Err(e) = written {
+                eprintln!("Unable to write mirror file {path:?}: {e}");
+            }
+        }
+    }
+
+    fn changed(&mut self, name: &str) -> bool {
+        if self.last.as_deref() == Some(name) {
+            return false;
+        }
+        self.last = Some(name.to_string());
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_name_mirror_changed() {
+        let mut mirror = NameMirror::default();
+        assert!(mirror.changed("1: term"));
+        assert!(!mirror.changed("1: term"));
+        assert!(mirror.changed("2: web"));
+        assert!(mirror.changed("1: term"));
+    }
+}
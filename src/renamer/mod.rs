@@ -1,29 +1,135 @@
+mod audit;
+mod autospawn;
+mod backend;
+mod balance;
+mod compat;
+mod config_watch;
+mod conflict;
+mod dedup;
+mod event_queue;
+mod fixture;
 mod formatter;
 mod icon;
+mod media;
+mod mirror;
+mod notify;
+mod power;
+mod progress;
+mod rename_cmd;
+mod singleton;
+mod status;
+mod tags;
+mod throttle;
+mod title_history;
+mod toplevel;
+mod urgency;
+mod validate;
+mod window_moves;
 
 #[macro_use]
 mod macros;
 
-use crate::config::{Config, ConfigFile, ConfigFormatRaw};
+use crate::config::{
+    generate_config, local_path, Config, ConfigBehaviorRaw, ConfigFile, ConfigFormatRaw,
+    ConflictPolicy, PackLayers,
+};
+use crate::control::{ControlCommand, Subscribers};
 use crate::params::Args;
+use crate::state::StateDir;
+use audit::audit_dispatch;
+use autospawn::AutoSpawner;
+use backend::ActiveWorkspace;
+pub use backend::{Backend, HyprlandBackend};
+use balance::balance_widths;
+use config_watch::ConfigWatcher;
+use conflict::{is_foreign_name, sign};
+use dedup::DedupPolicy;
+use event_queue::EventQueue;
+pub use fixture::{render_clients_dump, Fixture};
 use formatter::*;
-use hyprland::data::{Client, Clients, FullscreenMode, Workspace};
-use hyprland::dispatch::*;
-use hyprland::event_listener::{EventListener, WorkspaceEventData};
-use hyprland::prelude::*;
-use hyprland::shared::Address;
-use icon::{IconConfig, IconStatus};
-use inotify::{Inotify, WatchMask};
-use std::collections::{HashMap, HashSet};
+use hyprland::data::{Client, FullscreenMode, WorkspaceBasic};
+use hyprland::event_listener::{EventListener, WorkspaceEventData, WorkspaceMovedEventData};
+use hyprland::shared::{Address, MonitorId};
+use icon::{floating_icon, title_history_icon, xwayland_icon, IconConfig, IconStatus};
+use mirror::NameMirror;
+use notify::notify_error;
+use power::PowerState;
+use rename_cmd::run_rename_cmd;
+use singleton::SingletonTracker;
+use status::{DaemonStatus, ListenerState};
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
 use std::error::Error;
-use std::path::PathBuf;
-use std::sync::{Arc, Mutex};
+use std::hash::{Hash, Hasher};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread;
+use std::time::{Duration, Instant, SystemTime};
+use tags::{tag_name, WindowTags};
+use throttle::{StartupThrottle, TickAction};
+use title_history::TitleHistory;
+pub use toplevel::ToplevelBackend;
+use urgency::{urgency_vars, UrgencyTracker};
+pub use validate::validate_regex;
+#[cfg(feature = "tui")]
+pub use validate::{matched_clients, open_clients, MatchedClient};
+use window_moves::WindowMoves;
 
 pub struct Renamer {
     known_workspaces: Mutex<HashSet<i32>>,
     cfg: Mutex<Config>,
+    config_changed: Condvar,
+    pack_layers: Mutex<Option<PackLayers>>,
+    shadow_cfg: Mutex<Option<ConfigFile>>,
+    total_clients: Mutex<usize>,
+    workspace_monitors: Mutex<HashMap<i32, String>>,
+    monitor_ids: Mutex<HashMap<String, MonitorId>>,
+    workspace_windows: Mutex<HashMap<i32, u16>>,
+    workspace_names: Mutex<HashMap<i32, String>>,
+    warned_foreign_names: Mutex<HashMap<i32, String>>,
+    persistent_workspaces: Mutex<HashSet<i32>>,
+    focused_workspace: Mutex<Option<i32>>,
+    visible_workspaces: Mutex<Vec<i32>>,
     args: Args,
+    state: StateDir,
     workspace_strings_cache: Mutex<HashMap<i32, String>>,
+    last_batch_hash: Mutex<Option<u64>>,
+    workspace_last_used: Mutex<HashMap<i32, u64>>,
+    startup_throttle: Mutex<StartupThrottle>,
+    power: Mutex<PowerState>,
+    debounced_event: Mutex<Option<(String, Instant)>>,
+    debounce_changed: Condvar,
+    event_queue: Mutex<EventQueue>,
+    event_queued: Condvar,
+    dispatch_lock: Mutex<()>,
+    shutting_down: Mutex<bool>,
+    client_icon_overrides: Mutex<HashMap<String, String>>,
+    locked_workspaces: Mutex<HashSet<i32>>,
+    status: Mutex<DaemonStatus>,
+    session_resumed: Condvar,
+    urgency: Mutex<UrgencyTracker>,
+    urgency_changed: Condvar,
+    spawned_watchers: Mutex<HashSet<Watcher>>,
+    title_history: Mutex<TitleHistory>,
+    window_tags: Mutex<WindowTags>,
+    singletons: Mutex<SingletonTracker>,
+    autospawner: Mutex<AutoSpawner>,
+    window_moves: Mutex<WindowMoves>,
+    name_mirror: Mutex<NameMirror>,
+    subscribers: Subscribers,
+    backend: Arc<dyn Backend>,
+}
+
+/// The threads renaming the workspaces without an event, started only
+/// when their feature is enabled.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum Watcher {
+    StartupStorm,
+    PowerState,
+    ConfigChanges,
+    Urgency,
+    Debounce,
+    Resync,
 }
 
 #[derive(Clone, Eq, Debug)]
@@ -38,15 +144,23 @@ pub struct AppClient {
     initial_title: String,
     is_active: bool,
     is_fullscreen: FullscreenMode,
+    is_fullscreen_client: FullscreenMode,
     is_dedup_inactive_fullscreen: bool,
     matched_rule: IconStatus,
+    position: (i16, i16),
+    size: (i16, i16),
+    urgent_for: Option<Duration>,
+    is_unmanaged: bool,
+    is_floating: bool,
+    is_pinned: bool,
+    is_xwayland: bool,
 }
 
+// Only the compositor fullscreen state is considered, so clients toggling
+// their own fullscreen state are still deduplicated together.
 impl PartialEq for AppClient {
     fn eq(&self, other: &Self) -> bool {
-        self.matched_rule == other.matched_rule
-            && self.is_active == other.is_active
-            && (self.is_dedup_inactive_fullscreen || self.is_fullscreen == other.is_fullscreen)
+        DedupPolicy::default().same(self, other)
     }
 }
 
@@ -56,58 +170,544 @@ impl AppClient {
         is_active: bool,
         is_dedup_inactive_fullscreen: bool,
         matched_rule: IconStatus,
+        urgent_for: Option<Duration>,
     ) -> Self {
         AppClient {
+            is_unmanaged: client.pid <= 0,
+            is_floating: client.floating,
+            is_pinned: client.pinned,
+            is_xwayland: client.xwayland,
             initial_class: client.initial_class,
             class: client.class,
             initial_title: client.initial_title,
             title: client.title,
             is_active,
             is_fullscreen: client.fullscreen,
+            is_fullscreen_client: client.fullscreen_client,
             is_dedup_inactive_fullscreen,
             matched_rule,
+            position: client.at,
+            size: client.size,
+            urgent_for,
         }
     }
 }
 
 impl Renamer {
     pub fn new(cfg: Config, args: Args) -> Arc<Self> {
+        Renamer::with_backend(cfg, args, Arc::new(HyprlandBackend::default()))
+    }
+
+    pub fn with_backend(cfg: Config, args: Args, backend: Arc<dyn Backend>) -> Arc<Self> {
         Arc::new(Renamer {
             known_workspaces: Mutex::new(HashSet::default()),
             cfg: Mutex::new(cfg),
+            config_changed: Condvar::new(),
+            pack_layers: Mutex::new(None),
+            shadow_cfg: Mutex::new(None),
+            total_clients: Mutex::new(0),
+            workspace_monitors: Mutex::new(HashMap::new()),
+            monitor_ids: Mutex::new(HashMap::new()),
+            workspace_windows: Mutex::new(HashMap::new()),
+            workspace_names: Mutex::new(HashMap::new()),
+            warned_foreign_names: Mutex::new(HashMap::new()),
+            persistent_workspaces: Mutex::new(HashSet::new()),
+            focused_workspace: Mutex::new(None),
+            visible_workspaces: Mutex::new(Vec::new()),
+            state: StateDir::new(args.state_dir.as_ref().map(Into::into)),
             args,
             workspace_strings_cache: Mutex::new(HashMap::new()),
+            last_batch_hash: Mutex::new(None),
+            workspace_last_used: Mutex::new(HashMap::new()),
+            startup_throttle: Mutex::new(StartupThrottle::new(Instant::now())),
+            power: Mutex::new(PowerState::default()),
+            debounced_event: Mutex::new(None),
+            debounce_changed: Condvar::new(),
+            event_queue: Mutex::new(EventQueue::default()),
+            event_queued: Condvar::new(),
+            dispatch_lock: Mutex::new(()),
+            shutting_down: Mutex::new(false),
+            client_icon_overrides: Mutex::new(HashMap::new()),
+            locked_workspaces: Mutex::new(HashSet::new()),
+            status: Mutex::new(DaemonStatus::new(SystemTime::now())),
+            session_resumed: Condvar::new(),
+            urgency: Mutex::new(UrgencyTracker::default()),
+            urgency_changed: Condvar::new(),
+            spawned_watchers: Mutex::new(HashSet::new()),
+            title_history: Mutex::new(TitleHistory::default()),
+            window_tags: Mutex::new(WindowTags::default()),
+            singletons: Mutex::new(SingletonTracker::default()),
+            autospawner: Mutex::new(AutoSpawner::default()),
+            window_moves: Mutex::new(WindowMoves::default()),
+            name_mirror: Mutex::new(NameMirror::default()),
+            subscribers: Subscribers::default(),
+            backend,
         })
     }
 
-    pub fn rename_workspace(&self) -> Result<(), Box<dyn Error + '_>> {
+    /// Renames the workspaces, and keeps track of the outcome for `status`.
+    pub fn rename_workspace(&self, event: &str) -> Result<(), Box<dyn Error + '_>> {
+        // Nothing is renamed while the session is idle, resuming resyncs
+        if self.status.lock()?.is_idle() {
+            return Ok(());
+        }
+        let result = self.try_rename_workspace(event).map_err(|e| e.to_string());
+        let mut status = self.status.lock()?;
+        match &result {
+            Ok(()) => status.on_render(SystemTime::now()),
+            Err(e) => status.on_error(format!("{event}: {e}")),
+        }
+        Ok(result?)
+    }
+
+    fn try_rename_workspace(&self, event: &str) -> Result<(), Box<dyn Error + '_>> {
+        // One rename at a time, and none once the names are reset
+        let _dispatching = self.dispatch_lock.lock()?;
+        if self.is_shutting_down() {
+            return Ok(());
+        }
+
         // Config
         let config = &self.cfg.lock()?.config.clone();
 
-        // Rename active workspace if empty
-        rename_empty_workspace(config);
-
         // Filter clients
-        let clients = get_filtered_clients(config);
+        let mut clients = self.backend.clients()?;
+        self.window_moves.lock()?.apply(&mut clients);
+        let (clients, mut excluded_counts) =
+            filter_clients(config, clients, self.args.debug(module_path!()));
 
         // Get the active client
-        let active_client = get_active_client();
+        let active_client = self.backend.active_client();
+
+        // Forget the icons set for closed windows, and the urgency of
+        // closed and focused windows
+        let addresses: HashSet<String> = clients.iter().map(|c| c.address.to_string()).collect();
+        self.client_icon_overrides
+            .lock()?
+            .retain(|address, _| addresses.contains(address));
+        self.urgency.lock()?.on_clients(&active_client, &addresses);
+        self.title_history.lock()?.retain(&addresses);
+        self.window_tags.lock()?.retain(&addresses);
+
+        // Singleton classes are only shown on the workspace of their last
+        // focused window, the others count as excluded
+        let clients = {
+            let mut singletons = self.singletons.lock()?;
+            singletons.retain(&addresses);
+            let active = clients
+                .iter()
+                .find(|c| c.address.to_string() == active_client);
+            if let Some(client) = active.filter(|c| is_singleton(config, &c.class)) {
+                singletons.record(&client.class, &active_client);
+            }
+            let (clients, hidden_counts) = hide_singletons(config, clients, &singletons);
+            for (id, count) in hidden_counts {
+                *excluded_counts.entry(id).or_insert(0) += count;
+            }
+            clients
+        };
+
+        if config.behavior.rewrite_titles {
+            self.tag_windows(&clients, config)?;
+        }
+
+        // Persistent workspaces are named even if they never had a client
+        if uses_persistent_vars(&config.format) {
+            let persistent_ids = self.backend.persistent_workspaces();
+            self.known_workspaces.lock()?.extend(&persistent_ids);
+            *self.persistent_workspaces.lock()? = persistent_ids;
+        }
 
         // Get workspaces based on open clients
-        let workspaces = self.get_workspaces_from_clients(clients, active_client, config)?;
+        let workspaces =
+            self.get_workspaces_from_clients(clients, &excluded_counts, active_client, config)?;
         let workspace_ids: HashSet<_> = workspaces.iter().map(|w| w.id).collect();
 
-        // Generate workspace strings
-        let workspaces_strings = self.generate_workspaces_string(workspaces, config);
+        // Track the monitor of each workspace
+        self.refresh_workspace_monitors(&workspace_ids)?;
+
+        // The window counts of the compositor, only fetched when shown
+        if uses_workspace_windows_var(&config.format) {
+            *self.workspace_windows.lock()? = self.backend.workspace_windows().unwrap_or_default();
+        }
+
+        // The focused workspace may be empty, with a format of its own
+        let (focused_id, visible_ids) = self.backend.visible_workspaces();
+        *self.focused_workspace.lock()? = focused_id;
+        *self.visible_workspaces.lock()? = visible_ids.clone();
+
+        // Generate variables shared by all workspaces
+        *self.total_clients.lock()? = count_clients(&workspaces);
+        let global_vars = self.generate_global_vars(&workspaces)?;
+
+        // Generate workspace names
+        let workspaces_names = self.generate_workspaces_names(workspaces, &global_vars, config);
+
+        // Rename active workspace if empty, unless disabled
+        let mut batch = workspaces_names.clone();
+        let mut batch_ids = workspace_ids;
+        let active_workspace = self.backend.active_workspace();
+        let spawn_command = self
+            .autospawner
+            .lock()?
+            .on_active_workspace(active_workspace.as_ref(), &config.autospawn);
+        if let Some(command) = spawn_command {
+            autospawn::spawn(command);
+        }
+        let active_workspace = active_workspace.filter(|_| config.behavior.rename_empty);
+        let persistent_ids = self.persistent_workspaces.lock()?.clone();
+        let monitor_ids = self.monitor_ids.lock()?.clone();
+        if let Some((id, name)) = render_empty_workspace(
+            active_workspace,
+            config,
+            &global_vars,
+            &persistent_ids,
+            &monitor_ids,
+        ) {
+            batch.entry(id).or_insert(name);
+            batch_ids.insert(id);
+        }
+
+        // Leave the workspaces of the other monitors alone
+        if !config.behavior.monitors.is_empty() {
+            self.refresh_workspace_monitors(&batch_ids)?;
+            let workspace_monitors = self.workspace_monitors.lock()?;
+            let is_managed = |id: &i32| is_managed(&config.behavior, &workspace_monitors, *id);
+            batch.retain(|id, _| is_managed(id));
+            batch_ids.retain(is_managed);
+        }
+
+        // Workspaces named by another tool
+        if config.behavior.on_conflict != ConflictPolicy::Overwrite {
+            let workspace_names = self.workspace_names.lock()?;
+            let foreign: Vec<i32> = batch
+                .keys()
+                .filter(|id| {
+                    workspace_names
+                        .get(id)
+                        .is_some_and(|name| is_foreign_name(**id, name))
+                })
+                .copied()
+                .collect();
+            let mut warned = self.warned_foreign_names.lock()?;
+            for id in foreign {
+                let name = &workspace_names[&id];
+                match config.behavior.on_conflict {
+                    ConflictPolicy::Skip => _ = batch.remove(&id),
+                    // Once per name, not on every event
+                    _ if warned.get(&id) != Some(name) => {
+                        eprintln!("Workspace {id} was named '{name}' by another tool, renaming it");
+                        warned.insert(id, name.clone());
+                    }
+                    _ => {}
+                }
+            }
+        }
+
+        // Keep the whole bar under the configured width
+        if let Some(id) = focused_id {
+            self.mark_workspace_used(id)?;
+        }
+        if let Some(max_width) = config.format.total_max_width {
+            batch = balance_widths(&batch, max_width, &*self.workspace_last_used.lock()?);
+        }
+
+        // Show the name of the focused workspace outside the bar too
+        if let Some(id) = focused_id {
+            let name = batch.get(&id).cloned().unwrap_or_else(|| id.to_string());
+            self.name_mirror.lock()?.mirror(
+                &config.behavior,
+                &self.state,
+                self.backend.as_ref(),
+                &name,
+            );
+        }
+
+        // Skip the whole batch when nothing changed, e.g. on a no-op reload
+        let batch_hash = hash_workspaces_names(&batch);
+        if config.behavior.cache && *self.last_batch_hash.lock()? == Some(batch_hash) {
+            self.record_cache_stats(batch.len(), 0, true)?;
+            return self.log_shadow_diff(&workspaces_names, event);
+        }
 
-        // Filter out unchanged workspaces
-        let altered_workspaces = self.get_altered_workspaces(&workspaces_strings)?;
+        // Filter out unchanged workspaces, unless the cache is disabled
+        let mut altered_workspaces = if config.behavior.cache {
+            let altered_workspaces = self.get_altered_workspaces(&batch)?;
+            let misses = altered_workspaces.len();
+            self.record_cache_stats(batch.len() - misses, misses, false)?;
+            altered_workspaces
+        } else {
+            batch
+        };
 
-        altered_workspaces.iter().for_each(|(&id, clients)| {
-            rename_cmd(id, clients, &config.format, &config.workspaces_name);
+        // Locked workspaces keep their name, and in low power mode or with
+        // only_visible, hidden workspaces are renamed once visible
+        let altered_count = altered_workspaces.len();
+        let only_visible = config.behavior.only_visible || self.is_low_power(config)?;
+        let locked_ids = self.locked_workspaces.lock()?.clone();
+        altered_workspaces.retain(|id, _| {
+            !locked_ids.contains(id)
+                && (!only_visible || Some(*id) == focused_id || visible_ids.contains(id))
         });
+        let batch_hash = (altered_workspaces.len() == altered_count).then_some(batch_hash);
+
+        // Rename focused and visible workspaces first
+        let ordered_ids = sort_by_priority(altered_workspaces.keys(), focused_id, &visible_ids);
+
+        {
+            let cache = self.workspace_strings_cache.lock()?;
+            for id in &ordered_ids {
+                // Leave the remaining names to the reset
+                if self.is_shutting_down() {
+                    return Ok(());
+                }
+                let name = &altered_workspaces[id];
+                let old = cache.get(id).map(String::as_str);
+                audit_dispatch(&config.behavior, &self.state, *id, old, name, event);
+                self.publish_rename(*id, old, name, &config.format);
+                self.dispatch_rename(&config.behavior, *id, name);
+            }
+        }
+
+        self.update_cache(&altered_workspaces, &batch_ids)?;
+        *self.last_batch_hash.lock()? = batch_hash;
+
+        // Compare with the candidate config, without renaming anything
+        self.log_shadow_diff(&workspaces_names, event)?;
+
+        Ok(())
+    }
+
+    /// Generates the final name of every workspace, from its clients string
+    /// and the workspace formats.
+    fn generate_workspaces_names(
+        &self,
+        workspaces: Vec<AppWorkspace>,
+        global_vars: &HashMap<String, String>,
+        config: &ConfigFile,
+    ) -> HashMap<i32, String> {
+        let workspace_monitors = self
+            .workspace_monitors
+            .lock()
+            .map(|m| m.clone())
+            .unwrap_or_default();
+        let monitor_ids = self
+            .monitor_ids
+            .lock()
+            .map(|m| m.clone())
+            .unwrap_or_default();
+        let fullscreen_counts: HashMap<i32, usize> = workspaces
+            .iter()
+            .map(|w| (w.id, count_fullscreen_clients(&w.clients)))
+            .collect();
+        let excluded_counts: HashMap<i32, usize> = workspaces
+            .iter()
+            .map(|w| (w.id, w.excluded_count))
+            .collect();
+        // As counted by the compositor, else the clients we know of
+        let reported_windows = self
+            .workspace_windows
+            .lock()
+            .map(|w| w.clone())
+            .unwrap_or_default();
+        let workspace_windows: HashMap<i32, usize> = workspaces
+            .iter()
+            .map(|w| {
+                let windows = reported_windows.get(&w.id).map(|&n| n as usize);
+                (w.id, windows.unwrap_or(w.clients.len() + w.excluded_count))
+            })
+            .collect();
+        let client_positions: HashMap<i32, (usize, usize)> = workspaces
+            .iter()
+            .map(|w| (w.id, self.client_positions(w, config)))
+            .collect();
+        let workspace_names = self
+            .workspace_names
+            .lock()
+            .map(|m| m.clone())
+            .unwrap_or_default();
+        let persistent_ids = self
+            .persistent_workspaces
+            .lock()
+            .map(|p| p.clone())
+            .unwrap_or_default();
+        let focused_id = self.focused_workspace.lock().map_or(None, |f| *f);
+        let visible_ids = self
+            .visible_workspaces
+            .lock()
+            .map(|v| v.clone())
+            .unwrap_or_default();
+        // The workspace is urgent since its oldest urgent client is
+        let urgent_for: HashMap<i32, Option<Duration>> = workspaces
+            .iter()
+            .map(|w| (w.id, w.clients.iter().filter_map(|c| c.urgent_for).max()))
+            .collect();
+
+        self.generate_workspaces_string(workspaces, config)
+            .into_iter()
+            .map(|(id, clients)| {
+                let mut vars = global_vars.clone();
+                let monitor = workspace_monitors.get(&id).cloned().unwrap_or_default();
+                vars.insert(
+                    "monitor_id".to_string(),
+                    monitor_id_var(&monitor_ids, &monitor),
+                );
+                vars.insert("monitor".to_string(), monitor);
+                vars.insert(
+                    "fullscreen_count".to_string(),
+                    fullscreen_counts.get(&id).copied().unwrap_or(0).to_string(),
+                );
+                vars.insert(
+                    "excluded_count".to_string(),
+                    excluded_counts.get(&id).copied().unwrap_or(0).to_string(),
+                );
+                vars.insert(
+                    "workspace_windows".to_string(),
+                    workspace_windows.get(&id).copied().unwrap_or(0).to_string(),
+                );
+                let (index_active, count) = client_positions.get(&id).copied().unwrap_or((0, 0));
+                vars.insert("client_index_active".to_string(), index_active.to_string());
+                vars.insert("client_count".to_string(), count.to_string());
+                vars.insert(
+                    "special_name".to_string(),
+                    special_name(id, workspace_names.get(&id)),
+                );
+                vars.extend(urgency_vars(
+                    urgent_for.get(&id).copied().flatten(),
+                    &config.format.urgent_marker,
+                ));
+                let is_persistent = persistent_ids.contains(&id);
+                vars.insert(
+                    "persistent".to_string(),
+                    persistent_var(is_persistent, &config.format),
+                );
+                vars.insert(
+                    "is_monitor_active".to_string(),
+                    monitor_active_var(visible_ids.contains(&id), &config.format),
+                );
+                let name = render_workspace(
+                    id,
+                    &clients,
+                    &config.format,
+                    &config.workspaces_name,
+                    &vars,
+                    is_persistent,
+                    Some(id) == focused_id,
+                );
+                (id, name)
+            })
+            .collect()
+    }
+
+    /// Fetches the monitor of the workspaces we don't know yet,
+    /// moved workspaces are then tracked with the workspace moved event.
+    /// The ids of the monitors are fetched again once one is unknown.
+    fn refresh_workspace_monitors(
+        &self,
+        workspace_ids: &HashSet<i32>,
+    ) -> Result<(), Box<dyn Error + '_>> {
+        let mut workspace_monitors = self.workspace_monitors.lock()?;
+        if !workspace_ids
+            .iter()
+            .all(|id| workspace_monitors.contains_key(id))
+        {
+            if let Some(monitors) = self.backend.workspace_monitors() {
+                workspace_monitors.extend(monitors);
+            }
+        }
+
+        let mut monitor_ids = self.monitor_ids.lock()?;
+        if workspace_monitors
+            .values()
+            .any(|monitor| !monitor_ids.contains_key(monitor))
+        {
+            if let Some(ids) = self.backend.monitor_ids() {
+                *monitor_ids = ids;
+            }
+        }
+        Ok(())
+    }
+
+    /// Records the move of a window, and renders the workspace it left again,
+    /// even if its name seems unchanged.
+    fn move_window(
+        &self,
+        address: &str,
+        workspace: WorkspaceBasic,
+    ) -> Result<(), Box<dyn Error + '_>> {
+        let origin = self.window_moves.lock()?.on_move(address, workspace);
+        if let Some(origin) = origin {
+            self.known_workspaces.lock()?.insert(origin);
+            self.workspace_strings_cache.lock()?.remove(&origin);
+            *self.last_batch_hash.lock()? = None;
+        }
+        Ok(())
+    }
+
+    /// Tracks the new monitor of a workspace, returns true if the workspaces
+    /// need to be renamed because their format or `rename_cmd` depends on
+    /// the monitor, or only the workspaces of some monitors are managed.
+    fn move_workspace(
+        &self,
+        wt: &WorkspaceMovedEventData,
+        config: &ConfigFile,
+    ) -> Result<bool, Box<dyn Error + '_>> {
+        let previous = self
+            .workspace_monitors
+            .lock()?
+            .insert(wt.id, wt.monitor.clone());
+        if previous.as_ref() == Some(&wt.monitor) {
+            return Ok(false);
+        }
+
+        // The command runs again with the new monitor, even for the same name
+        let rename_cmd_uses_monitor = rename_cmd_uses_monitor_vars(&config.behavior);
+        if rename_cmd_uses_monitor {
+            self.workspace_strings_cache.lock()?.remove(&wt.id);
+            *self.last_batch_hash.lock()? = None;
+        }
+        Ok(rename_cmd_uses_monitor
+            || uses_monitor_vars(&config.format)
+            || !config.behavior.monitors.is_empty())
+    }
+
+    /// Keeps the pack layers of the config, so a changed local pack is
+    /// reloaded alone.
+    pub fn set_pack_layers(&self, layers: Option<PackLayers>) -> Result<(), Box<dyn Error + '_>> {
+        *self.pack_layers.lock()? = layers;
+        Ok(())
+    }
+
+    pub fn set_shadow_config(&self, config: ConfigFile) -> Result<(), Box<dyn Error + '_>> {
+        *self.shadow_cfg.lock()? = Some(config);
+        Ok(())
+    }
+
+    fn log_shadow_diff(
+        &self,
+        workspaces_names: &HashMap<i32, String>,
+        event: &str,
+    ) -> Result<(), Box<dyn Error + '_>> {
+        let shadow_config = match &*self.shadow_cfg.lock()? {
+            Some(shadow_config) => shadow_config.clone(),
+            None => return Ok(()),
+        };
 
-        self.update_cache(&altered_workspaces, &workspace_ids)?;
+        let (clients, excluded_counts) =
+            filter_clients(&shadow_config, self.backend.clients()?, false);
+        let active_client = self.backend.active_client();
+        let workspaces = self.get_workspaces_from_clients(
+            clients,
+            &excluded_counts,
+            active_client,
+            &shadow_config,
+        )?;
+        let global_vars = self.generate_global_vars(&workspaces)?;
+        let shadow_names = self.generate_workspaces_names(workspaces, &global_vars, &shadow_config);
+
+        for (id, active, candidate) in diff_workspaces_names(workspaces_names, &shadow_names) {
+            println!("shadow ({event}): workspace {id}: {active:?} => {candidate:?}");
+        }
 
         Ok(())
     }
@@ -129,6 +729,185 @@ impl Renamer {
             .collect())
     }
 
+    /// Renames the workspace with `rename_cmd`, else with the backend.
+    /// Names are signed, unless names of other tools are overwritten.
+    fn dispatch_rename(&self, behavior: &ConfigBehaviorRaw, id: i32, name: &str) {
+        let signed;
+        let name = match behavior.on_conflict {
+            ConflictPolicy::Overwrite => name,
+            _ => {
+                signed = sign(name);
+                &signed
+            }
+        };
+        let Some(rename_cmd) = &behavior.rename_cmd else {
+            return self.backend.rename_workspace(id, name);
+        };
+        let monitor = self
+            .workspace_monitors
+            .lock()
+            .ok()
+            .and_then(|m| m.get(&id).cloned())
+            .unwrap_or_default();
+        let monitor_id = self
+            .monitor_ids
+            .lock()
+            .map(|ids| monitor_id_var(&ids, &monitor))
+            .unwrap_or_default();
+        let id = id.to_string();
+        run_rename_cmd(
+            rename_cmd,
+            &id,
+            // The name last, so its own braces are kept
+            &[
+                ("id", &id),
+                ("monitor", &monitor),
+                ("monitor_id", &monitor_id),
+                ("name", name),
+            ],
+        );
+    }
+
+    /// Counts the workspaces whose rename the cache avoided, or not.
+    fn record_cache_stats(
+        &self,
+        hits: usize,
+        misses: usize,
+        skipped_batch: bool,
+    ) -> Result<(), Box<dyn Error + '_>> {
+        let stats = self.status.lock()?.on_cache(hits, misses, skipped_batch);
+        if self.args.debug(module_path!()) {
+            println!("cache: {stats}");
+        }
+        Ok(())
+    }
+
+    /// Records the workspace as the most recently used one.
+    fn mark_workspace_used(&self, id: i32) -> Result<(), Box<dyn Error + '_>> {
+        let mut last_used = self.workspace_last_used.lock()?;
+        let latest = last_used.values().max().copied();
+        if latest.is_none() || latest != last_used.get(&id).copied() {
+            last_used.insert(id, latest.map_or(0, |tick| tick + 1));
+        }
+        Ok(())
+    }
+
+    fn flush_cache(&self) -> Result<(), Box<dyn Error + '_>> {
+        self.workspace_strings_cache.lock()?.clear();
+        *self.last_batch_hash.lock()? = None;
+        Ok(())
+    }
+
+    /// Overrides the icon of a window, until it is closed.
+    fn set_client_icon(&self, address: &str, icon: &str) -> Result<(), Box<dyn Error + '_>> {
+        self.client_icon_overrides
+            .lock()?
+            .insert(Address::new(address).to_string(), icon.to_string());
+        Ok(())
+    }
+
+    /// Lets the workspace be renamed again, even if its name didn't change
+    /// while it was locked.
+    fn unlock_workspace(&self, id: i32) -> Result<(), Box<dyn Error + '_>> {
+        self.locked_workspaces.lock()?.remove(&id);
+        self.workspace_strings_cache.lock()?.remove(&id);
+        *self.last_batch_hash.lock()? = None;
+        Ok(())
+    }
+
+    /// Flips `format.dedup` until the next config reload.
+    fn toggle_dedup(&self) -> Result<bool, Box<dyn Error + '_>> {
+        let mut cfg = self.cfg.lock()?;
+        cfg.config.format.dedup = !cfg.config.format.dedup;
+        Ok(cfg.config.format.dedup)
+    }
+
+    pub fn handle_command(&self, command: ControlCommand) -> Result<String, String> {
+        match command {
+            ControlCommand::FlushCache => {
+                self.flush_cache().map_err(|e| e.to_string())?;
+                self.rename_workspace("flush_cache")
+                    .map_err(|e| e.to_string())?;
+                Ok("ok".to_string())
+            }
+            ControlCommand::SetClientIcon { address, icon } => {
+                self.set_client_icon(&address, &icon)
+                    .map_err(|e| e.to_string())?;
+                self.rename_workspace("set_client_icon")
+                    .map_err(|e| e.to_string())?;
+                Ok("ok".to_string())
+            }
+            ControlCommand::LockWorkspace(id) => {
+                self.locked_workspaces
+                    .lock()
+                    .map_err(|e| e.to_string())?
+                    .insert(id);
+                Ok(format!("workspace {id} locked"))
+            }
+            ControlCommand::UnlockWorkspace(id) => {
+                self.unlock_workspace(id).map_err(|e| e.to_string())?;
+                self.rename_workspace("unlock_workspace")
+                    .map_err(|e| e.to_string())?;
+                Ok(format!("workspace {id} unlocked"))
+            }
+            ControlCommand::Status => self.status_report().map_err(|e| e.to_string()),
+            // Subscriptions are kept by the control socket listener
+            ControlCommand::Subscribe => Err("subscribe needs a connection".to_string()),
+            ControlCommand::ToggleDedup => {
+                let dedup = self.toggle_dedup().map_err(|e| e.to_string())?;
+                self.rename_workspace("toggle_dedup")
+                    .map_err(|e| e.to_string())?;
+                Ok(format!("dedup {}", if dedup { "on" } else { "off" }))
+            }
+            ControlCommand::Idle => {
+                self.status
+                    .lock()
+                    .map_err(|e| e.to_string())?
+                    .on_idle(SystemTime::now());
+                Ok("idle".to_string())
+            }
+            ControlCommand::Resume => {
+                let was_idle = self.status.lock().map_err(|e| e.to_string())?.on_resume();
+                self.session_resumed.notify_all();
+                if was_idle {
+                    self.resync("resume").map_err(|e| e.to_string())?;
+                }
+                Ok("resumed".to_string())
+            }
+        }
+    }
+
+    pub fn subscribers(&self) -> &Subscribers {
+        &self.subscribers
+    }
+
+    /// Streams the rename to the control socket subscribers, as a JSON line,
+    /// with the rendered `workspace_transition` if any.
+    fn publish_rename(&self, id: i32, old: Option<&str>, new: &str, format: &ConfigFormatRaw) {
+        let mut event = serde_json::json!({ "workspace": id, "old": old, "new": new });
+        if let Some(transition) = render_transition(id, old, new, format) {
+            event["transition"] = transition.into();
+        }
+        self.subscribers.publish(&event.to_string());
+    }
+
+    fn status_report(&self) -> Result<String, Box<dyn Error + '_>> {
+        let (cfg_path, quarantine) = {
+            let cfg = self.cfg.lock()?;
+            (cfg.cfg_path.clone(), cfg.config.quarantine.clone())
+        };
+        let workspaces = self.workspace_strings_cache.lock()?.len();
+        let events = self.event_queue.lock()?.stats();
+        Ok(self.status.lock()?.report(
+            std::process::id(),
+            cfg_path.as_deref(),
+            &quarantine,
+            workspaces,
+            events,
+            SystemTime::now(),
+        ))
+    }
+
     fn update_cache(
         &self,
         workspaces_strings: &HashMap<i32, String>,
@@ -145,12 +924,16 @@ impl Renamer {
         Ok(())
     }
 
+    /// Groups the clients by workspace. Workspaces whose clients are all
+    /// excluded are kept without clients, so they are rendered as empty.
     fn get_workspaces_from_clients(
         &self,
         clients: Vec<Client>,
+        excluded_counts: &HashMap<i32, usize>,
         active_client: String,
         config: &ConfigFile,
     ) -> Result<Vec<AppWorkspace>, Box<dyn Error + '_>> {
+        self.known_workspaces.lock()?.extend(excluded_counts.keys());
         let mut workspaces = self
             .known_workspaces
             .lock()?
@@ -159,11 +942,19 @@ impl Renamer {
             .collect::<HashMap<i32, Vec<AppClient>>>();
 
         let is_dedup_inactive_fullscreen = config.format.dedup_inactive_fullscreen;
+        let icon_overrides = self.client_icon_overrides.lock()?.clone();
+        let urgency = self.urgency.lock()?;
+        let mut title_history = self.title_history.lock()?;
+        let now = Instant::now();
 
+        let mut workspace_names = self.workspace_names.lock()?;
         for client in clients {
             let workspace_id = client.workspace.id;
             self.known_workspaces.lock()?.insert(workspace_id);
+            workspace_names.insert(workspace_id, client.workspace.name.clone());
             let is_active = active_client == client.address.to_string();
+            let address = client.address.to_string();
+            title_history.record(&address, &client.title, config.matching.title_history_size);
             workspaces
                 .entry(workspace_id)
                 .or_insert_with(Vec::new)
@@ -171,134 +962,1059 @@ impl Renamer {
                     client.clone(),
                     is_active,
                     is_dedup_inactive_fullscreen,
-                    self.parse_icon(
-                        client.initial_class,
-                        client.class,
-                        client.initial_title,
-                        client.title,
-                        is_active,
-                        config,
-                    ),
+                    match icon_overrides.get(&address) {
+                        Some(icon) => IconStatus::Inactive(IconConfig::Class(
+                            "set-client-icon".to_string(),
+                            icon.clone(),
+                        )),
+                        None => title_history_icon(
+                            floating_icon(
+                                xwayland_icon(
+                                    self.parse_icon(
+                                        client.initial_class,
+                                        client.class.clone(),
+                                        client.initial_title,
+                                        client.title,
+                                        is_active,
+                                        config,
+                                    ),
+                                    &client.class,
+                                    client.xwayland,
+                                    is_active,
+                                    config,
+                                ),
+                                &client.class,
+                                client.floating,
+                                is_active,
+                                config,
+                            ),
+                            &client.class,
+                            title_history.get(&address),
+                            is_active,
+                            config,
+                        ),
+                    },
+                    urgency.urgent_for(&address, now),
                 ));
         }
 
         Ok(workspaces
             .iter()
-            .map(|(&id, clients)| AppWorkspace::new(id, clients.to_vec()))
+            .map(|(&id, clients)| AppWorkspace {
+                excluded_count: excluded_counts.get(&id).copied().unwrap_or(0),
+                ..AppWorkspace::new(id, clients.to_vec())
+            })
             .collect())
     }
 
-    pub fn reset_workspaces(&self, config: ConfigFile) -> Result<(), Box<dyn Error + '_>> {
-        self.workspace_strings_cache.lock()?.clear();
-
-        self.known_workspaces
-            .lock()?
-            .iter()
-            .for_each(|&id| rename_cmd(id, "", &config.format, &config.workspaces_name));
-
+    /// Tags the windows with the words of their inactive icon, as Hyprland
+    /// titles can't be changed.
+    fn tag_windows(
+        &self,
+        clients: &[Client],
+        config: &ConfigFile,
+    ) -> Result<(), Box<dyn Error + '_>> {
+        let mut window_tags = self.window_tags.lock()?;
+        for client in clients {
+            let icon = self.parse_icon(
+                client.initial_class.clone(),
+                client.class.clone(),
+                client.initial_title.clone(),
+                client.title.clone(),
+                false,
+                config,
+            );
+            let mut vars = HashMap::from([
+                ("class".to_string(), escape_braces(&client.class)),
+                ("title".to_string(), escape_braces(&client.title)),
+            ]);
+            vars.extend(
+                icon.captures()
+                    .unwrap_or_default()
+                    .into_iter()
+                    .map(|(name, value)| (name, escape_braces(&value))),
+            );
+            let icon = formatter(&icon.icon(), &vars, config.format.placeholder_depth);
+            let Some(tag) = tag_name(&unescape_braces(&icon)) else {
+                continue;
+            };
+
+            let address = client.address.to_string();
+            if let Some((previous, tag)) = window_tags.update(&address, tag) {
+                self.backend.tag_window(&address, previous.as_deref(), &tag);
+            }
+        }
         Ok(())
     }
 
-    pub fn start_listeners(self: &Arc<Self>) {
-        let mut event_listener = EventListener::new();
+    /// Stops renaming the workspaces, then resets their names with the
+    /// current config once the in-flight rename is over, so the reset is
+    /// always the last rename.
+    pub fn shutdown(&self) -> Result<(), Box<dyn Error + '_>> {
+        *self.shutting_down.lock()? = true;
+        let config = self.cfg.lock()?.config.clone();
+        self.reset_workspaces(config)
+    }
 
-        rename_workspace_if!(
-            self,
-            event_listener,
-            add_window_opened_handler,
-            add_window_closed_handler,
-            add_window_moved_handler,
-            add_active_window_changed_handler,
-            add_workspace_added_handler,
-            add_workspace_moved_handler,
-            add_workspace_changed_handler,
-            add_fullscreen_state_changed_handler,
-            add_window_title_changed_handler
-        );
+    fn is_shutting_down(&self) -> bool {
+        self.shutting_down.lock().map_or(true, |s| *s)
+    }
 
-        let this = self.clone();
-        event_listener.add_workspace_deleted_handler(move |wt| {
-            _ = this.rename_workspace();
-            _ = this.remove_workspace(wt);
+    pub fn reset_workspaces(&self, config: ConfigFile) -> Result<(), Box<dyn Error + '_>> {
+        let _dispatching = self.dispatch_lock.lock()?;
+        let cache = std::mem::take(&mut *self.workspace_strings_cache.lock()?);
+        *self.last_batch_hash.lock()? = None;
+        let workspace_monitors = self.workspace_monitors.lock()?.clone();
+        let mut global_vars = generate_global_vars(&[]);
+        global_vars.insert("power_profile".to_string(), String::new());
+        let reset_vars = empty_workspace_vars(&global_vars);
+
+        self.known_workspaces.lock()?.iter().for_each(|&id| {
+            if !is_managed(&config.behavior, &workspace_monitors, id) {
+                return;
+            }
+            let old = cache.get(&id).map(String::as_str);
+            audit_dispatch(&config.behavior, &self.state, id, old, "", "reset");
+            let name = render_workspace(
+                id,
+                "",
+                &config.format,
+                &config.workspaces_name,
+                &reset_vars,
+                false,
+                false,
+            );
+            self.publish_rename(id, old, &name, &config.format);
+            self.dispatch_rename(&config.behavior, id, &name);
         });
 
-        _ = event_listener.start_listener();
+        Ok(())
     }
 
-    pub fn watch_config_changes(
+    /// Generates the variables shared by all workspaces formats.
+    fn generate_global_vars(
         &self,
-        cfg_path: Option<PathBuf>,
-    ) -> Result<(), Box<dyn Error + '_>> {
-        match &cfg_path {
-            Some(cfg_path) => {
+        workspaces: &[AppWorkspace],
+    ) -> Result<HashMap<String, String>, Box<dyn Error + '_>> {
+        let mut vars = generate_global_vars(workspaces);
+        vars.insert(
+            "power_profile".to_string(),
+            self.power.lock()?.profile.clone(),
+        );
+        Ok(vars)
+    }
+
+    fn is_low_power(&self, config: &ConfigFile) -> Result<bool, Box<dyn Error + '_>> {
+        Ok(config.behavior.low_power_on_battery && self.power.lock()?.is_low_power())
+    }
+
+    /// Renames the workspaces on an event, unless it is deferred
+    /// during a startup storm, in low power mode or by the debounce.
+    pub fn handle_event(&self, event: &str) -> Result<(), Box<dyn Error + '_>> {
+        let config = self.cfg.lock()?.config.clone();
+        if config.behavior.startup_throttle
+            && !self
+                .startup_throttle
+                .lock()?
+                .on_event(event, Instant::now())
+        {
+            return Ok(());
+        }
+        let low_power = self.is_low_power(&config)?;
+        if low_power && event == "window_title_changed" {
+            return Ok(());
+        }
+        if !debounce(&config.behavior, low_power).is_zero() {
+            let mut debounced_event = self.debounced_event.lock()?;
+            // In low power mode, the events are renamed at most every
+            // debounce, even when they never settle
+            let at = match &*debounced_event {
+                Some((_, at)) if low_power => *at,
+                _ => Instant::now(),
+            };
+            *debounced_event = Some((event.to_string(), at));
+            self.debounce_changed.notify_one();
+            return Ok(());
+        }
+        self.rename_workspace(event)
+    }
+
+    /// Queues the event of the listener, about a workspace or a window,
+    /// for `process_events`.
+    fn queue_event(&self, event: &str, subject: &str) -> Result<(), Box<dyn Error + '_>> {
+        self.event_queue.lock()?.push(event, subject);
+        self.event_queued.notify_one();
+        Ok(())
+    }
+
+    /// Handles the queued events one at a time, so the listener is never
+    /// blocked by a rename.
+    fn process_events(&self) -> Result<(), Box<dyn Error + '_>> {
+        loop {
+            self.wait_while_idle()?;
+            let event = {
+                let mut queue = self.event_queue.lock()?;
                 loop {
-                    // Watch for modify events.
-                    let mut notify = Inotify::init()?;
-
-                    notify.watches().add(cfg_path, WatchMask::MODIFY)?;
-                    let mut buffer = [0; 1024];
-                    notify.read_events_blocking(&mut buffer)?.last();
-
-                    println!("Reloading config !");
-                    // Clojure to force quick release of lock
-                    {
-                        match Config::new(cfg_path.clone(), false, false) {
-                            Ok(config) => self.cfg.lock()?.config = config.config,
-                            Err(err) => println!("Unable to reload config: {err:?}"),
-                        }
+                    match queue.pop() {
+                        Some(event) => break event,
+                        None => queue = self.event_queued.wait(queue)?,
                     }
+                }
+            };
+            _ = self.handle_event(&event);
+        }
+    }
+
+    /// Renames the last debounced event once no event came for `debounce`.
+    /// Returns whether it was renamed.
+    fn flush_debounced_event(&self, debounce: Duration) -> Result<bool, Box<dyn Error + '_>> {
+        let due = {
+            let mut debounced_event = self.debounced_event.lock()?;
+            match &*debounced_event {
+                Some((_, at)) if at.elapsed() >= debounce => debounced_event.take(),
+                _ => None,
+            }
+        };
+        match due {
+            Some((event, _)) => self.rename_workspace(&event).map(|_| true),
+            None => Ok(false),
+        }
+    }
 
-                    // Handle event
-                    // Run on window events
-                    _ = self.rename_workspace();
+    /// Waits for a debounced event, then for it to settle.
+    fn watch_debounce(&self) -> Result<(), Box<dyn Error + '_>> {
+        loop {
+            self.wait_while_idle()?;
+            let debounce = {
+                let cfg = self.cfg.lock()?;
+                debounce(&cfg.config.behavior, self.is_low_power(&cfg.config)?)
+            };
+            let debounced_event = self.debounced_event.lock()?;
+            let left = match &*debounced_event {
+                Some((_, at)) => debounce.saturating_sub(at.elapsed()),
+                None => {
+                    drop(self.debounce_changed.wait(debounced_event)?);
+                    continue;
                 }
+            };
+            if left.is_zero() {
+                drop(debounced_event);
+                _ = self.flush_debounced_event(debounce);
+            } else {
+                drop(self.debounce_changed.wait_timeout(debounced_event, left)?);
             }
-            None => Ok(()),
         }
     }
 
-    fn remove_workspace(&self, wt: WorkspaceEventData) -> Result<bool, Box<dyn Error + '_>> {
-        Ok(self.known_workspaces.lock()?.remove(&wt.id))
+    /// Forgets the workspaces state that may be stale, then renames every
+    /// workspace.
+    fn resync(&self, event: &str) -> Result<(), Box<dyn Error + '_>> {
+        self.forget_workspaces_state()?;
+        self.rename_workspace(event)
+    }
+
+    /// Forgets the persistent workspaces and their monitors, and the cached
+    /// names, so they are all looked up again.
+    fn forget_workspaces_state(&self) -> Result<(), Box<dyn Error + '_>> {
+        self.workspace_monitors.lock()?.clear();
+        self.monitor_ids.lock()?.clear();
+        self.persistent_workspaces.lock()?.clear();
+        self.flush_cache()
     }
-}
 
-fn rename_empty_workspace(config: &ConfigFile) {
-    _ = Workspace::get_active().map(|workspace| {
-        if workspace.windows == 0 {
-            rename_cmd(workspace.id, "", &config.format, &config.workspaces_name);
+    /// Renames every workspace, changed or not, every `resync` seconds, to
+    /// recover the names changed by someone else.
+    fn watch_resync(&self) -> Result<(), Box<dyn Error + '_>> {
+        let mut cfg = self.cfg.lock()?;
+        loop {
+            let resync = cfg.config.behavior.resync;
+            if resync == 0 {
+                cfg = self.config_changed.wait(cfg)?;
+                continue;
+            }
+            let (guard, wait) = self
+                .config_changed
+                .wait_timeout(cfg, Duration::from_secs(resync))?;
+            cfg = guard;
+            if wait.timed_out() {
+                drop(cfg);
+                self.wait_while_idle()?;
+                _ = self.resync("resync");
+                cfg = self.cfg.lock()?;
+            }
         }
-    });
-}
+    }
 
-fn rename_cmd(
-    id: i32,
-    clients: &str,
-    config_format: &ConfigFormatRaw,
-    workspaces_name: &[(String, String)],
-) {
-    let workspace_fmt = &config_format.workspace.to_string();
-    let workspace_empty_fmt = &config_format.workspace_empty.to_string();
-    let id_two_digits = format!("{:02}", id);
-    let workspace_name = get_workspace_name(id, workspaces_name);
+    /// Follows the power state over D-Bus, and renames the workspaces when
+    /// it changes.
+    fn watch_power_state(self: &Arc<Self>) -> Result<(), Box<dyn Error + '_>> {
+        let connection = power::connect()?;
 
-    let mut vars = HashMap::from([
-        ("id".to_string(), id.to_string()),
-        ("id_long".to_string(), id_two_digits),
-        ("name".to_string(), workspace_name),
-        ("delim".to_string(), config_format.delim.to_string()),
-    ]);
+        let this = self.clone();
+        let profile_connection = connection.clone();
+        thread::spawn(move || {
+            let watched = power::watch_profile(&profile_connection, |profile| {
+                _ = this.update_power_state(|power| power.profile = profile);
+            });
+            if let Err(e) = watched {
+                eprintln!("Unable to watch the power profile: {e}");
+            }
+        });
 
-    vars.insert("clients".to_string(), clients.to_string());
-    let workspace = if !clients.is_empty() {
-        formatter(workspace_fmt, &vars)
-    } else {
-        formatter(workspace_empty_fmt, &vars)
-    };
+        let watched = power::watch_on_battery(&connection, |on_battery| {
+            _ = self.update_power_state(|power| power.on_battery = on_battery);
+        });
+        if let Err(e) = watched {
+            eprintln!("Unable to watch the power source: {e}");
+        }
+        Ok(())
+    }
 
-    let _ = hyprland::dispatch!(RenameWorkspace, id, Some(workspace.trim()));
-}
+    fn update_power_state(
+        &self,
+        update: impl FnOnce(&mut PowerState),
+    ) -> Result<(), Box<dyn Error + '_>> {
+        let mut power = self.power.lock()?;
+        let previous = power.clone();
+        update(&mut power);
+        if *power == previous {
+            return Ok(());
+        }
+        drop(power);
 
-fn get_workspace_name(id: i32, workspaces_name: &[(String, String)]) -> String {
+        // Render what was skipped in low power mode, e.g. hidden workspaces,
+        // and the new {power_profile}
+        self.rename_workspace("power_changed")
+    }
+
+    /// Renames the events deferred during a startup storm, until no storm
+    /// can happen anymore.
+    fn watch_startup_storm(&self) -> Result<(), Box<dyn Error + '_>> {
+        loop {
+            thread::sleep(Duration::from_millis(250));
+            self.wait_while_idle()?;
+            let now = Instant::now();
+            let action = self.startup_throttle.lock()?.on_tick(now);
+            match action {
+                TickAction::Render => self.rename_workspace("startup_storm")?,
+                TickAction::FinalRender => self.rename_workspace("startup_storm_end")?,
+                TickAction::Idle if self.startup_throttle.lock()?.is_over(now) => return Ok(()),
+                TickAction::Idle => {}
+            }
+        }
+    }
+
+    /// Renders the names changing by themselves while windows are urgent:
+    /// the urgency timeout, and the age shown by `{urgent_for}`.
+    fn watch_urgency(&self) -> Result<(), Box<dyn Error + '_>> {
+        let mut last_check = Instant::now();
+        loop {
+            self.wait_while_idle()?;
+            let (timeout, with_age) = {
+                let cfg = self.cfg.lock()?;
+                (
+                    urgent_timeout(&cfg.config),
+                    uses_urgent_for_var(&cfg.config.format),
+                )
+            };
+
+            let mut urgency = self.urgency.lock()?;
+            let now = Instant::now();
+            match urgency.next_deadline(last_check, timeout, with_age) {
+                Some(deadline) if deadline <= now => {
+                    urgency.expire(now, timeout);
+                    drop(urgency);
+                    last_check = now;
+                    _ = self.rename_workspace("urgency_timer");
+                }
+                Some(deadline) => {
+                    drop(self.urgency_changed.wait_timeout(urgency, deadline - now)?);
+                }
+                None => drop(self.urgency_changed.wait(urgency)?),
+            }
+        }
+    }
+
+    /// Parks the calling thread while the session is idle, until `resume`.
+    fn wait_while_idle(&self) -> Result<(), Box<dyn Error + '_>> {
+        let mut status = self.status.lock()?;
+        while status.is_idle() {
+            status = self.session_resumed.wait(status)?;
+        }
+        Ok(())
+    }
+
+    /// Starts the watchers of the enabled features, once each: at startup,
+    /// and for the features enabled by a reloaded config.
+    fn spawn_watchers(self: &Arc<Self>) {
+        let enabled = match self.cfg.lock() {
+            Ok(cfg) => {
+                let behavior = &cfg.config.behavior;
+                [
+                    (Watcher::StartupStorm, behavior.startup_throttle),
+                    (
+                        Watcher::PowerState,
+                        behavior.low_power_on_battery || uses_power_profile_var(&cfg.config.format),
+                    ),
+                    (Watcher::ConfigChanges, cfg.cfg_path.is_some()),
+                    (
+                        Watcher::Urgency,
+                        urgent_timeout(&cfg.config).is_some()
+                            || uses_urgent_for_var(&cfg.config.format),
+                    ),
+                    (
+                        Watcher::Debounce,
+                        behavior.debounce > 0 || behavior.low_power_on_battery,
+                    ),
+                    (Watcher::Resync, behavior.resync > 0),
+                ]
+            }
+            Err(_) => return,
+        };
+
+        let Ok(mut spawned) = self.spawned_watchers.lock() else {
+            return;
+        };
+        for (watcher, enabled) in enabled {
+            if enabled && spawned.insert(watcher) {
+                let this = self.clone();
+                thread::spawn(move || _ = this.watch(watcher));
+            }
+        }
+    }
+
+    fn watch(self: &Arc<Self>, watcher: Watcher) -> Result<(), Box<dyn Error + '_>> {
+        match watcher {
+            Watcher::StartupStorm => self.watch_startup_storm(),
+            Watcher::PowerState => self.watch_power_state(),
+            Watcher::ConfigChanges => self.watch_config_changes(),
+            Watcher::Urgency => self.watch_urgency(),
+            Watcher::Debounce => self.watch_debounce(),
+            Watcher::Resync => self.watch_resync(),
+        }
+    }
+
+    /// Renames the workspaces on the windows changes of another compositor,
+    /// with the toplevel backend.
+    pub fn start_toplevel_listener(self: &Arc<Self>, backend: &ToplevelBackend) {
+        self.spawn_watchers();
+
+        self.set_listener_state(ListenerState::Listening, None);
+        let error = toplevel::listen(|toplevels| {
+            backend.set_toplevels(toplevels);
+            _ = self.handle_event("toplevel_changed");
+        })
+        .err()
+        .map(|e| e.to_string());
+        if let Some(error) = &error {
+            eprintln!("Toplevel listener stopped: {error}");
+        }
+        self.set_listener_state(ListenerState::Stopped, error);
+    }
+
+    pub fn start_listeners(self: &Arc<Self>) {
+        let mut event_listener = EventListener::new();
+        self.spawn_watchers();
+
+        let this = self.clone();
+        thread::spawn(move || _ = this.process_events());
+
+        queue_event_if!(
+            self,
+            event_listener,
+            add_window_opened_handler => |window| window.workspace_name,
+            add_window_closed_handler => |address| address.to_string(),
+            add_workspace_added_handler => |workspace| workspace.id.to_string(),
+            add_workspace_changed_handler => |workspace| workspace.id.to_string(),
+            add_window_title_changed_handler => |window| window.address.to_string()
+        );
+
+        // The fullscreen events don't tell the window, the active one
+        let active_window = Arc::new(Mutex::new(String::new()));
+
+        let this = self.clone();
+        let active = active_window.clone();
+        event_listener.add_active_window_changed_handler(move |window| {
+            let subject = window.map(|w| w.address.to_string()).unwrap_or_default();
+            _ = this.queue_event("active_window_changed", &subject);
+            if let Ok(mut active) = active.lock() {
+                *active = subject;
+            }
+        });
+
+        let this = self.clone();
+        event_listener.add_fullscreen_state_changed_handler(move |_| {
+            if let Ok(active) = active_window.lock() {
+                _ = this.queue_event("fullscreen_state_changed", &active);
+            }
+        });
+
+        let this = self.clone();
+        event_listener.add_window_moved_handler(move |event| {
+            let workspace = WorkspaceBasic {
+                id: event.workspace_id,
+                name: event.workspace_name.to_string(),
+            };
+            let subject = event.window_address.to_string();
+            _ = this.move_window(&subject, workspace);
+            _ = this.queue_event("window_moved", &subject);
+        });
+
+        let this = self.clone();
+        event_listener.add_workspace_moved_handler(move |wt| {
+            let config = match this.cfg.lock() {
+                Ok(cfg) => cfg.config.clone(),
+                Err(_) => return,
+            };
+            if let Ok(true) = this.move_workspace(&wt, &config) {
+                _ = this.queue_event("workspace_moved", &wt.id.to_string());
+            }
+        });
+
+        let this = self.clone();
+        event_listener.add_urgent_state_changed_handler(move |address| {
+            let Ok(mut urgency) = this.urgency.lock() else {
+                return;
+            };
+            let subject = address.to_string();
+            urgency.on_urgent(&subject, Instant::now());
+            drop(urgency);
+            this.urgency_changed.notify_one();
+            _ = this.queue_event("urgent_state_changed", &subject);
+        });
+
+        // Hyprland reloads its config with the workspace rules and monitors
+        let this = self.clone();
+        event_listener.add_config_reloaded_handler(move || {
+            _ = this.forget_workspaces_state();
+            _ = this.queue_event("config_reloaded", "");
+        });
+
+        let this = self.clone();
+        event_listener.add_workspace_deleted_handler(move |wt| {
+            let subject = wt.id.to_string();
+            _ = this.remove_workspace(wt);
+            _ = this.queue_event("workspace_deleted", &subject);
+        });
+
+        self.set_listener_state(ListenerState::Listening, None);
+        let error = event_listener.start_listener().err().map(|e| e.to_string());
+        self.set_listener_state(ListenerState::Stopped, error);
+    }
+
+    fn set_listener_state(&self, listener: ListenerState, error: Option<String>) {
+        if let Ok(mut status) = self.status.lock() {
+            status.set_listener(listener);
+            if let Some(error) = error {
+                status.on_error(format!("event listener: {error}"));
+            }
+        }
+    }
+
+    /// Reloads the config when its file or one of its local packs changes,
+    /// and renames the workspaces with it.
+    fn watch_config_changes(self: &Arc<Self>) -> Result<(), Box<dyn Error + '_>> {
+        let Some(cfg_path) = self.cfg.lock()?.cfg_path.clone() else {
+            return Ok(());
+        };
+
+        let mut watcher = ConfigWatcher::new(&cfg_path);
+        watcher.set_packs(self.local_packs()?);
+        loop {
+            match watcher.wait() {
+                Ok(changed) if changed.iter().any(|path| watcher.is_config(path)) => {
+                    self.reload_config(&cfg_path)?;
+                    watcher.set_packs(self.local_packs()?);
+                    self.spawn_watchers();
+                    _ = self.rename_workspace("config_reloaded");
+                }
+                Ok(changed) => {
+                    self.reload_packs(&cfg_path, &changed)?;
+                    self.spawn_watchers();
+                    _ = self.rename_workspace("pack_reloaded");
+                }
+                Err(e) => {
+                    eprintln!("Unable to watch config changes: {e}");
+                    self.status.lock()?.on_error(format!("config watch: {e}"));
+                    thread::sleep(watcher.backoff());
+                }
+            }
+        }
+    }
+
+    /// The files of the local packs of the config, watched like the config.
+    fn local_packs(&self) -> Result<Vec<PathBuf>, Box<dyn Error + '_>> {
+        let cfg = self.cfg.lock()?;
+        Ok(cfg
+            .config
+            .packs
+            .iter()
+            .filter_map(|p| local_path(p))
+            .collect())
+    }
+
+    fn reload_config(&self, cfg_path: &Path) -> Result<(), Box<dyn Error + '_>> {
+        eprintln!("Reloading config !");
+        let behavior = self.cfg.lock()?.config.behavior.clone();
+        match Config::with_layers(cfg_path.to_path_buf(), false, false) {
+            Ok((config, layers)) => {
+                *self.pack_layers.lock()? = Some(layers);
+                self.set_reloaded_config(cfg_path, config.config)?;
+            }
+            Err(err) => {
+                notify_error(&behavior, "Unable to reload config", &err.to_string());
+                self.status
+                    .lock()?
+                    .on_error(format!("config reload: {err}"));
+            }
+        }
+        Ok(())
+    }
+
+    /// Reloads the changed packs alone, and merges them again with the
+    /// config file, unless the packs weren't kept since the start:
+    /// everything is reloaded then.
+    fn reload_packs(&self, cfg_path: &Path, paths: &[PathBuf]) -> Result<(), Box<dyn Error + '_>> {
+        let merged = match self.pack_layers.lock()?.as_mut() {
+            Some(layers) => {
+                let mut reloaded = false;
+                for path in paths {
+                    reloaded |= layers.reload(path);
+                }
+                reloaded.then(|| layers.merged())
+            }
+            None => None,
+        };
+
+        match merged {
+            Some(config) => {
+                eprintln!("Reloading icon packs !");
+                self.set_reloaded_config(cfg_path, generate_config(config))
+            }
+            None => self.reload_config(cfg_path),
+        }
+    }
+
+    fn set_reloaded_config(
+        &self,
+        cfg_path: &Path,
+        config: ConfigFile,
+    ) -> Result<(), Box<dyn Error + '_>> {
+        if config.rules_count() == 0 {
+            notify_error(
+                &config.behavior,
+                "Config reloaded without any icon rule",
+                &format!("Check the regexes of {}", cfg_path.display()),
+            );
+        }
+        self.cfg.lock()?.config = config;
+        self.config_changed.notify_all();
+        self.debounce_changed.notify_one();
+        self.urgency_changed.notify_one();
+        Ok(())
+    }
+
+    fn remove_workspace(&self, wt: WorkspaceEventData) -> Result<bool, Box<dyn Error + '_>> {
+        self.workspace_monitors.lock()?.remove(&wt.id);
+        self.workspace_names.lock()?.remove(&wt.id);
+        self.warned_foreign_names.lock()?.remove(&wt.id);
+        self.workspace_last_used.lock()?.remove(&wt.id);
+        Ok(self.known_workspaces.lock()?.remove(&wt.id))
+    }
+}
+
+/// Renders the name of the active workspace, if it is empty.
+fn render_empty_workspace(
+    workspace: Option<ActiveWorkspace>,
+    config: &ConfigFile,
+    global_vars: &HashMap<String, String>,
+    persistent_ids: &HashSet<i32>,
+    monitor_ids: &HashMap<String, MonitorId>,
+) -> Option<(i32, String)> {
+    let workspace = workspace?;
+    if workspace.windows != 0 {
+        return None;
+    }
+
+    let is_persistent = persistent_ids.contains(&workspace.id);
+    let mut vars = empty_workspace_vars(global_vars);
+    vars.insert(
+        "monitor_id".to_string(),
+        monitor_id_var(monitor_ids, &workspace.monitor),
+    );
+    vars.insert("monitor".to_string(), workspace.monitor);
+    vars.insert(
+        "persistent".to_string(),
+        persistent_var(is_persistent, &config.format),
+    );
+    vars.insert(
+        "is_monitor_active".to_string(),
+        monitor_active_var(true, &config.format),
+    );
+    let name = render_workspace(
+        workspace.id,
+        "",
+        &config.format,
+        &config.workspaces_name,
+        &vars,
+        is_persistent,
+        true,
+    );
+    Some((workspace.id, name))
+}
+
+/// Returns the variables of a workspace without clients, on top of the
+/// global ones. The monitor and marker vars are left empty.
+fn empty_workspace_vars(global_vars: &HashMap<String, String>) -> HashMap<String, String> {
+    let mut vars = global_vars.clone();
+    vars.extend([
+        ("monitor".to_string(), String::new()),
+        ("monitor_id".to_string(), String::new()),
+        ("fullscreen_count".to_string(), "0".to_string()),
+        ("excluded_count".to_string(), "0".to_string()),
+        ("workspace_windows".to_string(), "0".to_string()),
+        ("client_index_active".to_string(), "0".to_string()),
+        ("client_count".to_string(), "0".to_string()),
+        ("xwayland".to_string(), String::new()),
+        ("special_name".to_string(), String::new()),
+        ("persistent".to_string(), String::new()),
+        ("is_monitor_active".to_string(), String::new()),
+    ]);
+    vars.extend(urgency_vars(None, ""));
+    vars
+}
+
+/// Hashes a whole batch of names, independently of the map order.
+fn hash_workspaces_names(workspaces_names: &HashMap<i32, String>) -> u64 {
+    let sorted: BTreeMap<_, _> = workspaces_names.iter().collect();
+    let mut hasher = DefaultHasher::new();
+    sorted.hash(&mut hasher);
+    hasher.finish()
+}
+
+fn render_workspace(
+    id: i32,
+    clients: &str,
+    config_format: &ConfigFormatRaw,
+    workspaces_name: &[(String, String)],
+    extra_vars: &HashMap<String, String>,
+    is_persistent: bool,
+    is_focused: bool,
+) -> String {
+    let workspace_fmt = &config_format.workspace.to_string();
+    let workspace_empty_fmt = match (
+        &config_format.workspace_empty_active,
+        &config_format.workspace_persistent_empty,
+    ) {
+        (Some(fmt), _) if is_focused => fmt,
+        (_, Some(fmt)) if is_persistent => fmt,
+        _ => &config_format.workspace_empty,
+    };
+    let id_two_digits = format!("{:02}", id);
+    let workspace_name = get_workspace_name(id, workspaces_name);
+
+    let mut vars = HashMap::from([
+        ("id".to_string(), id.to_string()),
+        ("id_long".to_string(), id_two_digits),
+        ("id_abs".to_string(), id.unsigned_abs().to_string()),
+        ("id_alpha".to_string(), id_alpha(id)),
+        ("id_roman".to_string(), id_roman(id)),
+        ("name".to_string(), workspace_name),
+        ("delim".to_string(), config_format.delim.to_string()),
+    ]);
+
+    vars.extend(extra_vars.clone());
+    let depth = config_format.placeholder_depth;
+    if let (true, Some(prefix)) = (id < 0, &config_format.special_prefix) {
+        let special_id = formatter(prefix, &vars, depth);
+        vars.insert("id".to_string(), special_id.clone());
+        vars.insert("id_long".to_string(), special_id);
+    }
+    vars.insert("clients".to_string(), clients.to_string());
+    // Special workspaces have their own formats, where {name} is the name
+    // of the scratchpad, the empty one defaulting to the other
+    let is_empty = clients.trim().is_empty();
+    let special_fmt = match &config_format.workspace_special_empty {
+        Some(fmt) if is_empty => Some(fmt),
+        _ => config_format.workspace_special.as_ref(),
+    }
+    .filter(|_| id < 0);
+    let workspace = if let Some(special_fmt) = special_fmt {
+        let special_name = vars.get("special_name").cloned().unwrap_or_default();
+        vars.insert("name".to_string(), special_name);
+        formatter(special_fmt, &vars, depth)
+    // Clients rendered as blank, e.g. with empty icons, are an empty workspace
+    } else if !is_empty {
+        formatter(workspace_fmt, &vars, depth)
+    } else {
+        formatter(workspace_empty_fmt, &vars, depth)
+    };
+
+    let workspace = match config_format.collapse_delim {
+        true => collapse_delim(
+            &workspace,
+            &config_format.delim,
+            config_format.pad_clients_to.is_some(),
+        ),
+        false => workspace,
+    };
+
+    let workspace = if config_format.ascii_fallback {
+        to_ascii(&workspace)
+    } else {
+        workspace
+    };
+
+    // Keep the trailing padding, when clients are padded
+    let workspace = match config_format.pad_clients_to {
+        Some(_) => workspace.trim_start(),
+        None => workspace.trim(),
+    };
+    unescape_braces(workspace)
+}
+
+/// Collapses repeated delimiters, left by placeholders rendered empty, and
+/// strips the leading and trailing ones, unless the trailing padding is kept.
+fn collapse_delim(name: &str, delim: &str, keep_trailing: bool) -> String {
+    if delim.is_empty() {
+        return name.to_string();
+    }
+    let doubled = delim.repeat(2);
+    let mut name = name.to_string();
+    while name.contains(&doubled) {
+        name = name.replace(&doubled, delim);
+    }
+    let name = name.strip_prefix(delim).unwrap_or(&name);
+    match keep_trailing {
+        true => name.to_string(),
+        false => name.strip_suffix(delim).unwrap_or(name).to_string(),
+    }
+}
+
+/// Renders the `workspace_transition` format of a rename, where
+/// `{workspace_prev_name}` is the name being replaced.
+fn render_transition(
+    id: i32,
+    old: Option<&str>,
+    new: &str,
+    format: &ConfigFormatRaw,
+) -> Option<String> {
+    let transition_fmt = format.workspace_transition.as_ref()?;
+    let vars = HashMap::from([
+        ("id".to_string(), id.to_string()),
+        ("delim".to_string(), format.delim.to_string()),
+        ("workspace_name".to_string(), escape_braces(new)),
+        (
+            "workspace_prev_name".to_string(),
+            escape_braces(old.unwrap_or_default()),
+        ),
+    ]);
+    let transition = formatter(transition_fmt, &vars, format.placeholder_depth);
+    Some(unescape_braces(&transition))
+}
+
+/// Returns the name of a special workspace without its `special:` prefix,
+/// empty for regular workspaces.
+fn special_name(id: i32, name: Option<&String>) -> String {
+    if id >= 0 {
+        return String::new();
+    }
+    match name {
+        Some(name) => name.strip_prefix("special:").unwrap_or(name).to_string(),
+        None => "special".to_string(),
+    }
+}
+
+/// Returns the id as letters: 1 is `a`, 26 is `z`, 27 is `aa`...
+/// Special workspaces, with a negative id, keep their number.
+fn id_alpha(id: i32) -> String {
+    if id <= 0 {
+        return id.to_string();
+    }
+    let mut n = id as u32;
+    let mut letters = Vec::new();
+    while n > 0 {
+        n -= 1;
+        letters.push(char::from(b'a' + (n % 26) as u8));
+        n /= 26;
+    }
+    letters.iter().rev().collect()
+}
+
+/// Returns the id as roman numerals, e.g. `XIV`. Ids that can't be written
+/// in roman numerals keep their number.
+fn id_roman(id: i32) -> String {
+    const NUMERALS: [(i32, &str); 13] = [
+        (1000, "M"),
+        (900, "CM"),
+        (500, "D"),
+        (400, "CD"),
+        (100, "C"),
+        (90, "XC"),
+        (50, "L"),
+        (40, "XL"),
+        (10, "X"),
+        (9, "IX"),
+        (5, "V"),
+        (4, "IV"),
+        (1, "I"),
+    ];
+    if !(1..4000).contains(&id) {
+        return id.to_string();
+    }
+    let mut n = id;
+    let mut roman = String::new();
+    for (value, numeral) in NUMERALS {
+        while n >= value {
+            roman.push_str(numeral);
+            n -= value;
+        }
+    }
+    roman
+}
+
+/// Returns true if a format shows how long workspaces or clients are urgent.
+fn uses_urgent_for_var(format: &ConfigFormatRaw) -> bool {
+    let client_formats = [
+        &format.client,
+        &format.client_active,
+        &format.client_fullscreen,
+        &format.client_active_fullscreen,
+        &format.client_dup,
+        &format.client_dup_active,
+        &format.client_dup_fullscreen,
+        &format.client_dup_active_fullscreen,
+        &format.client_maximized,
+        &format.client_dup_maximized,
+        &format.client_modified,
+        &format.client_unmanaged,
+        &format.client_floating,
+    ];
+    workspace_formats(format)
+        .chain(client_formats)
+        .chain(&format.client_pinned)
+        .any(|fmt| fmt.contains("{urgent_for"))
+}
+
+/// Returns true if a workspace format shows the power profile.
+fn uses_power_profile_var(format: &ConfigFormatRaw) -> bool {
+    workspace_formats(format).any(|fmt| fmt.contains("{power_profile}"))
+}
+
+/// Returns how long the events wait to settle, longer in low power mode.
+fn debounce(behavior: &ConfigBehaviorRaw, low_power: bool) -> Duration {
+    const LOW_POWER_DEBOUNCE: Duration = Duration::from_secs(2);
+
+    let debounce = Duration::from_millis(behavior.debounce);
+    match low_power {
+        true => debounce.max(LOW_POWER_DEBOUNCE),
+        false => debounce,
+    }
+}
+
+fn urgent_timeout(config: &ConfigFile) -> Option<Duration> {
+    match config.behavior.urgent_timeout {
+        0 => None,
+        secs => Some(Duration::from_secs(secs)),
+    }
+}
+
+/// Every workspace format, the optional ones when set.
+fn workspace_formats(format: &ConfigFormatRaw) -> impl Iterator<Item = &String> {
+    [&format.workspace, &format.workspace_empty]
+        .into_iter()
+        .chain(&format.workspace_empty_active)
+        .chain(&format.workspace_special)
+        .chain(&format.workspace_special_empty)
+        .chain(&format.workspace_persistent_empty)
+}
+
+/// Tells whether persistent workspaces must be looked up.
+fn uses_persistent_vars(format: &ConfigFormatRaw) -> bool {
+    format.workspace_persistent_empty.is_some()
+        || workspace_formats(format).any(|fmt| fmt.contains("{persistent}"))
+}
+
+fn persistent_var(is_persistent: bool, format: &ConfigFormatRaw) -> String {
+    match is_persistent {
+        true => format.persistent_marker.clone(),
+        false => String::new(),
+    }
+}
+
+fn monitor_active_var(is_monitor_active: bool, format: &ConfigFormatRaw) -> String {
+    match is_monitor_active {
+        true => format.monitor_active_marker.clone(),
+        false => String::new(),
+    }
+}
+
+fn uses_workspace_windows_var(format: &ConfigFormatRaw) -> bool {
+    workspace_formats(format).any(|fmt| fmt.contains("{workspace_windows}"))
+}
+
+/// The id of the monitor, empty if unknown.
+fn monitor_id_var(monitor_ids: &HashMap<String, MonitorId>, monitor: &str) -> String {
+    monitor_ids
+        .get(monitor)
+        .map(ToString::to_string)
+        .unwrap_or_default()
+}
+
+/// Returns true if the workspace formats depend on the workspace monitor.
+fn uses_monitor_vars(format: &ConfigFormatRaw) -> bool {
+    workspace_formats(format).any(|fmt| fmt.contains("{monitor"))
+}
+
+/// Returns true if `rename_cmd` depends on the workspace monitor.
+fn rename_cmd_uses_monitor_vars(behavior: &ConfigBehaviorRaw) -> bool {
+    behavior
+        .rename_cmd
+        .as_ref()
+        .is_some_and(|cmd| cmd.contains("{monitor"))
+}
+
+fn count_fullscreen_clients(clients: &[AppClient]) -> usize {
+    clients
+        .iter()
+        .filter(|c| c.is_fullscreen != FullscreenMode::None)
+        .count()
+}
+
+fn count_clients(workspaces: &[AppWorkspace]) -> usize {
+    workspaces.iter().map(|w| w.clients.len()).sum()
+}
+
+/// Generates the variables shared by all workspaces formats.
+fn generate_global_vars(workspaces: &[AppWorkspace]) -> HashMap<String, String> {
+    HashMap::from([(
+        "total_clients".to_string(),
+        count_clients(workspaces).to_string(),
+    )])
+}
+
+/// Returns the workspaces whose final name differs between two configs,
+/// as `(id, active name, candidate name)` sorted by id.
+fn diff_workspaces_names(
+    active_names: &HashMap<i32, String>,
+    candidate_names: &HashMap<i32, String>,
+) -> Vec<(i32, String, String)> {
+    let ids: BTreeSet<i32> = active_names
+        .keys()
+        .chain(candidate_names.keys())
+        .copied()
+        .collect();
+
+    ids.into_iter()
+        .filter_map(|id| {
+            let active = active_names.get(&id).cloned().unwrap_or_default();
+            let candidate = candidate_names.get(&id).cloned().unwrap_or_default();
+            (active != candidate).then_some((id, active, candidate))
+        })
+        .collect()
+}
+
+fn get_workspace_name(id: i32, workspaces_name: &[(String, String)]) -> String {
     let default_workspace_name = id.to_string();
     workspaces_name
         .iter()
@@ -313,27 +2029,110 @@ fn get_workspace_name(id: i32, workspaces_name: &[(String, String)]) -> String {
         .to_string()
 }
 
-fn get_filtered_clients(config: &ConfigFile) -> Vec<Client> {
-    let binding = Clients::get().unwrap();
+/// Drops the excluded clients, and the unmanaged ones unless
+/// `behavior.include_unmanaged`, and counts the excluded clients of each
+/// workspace.
+fn filter_clients(
+    config: &ConfigFile,
+    clients: Vec<Client>,
+    debug: bool,
+) -> (Vec<Client>, HashMap<i32, usize>) {
     let config_exclude = &config.exclude;
 
-    binding
+    let (clients, excluded): (Vec<Client>, Vec<Client>) = clients
         .into_iter()
-        .filter(|client| client.pid > 0)
         .filter(|client| {
+            let keep = client.pid > 0 || config.behavior.include_unmanaged;
+            if !keep && debug {
+                println!(
+                    "unmanaged client {} ({}) dropped, pid {}",
+                    client.address, client.class, client.pid
+                );
+            }
+            keep
+        })
+        .partition(|client| {
             !config_exclude.iter().any(|(class, title)| {
                 class.is_match(&client.class) && (title.is_match(&client.title))
             })
-        })
-        .collect::<Vec<Client>>()
+        });
+
+    let mut excluded_counts = HashMap::new();
+    for client in excluded {
+        *excluded_counts.entry(client.workspace.id).or_insert(0) += 1;
+    }
+    (clients, excluded_counts)
 }
 
-fn get_active_client() -> String {
-    Client::get_active()
-        .unwrap_or(None)
-        .map(|x| x.address)
-        .unwrap_or(Address::new("0"))
-        .to_string()
+/// Tells if the workspace is on a monitor of `behavior.monitors`, any
+/// workspace is when the list is empty.
+fn is_managed(
+    behavior: &ConfigBehaviorRaw,
+    workspace_monitors: &HashMap<i32, String>,
+    id: i32,
+) -> bool {
+    behavior.monitors.is_empty()
+        || workspace_monitors
+            .get(&id)
+            .is_some_and(|monitor| behavior.monitors.contains(monitor))
+}
+
+fn is_singleton(config: &ConfigFile, class: &str) -> bool {
+    config.singleton.iter().any(|re| re.is_match(class))
+}
+
+/// Keeps the clients of each singleton class on a single workspace: the one
+/// of its last focused window, else of its most recently focused one.
+/// Returns the other clients, and the number of hidden clients by workspace.
+fn hide_singletons(
+    config: &ConfigFile,
+    clients: Vec<Client>,
+    singletons: &SingletonTracker,
+) -> (Vec<Client>, HashMap<i32, usize>) {
+    // The last focused window first, then by focus history
+    let mut owners: HashMap<String, (bool, i8, i32)> = HashMap::new();
+    for client in clients.iter().filter(|c| is_singleton(config, &c.class)) {
+        let is_focused = singletons.get(&client.class) == Some(&client.address.to_string());
+        let rank = (!is_focused, client.focus_history_id, client.workspace.id);
+        owners
+            .entry(client.class.clone())
+            .and_modify(|owner| *owner = rank.min(*owner))
+            .or_insert(rank);
+    }
+
+    let (clients, hidden): (Vec<Client>, Vec<Client>) =
+        clients
+            .into_iter()
+            .partition(|client| match owners.get(&client.class) {
+                Some(&(_, _, id)) => id == client.workspace.id,
+                None => true,
+            });
+
+    let mut hidden_counts = HashMap::new();
+    for client in hidden {
+        *hidden_counts.entry(client.workspace.id).or_insert(0) += 1;
+    }
+    (clients, hidden_counts)
+}
+
+/// Orders workspace ids: focused first, then visible ones, then the rest.
+fn sort_by_priority<'a>(
+    ids: impl Iterator<Item = &'a i32>,
+    focused_id: Option<i32>,
+    visible_ids: &[i32],
+) -> Vec<i32> {
+    let mut ids: Vec<i32> = ids.copied().collect();
+    ids.sort_by_key(|id| {
+        let priority = if Some(*id) == focused_id {
+            0
+        } else if visible_ids.contains(id) {
+            1
+        } else {
+            2
+        };
+        (priority, *id)
+    });
+    ids
 }
 
 #[cfg(test)]
@@ -345,6 +2144,7 @@ mod tests {
     use crate::renamer::IconStatus::*;
 
     #[test]
+    #[allow(clippy::bool_assert_comparison)]
     fn test_app_client_partial_eq() {
         let client1 = AppClient {
             initial_class: "kitty".to_string(),
@@ -355,6 +2155,14 @@ mod tests {
             initial_title: "zsh".to_string(),
             matched_rule: Inactive(Class("(kitty|alacritty)".to_string(), "term".to_string())),
             is_dedup_inactive_fullscreen: false,
+            size: (0, 0),
+            urgent_for: None,
+            is_unmanaged: false,
+            is_floating: false,
+            is_pinned: false,
+            is_xwayland: false,
+            position: (0, 0),
+            is_fullscreen_client: FullscreenMode::None,
         };
 
         let client2 = AppClient {
@@ -366,6 +2174,14 @@ mod tests {
             is_fullscreen: FullscreenMode::Fullscreen,
             matched_rule: Inactive(Class("(kitty|alacritty)".to_string(), "term".to_string())),
             is_dedup_inactive_fullscreen: false,
+            size: (0, 0),
+            urgent_for: None,
+            is_unmanaged: false,
+            is_floating: false,
+            is_pinned: false,
+            is_xwayland: false,
+            position: (0, 0),
+            is_fullscreen_client: FullscreenMode::None,
         };
 
         let client3 = AppClient {
@@ -377,6 +2193,14 @@ mod tests {
             is_fullscreen: FullscreenMode::None,
             matched_rule: Active(Class("(kitty|alacritty)".to_string(), "term".to_string())),
             is_dedup_inactive_fullscreen: false,
+            size: (0, 0),
+            urgent_for: None,
+            is_unmanaged: false,
+            is_floating: false,
+            is_pinned: false,
+            is_xwayland: false,
+            position: (0, 0),
+            is_fullscreen_client: FullscreenMode::None,
         };
 
         let client4 = AppClient {
@@ -388,6 +2212,14 @@ mod tests {
             is_fullscreen: FullscreenMode::Fullscreen,
             matched_rule: Inactive(Class("(kitty|alacritty)".to_string(), "term".to_string())),
             is_dedup_inactive_fullscreen: false,
+            size: (0, 0),
+            urgent_for: None,
+            is_unmanaged: false,
+            is_floating: false,
+            is_pinned: false,
+            is_xwayland: false,
+            position: (0, 0),
+            is_fullscreen_client: FullscreenMode::None,
         };
 
         let client5 = AppClient {
@@ -399,6 +2231,14 @@ mod tests {
             is_fullscreen: FullscreenMode::Fullscreen,
             matched_rule: Inactive(Class("(kitty|alacritty)".to_string(), "term".to_string())),
             is_dedup_inactive_fullscreen: false,
+            size: (0, 0),
+            urgent_for: None,
+            is_unmanaged: false,
+            is_floating: false,
+            is_pinned: false,
+            is_xwayland: false,
+            position: (0, 0),
+            is_fullscreen_client: FullscreenMode::None,
         };
 
         let client6 = AppClient {
@@ -410,13 +2250,25 @@ mod tests {
             is_fullscreen: FullscreenMode::None,
             matched_rule: Inactive(Class("alacritty".to_string(), "term".to_string())),
             is_dedup_inactive_fullscreen: false,
+            size: (0, 0),
+            urgent_for: None,
+            is_unmanaged: false,
+            is_floating: false,
+            is_pinned: false,
+            is_xwayland: false,
+            position: (0, 0),
+            is_fullscreen_client: FullscreenMode::None,
         };
 
+        let mut client7 = client5.clone();
+        client7.is_fullscreen_client = FullscreenMode::Maximized;
+
         assert_eq!(client1 == client2, true);
         assert_eq!(client4 == client5, true);
         assert_eq!(client1 == client4, true);
         assert_eq!(client1 == client3, false);
         assert_eq!(client5 == client6, false);
+        assert!(client5 == client7);
     }
 
     #[test]
@@ -437,9 +2289,20 @@ mod tests {
             Args {
                 verbose: false,
                 debug: false,
+                debug_filter: None,
                 config: None,
                 dump: false,
                 migrate_config: false,
+                check_config: false,
+                shadow: None,
+                config_from_stdin: false,
+                config_inline: None,
+                state_dir: None,
+                schema: false,
+                print_default_config: false,
+                backend: None,
+                validate_regex: None,
+                command: None,
             },
         );
 
@@ -447,6 +2310,7 @@ mod tests {
 
         let actual = renamer.generate_workspaces_string(
             vec![AppWorkspace {
+                excluded_count: 0,
                 id: 1,
                 clients: vec![
                     AppClient {
@@ -465,6 +2329,14 @@ mod tests {
                             &config,
                         ),
                         is_dedup_inactive_fullscreen: false,
+                        size: (0, 0),
+                        urgent_for: None,
+                        is_unmanaged: false,
+                        is_floating: false,
+                        is_pinned: false,
+                        is_xwayland: false,
+                        position: (0, 0),
+                        is_fullscreen_client: FullscreenMode::None,
                     },
                     AppClient {
                         class: "kitty".to_string(),
@@ -482,6 +2354,14 @@ mod tests {
                             &config,
                         ),
                         is_dedup_inactive_fullscreen: false,
+                        size: (0, 0),
+                        urgent_for: None,
+                        is_unmanaged: false,
+                        is_floating: false,
+                        is_pinned: false,
+                        is_xwayland: false,
+                        position: (0, 0),
+                        is_fullscreen_client: FullscreenMode::None,
                     },
                     AppClient {
                         initial_class: "alacritty".to_string(),
@@ -499,6 +2379,14 @@ mod tests {
                             &config,
                         ),
                         is_dedup_inactive_fullscreen: false,
+                        size: (0, 0),
+                        urgent_for: None,
+                        is_unmanaged: false,
+                        is_floating: false,
+                        is_pinned: false,
+                        is_xwayland: false,
+                        position: (0, 0),
+                        is_fullscreen_client: FullscreenMode::None,
                     },
                     AppClient {
                         class: "alacritty".to_string(),
@@ -516,6 +2404,14 @@ mod tests {
                             &config,
                         ),
                         is_dedup_inactive_fullscreen: false,
+                        size: (0, 0),
+                        urgent_for: None,
+                        is_unmanaged: false,
+                        is_floating: false,
+                        is_pinned: false,
+                        is_xwayland: false,
+                        position: (0, 0),
+                        is_fullscreen_client: FullscreenMode::None,
                     },
                     AppClient {
                         initial_class: "alacritty".to_string(),
@@ -533,6 +2429,14 @@ mod tests {
                             &config,
                         ),
                         is_dedup_inactive_fullscreen: false,
+                        size: (0, 0),
+                        urgent_for: None,
+                        is_unmanaged: false,
+                        is_floating: false,
+                        is_pinned: false,
+                        is_xwayland: false,
+                        position: (0, 0),
+                        is_fullscreen_client: FullscreenMode::None,
                     },
                 ],
             }],
@@ -573,9 +2477,20 @@ mod tests {
             Args {
                 verbose: false,
                 debug: false,
+                debug_filter: None,
                 config: None,
                 dump: false,
                 migrate_config: false,
+                check_config: false,
+                shadow: None,
+                config_from_stdin: false,
+                config_inline: None,
+                state_dir: None,
+                schema: false,
+                print_default_config: false,
+                backend: None,
+                validate_regex: None,
+                command: None,
             },
         );
 
@@ -583,6 +2498,7 @@ mod tests {
 
         let actual = renamer.generate_workspaces_string(
             vec![AppWorkspace {
+                excluded_count: 0,
                 id: 1,
                 clients: vec![
                     AppClient {
@@ -601,6 +2517,14 @@ mod tests {
                             &config,
                         ),
                         is_dedup_inactive_fullscreen: false,
+                        size: (0, 0),
+                        urgent_for: None,
+                        is_unmanaged: false,
+                        is_floating: false,
+                        is_pinned: false,
+                        is_xwayland: false,
+                        position: (0, 0),
+                        is_fullscreen_client: FullscreenMode::None,
                     },
                     AppClient {
                         initial_class: "alacritty".to_string(),
@@ -618,6 +2542,14 @@ mod tests {
                             &config,
                         ),
                         is_dedup_inactive_fullscreen: false,
+                        size: (0, 0),
+                        urgent_for: None,
+                        is_unmanaged: false,
+                        is_floating: false,
+                        is_pinned: false,
+                        is_xwayland: false,
+                        position: (0, 0),
+                        is_fullscreen_client: FullscreenMode::None,
                     },
                     AppClient {
                         initial_class: "kitty".to_string(),
@@ -635,6 +2567,14 @@ mod tests {
                             &config,
                         ),
                         is_dedup_inactive_fullscreen: false,
+                        size: (0, 0),
+                        urgent_for: None,
+                        is_unmanaged: false,
+                        is_floating: false,
+                        is_pinned: false,
+                        is_xwayland: false,
+                        position: (0, 0),
+                        is_fullscreen_client: FullscreenMode::None,
                     },
                 ],
             }],
@@ -665,9 +2605,20 @@ mod tests {
             Args {
                 verbose: false,
                 debug: false,
+                debug_filter: None,
                 config: None,
                 dump: false,
                 migrate_config: false,
+                check_config: false,
+                shadow: None,
+                config_from_stdin: false,
+                config_inline: None,
+                state_dir: None,
+                schema: false,
+                print_default_config: false,
+                backend: None,
+                validate_regex: None,
+                command: None,
             },
         );
 
@@ -675,6 +2626,7 @@ mod tests {
 
         let actual = renamer.generate_workspaces_string(
             vec![AppWorkspace {
+                excluded_count: 0,
                 id: 1,
                 clients: vec![
                     AppClient {
@@ -693,6 +2645,14 @@ mod tests {
                             &config,
                         ),
                         is_dedup_inactive_fullscreen: false,
+                        size: (0, 0),
+                        urgent_for: None,
+                        is_unmanaged: false,
+                        is_floating: false,
+                        is_pinned: false,
+                        is_xwayland: false,
+                        position: (0, 0),
+                        is_fullscreen_client: FullscreenMode::None,
                     },
                     AppClient {
                         class: "kitty".to_string(),
@@ -710,6 +2670,14 @@ mod tests {
                             &config,
                         ),
                         is_dedup_inactive_fullscreen: false,
+                        size: (0, 0),
+                        urgent_for: None,
+                        is_unmanaged: false,
+                        is_floating: false,
+                        is_pinned: false,
+                        is_xwayland: false,
+                        position: (0, 0),
+                        is_fullscreen_client: FullscreenMode::None,
                     },
                     AppClient {
                         class: "alacritty".to_string(),
@@ -727,6 +2695,14 @@ mod tests {
                             &config,
                         ),
                         is_dedup_inactive_fullscreen: false,
+                        size: (0, 0),
+                        urgent_for: None,
+                        is_unmanaged: false,
+                        is_floating: false,
+                        is_pinned: false,
+                        is_xwayland: false,
+                        position: (0, 0),
+                        is_fullscreen_client: FullscreenMode::None,
                     },
                     AppClient {
                         class: "alacritty".to_string(),
@@ -744,6 +2720,14 @@ mod tests {
                             &config,
                         ),
                         is_dedup_inactive_fullscreen: false,
+                        size: (0, 0),
+                        urgent_for: None,
+                        is_unmanaged: false,
+                        is_floating: false,
+                        is_pinned: false,
+                        is_xwayland: false,
+                        position: (0, 0),
+                        is_fullscreen_client: FullscreenMode::None,
                     },
                     AppClient {
                         initial_class: "alacritty".to_string(),
@@ -761,6 +2745,14 @@ mod tests {
                             &config,
                         ),
                         is_dedup_inactive_fullscreen: false,
+                        size: (0, 0),
+                        urgent_for: None,
+                        is_unmanaged: false,
+                        is_floating: false,
+                        is_pinned: false,
+                        is_xwayland: false,
+                        position: (0, 0),
+                        is_fullscreen_client: FullscreenMode::None,
                     },
                 ],
             }],
@@ -774,8 +2766,18 @@ mod tests {
     fn test_to_superscript() {
         let input = 1234567890;
         let expected = "¹²³⁴⁵⁶⁷⁸⁹⁰";
-        let output = to_superscript(input);
+        let output = to_superscript(input, &HashMap::new());
         assert_eq!(expected, output);
+
+        let dots = HashMap::from([
+            ("1".to_string(), "·".to_string()),
+            ("2".to_string(), "⁚".to_string()),
+            ("10".to_string(), "⑩".to_string()),
+        ]);
+        assert_eq!(to_superscript(2, &dots), "⁚");
+        assert_eq!(to_superscript(10, &dots), "⑩");
+        assert_eq!(to_superscript(12, &dots), "·⁚");
+        assert_eq!(to_superscript(13, &dots), "·³");
     }
 
     #[test]
@@ -793,9 +2795,20 @@ mod tests {
             Args {
                 verbose: false,
                 debug: false,
+                debug_filter: None,
                 config: None,
                 dump: false,
                 migrate_config: false,
+                check_config: false,
+                shadow: None,
+                config_from_stdin: false,
+                config_inline: None,
+                state_dir: None,
+                schema: false,
+                print_default_config: false,
+                backend: None,
+                validate_regex: None,
+                command: None,
             },
         );
 
@@ -805,6 +2818,7 @@ mod tests {
 
         let actual = renamer.generate_workspaces_string(
             vec![AppWorkspace {
+                excluded_count: 0,
                 id: 1,
                 clients: vec![
                     AppClient {
@@ -823,6 +2837,14 @@ mod tests {
                             &config,
                         ),
                         is_dedup_inactive_fullscreen: false,
+                        size: (0, 0),
+                        urgent_for: None,
+                        is_unmanaged: false,
+                        is_floating: false,
+                        is_pinned: false,
+                        is_xwayland: false,
+                        position: (0, 0),
+                        is_fullscreen_client: FullscreenMode::None,
                     },
                     AppClient {
                         class: "kitty".to_string(),
@@ -840,6 +2862,14 @@ mod tests {
                             &config,
                         ),
                         is_dedup_inactive_fullscreen: false,
+                        size: (0, 0),
+                        urgent_for: None,
+                        is_unmanaged: false,
+                        is_floating: false,
+                        is_pinned: false,
+                        is_xwayland: false,
+                        position: (0, 0),
+                        is_fullscreen_client: FullscreenMode::None,
                     },
                     AppClient {
                         class: "kitty".to_string(),
@@ -857,6 +2887,14 @@ mod tests {
                             &config,
                         ),
                         is_dedup_inactive_fullscreen: false,
+                        size: (0, 0),
+                        urgent_for: None,
+                        is_unmanaged: false,
+                        is_floating: false,
+                        is_pinned: false,
+                        is_xwayland: false,
+                        position: (0, 0),
+                        is_fullscreen_client: FullscreenMode::None,
                     },
                     AppClient {
                         initial_class: "kitty".to_string(),
@@ -874,6 +2912,14 @@ mod tests {
                             &config,
                         ),
                         is_dedup_inactive_fullscreen: false,
+                        size: (0, 0),
+                        urgent_for: None,
+                        is_unmanaged: false,
+                        is_floating: false,
+                        is_pinned: false,
+                        is_xwayland: false,
+                        position: (0, 0),
+                        is_fullscreen_client: FullscreenMode::None,
                     },
                     AppClient {
                         class: "kitty".to_string(),
@@ -891,6 +2937,14 @@ mod tests {
                             &config,
                         ),
                         is_dedup_inactive_fullscreen: false,
+                        size: (0, 0),
+                        urgent_for: None,
+                        is_unmanaged: false,
+                        is_floating: false,
+                        is_pinned: false,
+                        is_xwayland: false,
+                        position: (0, 0),
+                        is_fullscreen_client: FullscreenMode::None,
                     },
                 ],
             }],
@@ -916,9 +2970,20 @@ mod tests {
             Args {
                 verbose: false,
                 debug: false,
+                debug_filter: None,
                 dump: false,
                 config: None,
                 migrate_config: false,
+                check_config: false,
+                shadow: None,
+                config_from_stdin: false,
+                config_inline: None,
+                state_dir: None,
+                schema: false,
+                print_default_config: false,
+                backend: None,
+                validate_regex: None,
+                command: None,
             },
         );
 
@@ -928,6 +2993,7 @@ mod tests {
 
         let actual = renamer.generate_workspaces_string(
             vec![AppWorkspace {
+                excluded_count: 0,
                 id: 1,
                 clients: vec![
                     AppClient {
@@ -946,6 +3012,14 @@ mod tests {
                             &config,
                         ),
                         is_dedup_inactive_fullscreen: false,
+                        size: (0, 0),
+                        urgent_for: None,
+                        is_unmanaged: false,
+                        is_floating: false,
+                        is_pinned: false,
+                        is_xwayland: false,
+                        position: (0, 0),
+                        is_fullscreen_client: FullscreenMode::None,
                     },
                     AppClient {
                         initial_class: "kitty".to_string(),
@@ -963,6 +3037,14 @@ mod tests {
                             &config,
                         ),
                         is_dedup_inactive_fullscreen: false,
+                        size: (0, 0),
+                        urgent_for: None,
+                        is_unmanaged: false,
+                        is_floating: false,
+                        is_pinned: false,
+                        is_xwayland: false,
+                        position: (0, 0),
+                        is_fullscreen_client: FullscreenMode::None,
                     },
                     AppClient {
                         class: "kitty".to_string(),
@@ -980,6 +3062,14 @@ mod tests {
                             &config,
                         ),
                         is_dedup_inactive_fullscreen: false,
+                        size: (0, 0),
+                        urgent_for: None,
+                        is_unmanaged: false,
+                        is_floating: false,
+                        is_pinned: false,
+                        is_xwayland: false,
+                        position: (0, 0),
+                        is_fullscreen_client: FullscreenMode::None,
                     },
                     AppClient {
                         class: "kitty".to_string(),
@@ -997,6 +3087,14 @@ mod tests {
                             &config,
                         ),
                         is_dedup_inactive_fullscreen: false,
+                        size: (0, 0),
+                        urgent_for: None,
+                        is_unmanaged: false,
+                        is_floating: false,
+                        is_pinned: false,
+                        is_xwayland: false,
+                        position: (0, 0),
+                        is_fullscreen_client: FullscreenMode::None,
                     },
                     AppClient {
                         class: "kitty".to_string(),
@@ -1014,6 +3112,14 @@ mod tests {
                             &config,
                         ),
                         is_dedup_inactive_fullscreen: false,
+                        size: (0, 0),
+                        urgent_for: None,
+                        is_unmanaged: false,
+                        is_floating: false,
+                        is_pinned: false,
+                        is_xwayland: false,
+                        position: (0, 0),
+                        is_fullscreen_client: FullscreenMode::None,
                     },
                 ],
             }],
@@ -1040,9 +3146,20 @@ mod tests {
             Args {
                 verbose: false,
                 debug: false,
+                debug_filter: None,
                 dump: false,
                 migrate_config: false,
+                check_config: false,
                 config: None,
+                shadow: None,
+                config_from_stdin: false,
+                config_inline: None,
+                state_dir: None,
+                schema: false,
+                print_default_config: false,
+                backend: None,
+                validate_regex: None,
+                command: None,
             },
         );
 
@@ -1052,6 +3169,7 @@ mod tests {
 
         let actual = renamer.generate_workspaces_string(
             vec![AppWorkspace {
+                excluded_count: 0,
                 id: 1,
                 clients: vec![
                     AppClient {
@@ -1070,6 +3188,14 @@ mod tests {
                             &config,
                         ),
                         is_dedup_inactive_fullscreen: false,
+                        size: (0, 0),
+                        urgent_for: None,
+                        is_unmanaged: false,
+                        is_floating: false,
+                        is_pinned: false,
+                        is_xwayland: false,
+                        position: (0, 0),
+                        is_fullscreen_client: FullscreenMode::None,
                     },
                     AppClient {
                         class: "kitty".to_string(),
@@ -1087,6 +3213,14 @@ mod tests {
                             &config,
                         ),
                         is_dedup_inactive_fullscreen: false,
+                        size: (0, 0),
+                        urgent_for: None,
+                        is_unmanaged: false,
+                        is_floating: false,
+                        is_pinned: false,
+                        is_xwayland: false,
+                        position: (0, 0),
+                        is_fullscreen_client: FullscreenMode::None,
                     },
                     AppClient {
                         class: "kitty".to_string(),
@@ -1104,6 +3238,14 @@ mod tests {
                             &config,
                         ),
                         is_dedup_inactive_fullscreen: false,
+                        size: (0, 0),
+                        urgent_for: None,
+                        is_unmanaged: false,
+                        is_floating: false,
+                        is_pinned: false,
+                        is_xwayland: false,
+                        position: (0, 0),
+                        is_fullscreen_client: FullscreenMode::None,
                     },
                     AppClient {
                         class: "kitty".to_string(),
@@ -1121,6 +3263,14 @@ mod tests {
                             &config,
                         ),
                         is_dedup_inactive_fullscreen: false,
+                        size: (0, 0),
+                        urgent_for: None,
+                        is_unmanaged: false,
+                        is_floating: false,
+                        is_pinned: false,
+                        is_xwayland: false,
+                        position: (0, 0),
+                        is_fullscreen_client: FullscreenMode::None,
                     },
                     AppClient {
                         initial_class: "kitty".to_string(),
@@ -1138,6 +3288,14 @@ mod tests {
                             &config,
                         ),
                         is_dedup_inactive_fullscreen: false,
+                        size: (0, 0),
+                        urgent_for: None,
+                        is_unmanaged: false,
+                        is_floating: false,
+                        is_pinned: false,
+                        is_xwayland: false,
+                        position: (0, 0),
+                        is_fullscreen_client: FullscreenMode::None,
                     },
                 ],
             }],
@@ -1164,9 +3322,20 @@ mod tests {
             Args {
                 verbose: false,
                 debug: false,
+                debug_filter: None,
                 dump: false,
                 migrate_config: false,
+                check_config: false,
                 config: None,
+                shadow: None,
+                config_from_stdin: false,
+                config_inline: None,
+                state_dir: None,
+                schema: false,
+                print_default_config: false,
+                backend: None,
+                validate_regex: None,
+                command: None,
             },
         );
 
@@ -1176,6 +3345,7 @@ mod tests {
 
         let actual = renamer.generate_workspaces_string(
             vec![AppWorkspace {
+                excluded_count: 0,
                 id: 1,
                 clients: vec![
                     AppClient {
@@ -1194,6 +3364,14 @@ mod tests {
                             &config,
                         ),
                         is_dedup_inactive_fullscreen: false,
+                        size: (0, 0),
+                        urgent_for: None,
+                        is_unmanaged: false,
+                        is_floating: false,
+                        is_pinned: false,
+                        is_xwayland: false,
+                        position: (0, 0),
+                        is_fullscreen_client: FullscreenMode::None,
                     },
                     AppClient {
                         class: "kitty".to_string(),
@@ -1211,6 +3389,14 @@ mod tests {
                             &config,
                         ),
                         is_dedup_inactive_fullscreen: false,
+                        size: (0, 0),
+                        urgent_for: None,
+                        is_unmanaged: false,
+                        is_floating: false,
+                        is_pinned: false,
+                        is_xwayland: false,
+                        position: (0, 0),
+                        is_fullscreen_client: FullscreenMode::None,
                     },
                     AppClient {
                         class: "kitty".to_string(),
@@ -1228,6 +3414,14 @@ mod tests {
                             &config,
                         ),
                         is_dedup_inactive_fullscreen: false,
+                        size: (0, 0),
+                        urgent_for: None,
+                        is_unmanaged: false,
+                        is_floating: false,
+                        is_pinned: false,
+                        is_xwayland: false,
+                        position: (0, 0),
+                        is_fullscreen_client: FullscreenMode::None,
                     },
                     AppClient {
                         class: "kitty".to_string(),
@@ -1245,6 +3439,14 @@ mod tests {
                             &config,
                         ),
                         is_dedup_inactive_fullscreen: false,
+                        size: (0, 0),
+                        urgent_for: None,
+                        is_unmanaged: false,
+                        is_floating: false,
+                        is_pinned: false,
+                        is_xwayland: false,
+                        position: (0, 0),
+                        is_fullscreen_client: FullscreenMode::None,
                     },
                     AppClient {
                         class: "kitty".to_string(),
@@ -1262,6 +3464,14 @@ mod tests {
                             &config,
                         ),
                         is_dedup_inactive_fullscreen: false,
+                        size: (0, 0),
+                        urgent_for: None,
+                        is_unmanaged: false,
+                        is_floating: false,
+                        is_pinned: false,
+                        is_xwayland: false,
+                        position: (0, 0),
+                        is_fullscreen_client: FullscreenMode::None,
                     },
                 ],
             }],
@@ -1288,9 +3498,20 @@ mod tests {
             Args {
                 verbose: false,
                 debug: false,
+                debug_filter: None,
                 dump: false,
                 migrate_config: false,
+                check_config: false,
                 config: None,
+                shadow: None,
+                config_from_stdin: false,
+                config_inline: None,
+                state_dir: None,
+                schema: false,
+                print_default_config: false,
+                backend: None,
+                validate_regex: None,
+                command: None,
             },
         );
 
@@ -1298,6 +3519,7 @@ mod tests {
 
         let actual = renamer.generate_workspaces_string(
             vec![AppWorkspace {
+                excluded_count: 0,
                 id: 1,
                 clients: vec![
                     AppClient {
@@ -1309,6 +3531,14 @@ mod tests {
                         is_fullscreen: FullscreenMode::None,
                         matched_rule: Inactive(Class("kitty".to_string(), "term".to_string())),
                         is_dedup_inactive_fullscreen: false,
+                        size: (0, 0),
+                        urgent_for: None,
+                        is_unmanaged: false,
+                        is_floating: false,
+                        is_pinned: false,
+                        is_xwayland: false,
+                        position: (0, 0),
+                        is_fullscreen_client: FullscreenMode::None,
                     },
                     AppClient {
                         initial_class: "kitty".to_string(),
@@ -1319,6 +3549,14 @@ mod tests {
                         is_fullscreen: FullscreenMode::None,
                         matched_rule: Inactive(Class("kitty".to_string(), "term".to_string())),
                         is_dedup_inactive_fullscreen: false,
+                        size: (0, 0),
+                        urgent_for: None,
+                        is_unmanaged: false,
+                        is_floating: false,
+                        is_pinned: false,
+                        is_xwayland: false,
+                        position: (0, 0),
+                        is_fullscreen_client: FullscreenMode::None,
                     },
                     AppClient {
                         initial_class: "kitty".to_string(),
@@ -1329,6 +3567,14 @@ mod tests {
                         is_fullscreen: FullscreenMode::None,
                         matched_rule: Inactive(Class("kitty".to_string(), "term".to_string())),
                         is_dedup_inactive_fullscreen: false,
+                        size: (0, 0),
+                        urgent_for: None,
+                        is_unmanaged: false,
+                        is_floating: false,
+                        is_pinned: false,
+                        is_xwayland: false,
+                        position: (0, 0),
+                        is_fullscreen_client: FullscreenMode::None,
                     },
                     AppClient {
                         initial_class: "kitty".to_string(),
@@ -1339,6 +3585,14 @@ mod tests {
                         is_fullscreen: FullscreenMode::None,
                         matched_rule: Inactive(Class("kitty".to_string(), "term".to_string())),
                         is_dedup_inactive_fullscreen: false,
+                        size: (0, 0),
+                        urgent_for: None,
+                        is_unmanaged: false,
+                        is_floating: false,
+                        is_pinned: false,
+                        is_xwayland: false,
+                        position: (0, 0),
+                        is_fullscreen_client: FullscreenMode::None,
                     },
                     AppClient {
                         initial_class: "kitty".to_string(),
@@ -1349,6 +3603,14 @@ mod tests {
                         is_fullscreen: FullscreenMode::None,
                         matched_rule: Inactive(Class("kitty".to_string(), "term".to_string())),
                         is_dedup_inactive_fullscreen: false,
+                        size: (0, 0),
+                        urgent_for: None,
+                        is_unmanaged: false,
+                        is_floating: false,
+                        is_pinned: false,
+                        is_xwayland: false,
+                        position: (0, 0),
+                        is_fullscreen_client: FullscreenMode::None,
                     },
                 ],
             }],
@@ -1378,9 +3640,20 @@ mod tests {
             Args {
                 verbose: false,
                 debug: false,
+                debug_filter: None,
                 dump: false,
                 migrate_config: false,
+                check_config: false,
                 config: None,
+                shadow: None,
+                config_from_stdin: false,
+                config_inline: None,
+                state_dir: None,
+                schema: false,
+                print_default_config: false,
+                backend: None,
+                validate_regex: None,
+                command: None,
             },
         );
 
@@ -1388,6 +3661,7 @@ mod tests {
 
         let actual = renamer.generate_workspaces_string(
             vec![AppWorkspace {
+                excluded_count: 0,
                 id: 1,
                 clients: vec![
                     AppClient {
@@ -1406,6 +3680,14 @@ mod tests {
                             &config,
                         ),
                         is_dedup_inactive_fullscreen: false,
+                        size: (0, 0),
+                        urgent_for: None,
+                        is_unmanaged: false,
+                        is_floating: false,
+                        is_pinned: false,
+                        is_xwayland: false,
+                        position: (0, 0),
+                        is_fullscreen_client: FullscreenMode::None,
                     },
                     AppClient {
                         class: "kitty".to_string(),
@@ -1423,6 +3705,14 @@ mod tests {
                             &config,
                         ),
                         is_dedup_inactive_fullscreen: false,
+                        size: (0, 0),
+                        urgent_for: None,
+                        is_unmanaged: false,
+                        is_floating: false,
+                        is_pinned: false,
+                        is_xwayland: false,
+                        position: (0, 0),
+                        is_fullscreen_client: FullscreenMode::None,
                     },
                     AppClient {
                         initial_class: "kitty".to_string(),
@@ -1440,6 +3730,14 @@ mod tests {
                             &config,
                         ),
                         is_dedup_inactive_fullscreen: false,
+                        size: (0, 0),
+                        urgent_for: None,
+                        is_unmanaged: false,
+                        is_floating: false,
+                        is_pinned: false,
+                        is_xwayland: false,
+                        position: (0, 0),
+                        is_fullscreen_client: FullscreenMode::None,
                     },
                     AppClient {
                         initial_class: "kitty".to_string(),
@@ -1457,6 +3755,14 @@ mod tests {
                             &config,
                         ),
                         is_dedup_inactive_fullscreen: false,
+                        size: (0, 0),
+                        urgent_for: None,
+                        is_unmanaged: false,
+                        is_floating: false,
+                        is_pinned: false,
+                        is_xwayland: false,
+                        position: (0, 0),
+                        is_fullscreen_client: FullscreenMode::None,
                     },
                     AppClient {
                         class: "kitty".to_string(),
@@ -1474,6 +3780,14 @@ mod tests {
                             &config,
                         ),
                         is_dedup_inactive_fullscreen: false,
+                        size: (0, 0),
+                        urgent_for: None,
+                        is_unmanaged: false,
+                        is_floating: false,
+                        is_pinned: false,
+                        is_xwayland: false,
+                        position: (0, 0),
+                        is_fullscreen_client: FullscreenMode::None,
                     },
                 ],
             }],
@@ -1503,9 +3817,20 @@ mod tests {
             Args {
                 verbose: false,
                 debug: false,
+                debug_filter: None,
                 config: None,
                 dump: false,
                 migrate_config: false,
+                check_config: false,
+                shadow: None,
+                config_from_stdin: false,
+                config_inline: None,
+                state_dir: None,
+                schema: false,
+                print_default_config: false,
+                backend: None,
+                validate_regex: None,
+                command: None,
             },
         );
 
@@ -1513,6 +3838,7 @@ mod tests {
 
         let actual = renamer.generate_workspaces_string(
             vec![AppWorkspace {
+                excluded_count: 0,
                 id: 1,
                 clients: vec![
                     AppClient {
@@ -1531,6 +3857,14 @@ mod tests {
                             &config,
                         ),
                         is_dedup_inactive_fullscreen: false,
+                        size: (0, 0),
+                        urgent_for: None,
+                        is_unmanaged: false,
+                        is_floating: false,
+                        is_pinned: false,
+                        is_xwayland: false,
+                        position: (0, 0),
+                        is_fullscreen_client: FullscreenMode::None,
                     },
                     AppClient {
                         class: "kitty".to_string(),
@@ -1548,6 +3882,14 @@ mod tests {
                             &config,
                         ),
                         is_dedup_inactive_fullscreen: false,
+                        size: (0, 0),
+                        urgent_for: None,
+                        is_unmanaged: false,
+                        is_floating: false,
+                        is_pinned: false,
+                        is_xwayland: false,
+                        position: (0, 0),
+                        is_fullscreen_client: FullscreenMode::None,
                     },
                     AppClient {
                         class: "kitty".to_string(),
@@ -1565,6 +3907,14 @@ mod tests {
                             &config,
                         ),
                         is_dedup_inactive_fullscreen: false,
+                        size: (0, 0),
+                        urgent_for: None,
+                        is_unmanaged: false,
+                        is_floating: false,
+                        is_pinned: false,
+                        is_xwayland: false,
+                        position: (0, 0),
+                        is_fullscreen_client: FullscreenMode::None,
                     },
                     AppClient {
                         class: "kitty".to_string(),
@@ -1582,6 +3932,14 @@ mod tests {
                             &config,
                         ),
                         is_dedup_inactive_fullscreen: false,
+                        size: (0, 0),
+                        urgent_for: None,
+                        is_unmanaged: false,
+                        is_floating: false,
+                        is_pinned: false,
+                        is_xwayland: false,
+                        position: (0, 0),
+                        is_fullscreen_client: FullscreenMode::None,
                     },
                     AppClient {
                         class: "kitty".to_string(),
@@ -1599,6 +3957,14 @@ mod tests {
                             &config,
                         ),
                         is_dedup_inactive_fullscreen: false,
+                        size: (0, 0),
+                        urgent_for: None,
+                        is_unmanaged: false,
+                        is_floating: false,
+                        is_pinned: false,
+                        is_xwayland: false,
+                        position: (0, 0),
+                        is_fullscreen_client: FullscreenMode::None,
                     },
                 ],
             }],
@@ -1631,9 +3997,20 @@ mod tests {
             Args {
                 verbose: false,
                 debug: false,
+                debug_filter: None,
                 config: None,
                 dump: false,
                 migrate_config: false,
+                check_config: false,
+                shadow: None,
+                config_from_stdin: false,
+                config_inline: None,
+                state_dir: None,
+                schema: false,
+                print_default_config: false,
+                backend: None,
+                validate_regex: None,
+                command: None,
             },
         );
 
@@ -1641,6 +4018,7 @@ mod tests {
 
         let actual = renamer.generate_workspaces_string(
             vec![AppWorkspace {
+                excluded_count: 0,
                 id: 1,
                 clients: vec![
                     AppClient {
@@ -1659,6 +4037,14 @@ mod tests {
                             &config,
                         ),
                         is_dedup_inactive_fullscreen: false,
+                        size: (0, 0),
+                        urgent_for: None,
+                        is_unmanaged: false,
+                        is_floating: false,
+                        is_pinned: false,
+                        is_xwayland: false,
+                        position: (0, 0),
+                        is_fullscreen_client: FullscreenMode::None,
                     },
                     AppClient {
                         class: "kitty".to_string(),
@@ -1676,6 +4062,14 @@ mod tests {
                             &config,
                         ),
                         is_dedup_inactive_fullscreen: false,
+                        size: (0, 0),
+                        urgent_for: None,
+                        is_unmanaged: false,
+                        is_floating: false,
+                        is_pinned: false,
+                        is_xwayland: false,
+                        position: (0, 0),
+                        is_fullscreen_client: FullscreenMode::None,
                     },
                     AppClient {
                         initial_class: "kitty".to_string(),
@@ -1693,6 +4087,14 @@ mod tests {
                             &config,
                         ),
                         is_dedup_inactive_fullscreen: false,
+                        size: (0, 0),
+                        urgent_for: None,
+                        is_unmanaged: false,
+                        is_floating: false,
+                        is_pinned: false,
+                        is_xwayland: false,
+                        position: (0, 0),
+                        is_fullscreen_client: FullscreenMode::None,
                     },
                     AppClient {
                         class: "kitty".to_string(),
@@ -1710,6 +4112,14 @@ mod tests {
                             &config,
                         ),
                         is_dedup_inactive_fullscreen: false,
+                        size: (0, 0),
+                        urgent_for: None,
+                        is_unmanaged: false,
+                        is_floating: false,
+                        is_pinned: false,
+                        is_xwayland: false,
+                        position: (0, 0),
+                        is_fullscreen_client: FullscreenMode::None,
                     },
                     AppClient {
                         initial_class: "kitty".to_string(),
@@ -1727,6 +4137,14 @@ mod tests {
                             &config,
                         ),
                         is_dedup_inactive_fullscreen: false,
+                        size: (0, 0),
+                        urgent_for: None,
+                        is_unmanaged: false,
+                        is_floating: false,
+                        is_pinned: false,
+                        is_xwayland: false,
+                        position: (0, 0),
+                        is_fullscreen_client: FullscreenMode::None,
                     },
                 ],
             }],
@@ -1766,9 +4184,20 @@ mod tests {
             Args {
                 verbose: false,
                 debug: false,
+                debug_filter: None,
                 config: None,
                 dump: false,
                 migrate_config: false,
+                check_config: false,
+                shadow: None,
+                config_from_stdin: false,
+                config_inline: None,
+                state_dir: None,
+                schema: false,
+                print_default_config: false,
+                backend: None,
+                validate_regex: None,
+                command: None,
             },
         );
 
@@ -1776,6 +4205,7 @@ mod tests {
 
         let actual = renamer.generate_workspaces_string(
             vec![AppWorkspace {
+                excluded_count: 0,
                 id: 1,
                 clients: vec![
                     AppClient {
@@ -1794,6 +4224,14 @@ mod tests {
                             &config,
                         ),
                         is_dedup_inactive_fullscreen: false,
+                        size: (0, 0),
+                        urgent_for: None,
+                        is_unmanaged: false,
+                        is_floating: false,
+                        is_pinned: false,
+                        is_xwayland: false,
+                        position: (0, 0),
+                        is_fullscreen_client: FullscreenMode::None,
                     },
                     AppClient {
                         class: "alacritty".to_string(),
@@ -1811,6 +4249,14 @@ mod tests {
                             &config,
                         ),
                         is_dedup_inactive_fullscreen: false,
+                        size: (0, 0),
+                        urgent_for: None,
+                        is_unmanaged: false,
+                        is_floating: false,
+                        is_pinned: false,
+                        is_xwayland: false,
+                        position: (0, 0),
+                        is_fullscreen_client: FullscreenMode::None,
                     },
                     AppClient {
                         class: "qute".to_string(),
@@ -1828,6 +4274,14 @@ mod tests {
                             &config,
                         ),
                         is_dedup_inactive_fullscreen: false,
+                        size: (0, 0),
+                        urgent_for: None,
+                        is_unmanaged: false,
+                        is_floating: false,
+                        is_pinned: false,
+                        is_xwayland: false,
+                        position: (0, 0),
+                        is_fullscreen_client: FullscreenMode::None,
                     },
                 ],
             }],
@@ -1853,9 +4307,20 @@ mod tests {
             Args {
                 verbose: false,
                 debug: false,
+                debug_filter: None,
                 config: None,
                 dump: false,
                 migrate_config: false,
+                check_config: false,
+                shadow: None,
+                config_from_stdin: false,
+                config_inline: None,
+                state_dir: None,
+                schema: false,
+                print_default_config: false,
+                backend: None,
+                validate_regex: None,
+                command: None,
             },
         );
 
@@ -1863,6 +4328,7 @@ mod tests {
 
         let actual = renamer.generate_workspaces_string(
             vec![AppWorkspace {
+                excluded_count: 0,
                 id: 1,
                 clients: vec![AppClient {
                     initial_class: "".to_string(),
@@ -1880,6 +4346,14 @@ mod tests {
                         &config,
                     ),
                     is_dedup_inactive_fullscreen: false,
+                    size: (0, 0),
+                    urgent_for: None,
+                    is_unmanaged: false,
+                    is_floating: false,
+                    is_pinned: false,
+                    is_xwayland: false,
+                    position: (0, 0),
+                    is_fullscreen_client: FullscreenMode::None,
                 }],
             }],
             &config,
@@ -1904,9 +4378,20 @@ mod tests {
             Args {
                 verbose: false,
                 debug: false,
+                debug_filter: None,
                 config: None,
                 dump: false,
                 migrate_config: false,
+                check_config: false,
+                shadow: None,
+                config_from_stdin: false,
+                config_inline: None,
+                state_dir: None,
+                schema: false,
+                print_default_config: false,
+                backend: None,
+                validate_regex: None,
+                command: None,
             },
         );
 
@@ -1914,6 +4399,7 @@ mod tests {
 
         let actual = renamer.generate_workspaces_string(
             vec![AppWorkspace {
+                excluded_count: 0,
                 id: 1,
                 clients: vec![AppClient {
                     initial_class: "osu!".to_string(),
@@ -1931,6 +4417,14 @@ mod tests {
                         &config,
                     ),
                     is_dedup_inactive_fullscreen: false,
+                    size: (0, 0),
+                    urgent_for: None,
+                    is_unmanaged: false,
+                    is_floating: false,
+                    is_pinned: false,
+                    is_xwayland: false,
+                    position: (0, 0),
+                    is_fullscreen_client: FullscreenMode::None,
                 }],
             }],
             &config,
@@ -1964,9 +4458,20 @@ mod tests {
             Args {
                 verbose: false,
                 debug: false,
+                debug_filter: None,
                 config: None,
                 dump: false,
                 migrate_config: false,
+                check_config: false,
+                shadow: None,
+                config_from_stdin: false,
+                config_inline: None,
+                state_dir: None,
+                schema: false,
+                print_default_config: false,
+                backend: None,
+                validate_regex: None,
+                command: None,
             },
         );
 
@@ -1976,6 +4481,7 @@ mod tests {
 
         let actual = renamer.generate_workspaces_string(
             vec![AppWorkspace {
+                excluded_count: 0,
                 id: 1,
                 clients: vec![
                     AppClient {
@@ -1994,6 +4500,14 @@ mod tests {
                             &config,
                         ),
                         is_dedup_inactive_fullscreen: false,
+                        size: (0, 0),
+                        urgent_for: None,
+                        is_unmanaged: false,
+                        is_floating: false,
+                        is_pinned: false,
+                        is_xwayland: false,
+                        position: (0, 0),
+                        is_fullscreen_client: FullscreenMode::None,
                     },
                     AppClient {
                         initial_class: "fake-app-unknown".to_string(),
@@ -2011,6 +4525,14 @@ mod tests {
                             &config,
                         ),
                         is_dedup_inactive_fullscreen: false,
+                        size: (0, 0),
+                        urgent_for: None,
+                        is_unmanaged: false,
+                        is_floating: false,
+                        is_pinned: false,
+                        is_xwayland: false,
+                        position: (0, 0),
+                        is_fullscreen_client: FullscreenMode::None,
                     },
                 ],
             }],
@@ -2037,9 +4559,20 @@ mod tests {
             Args {
                 verbose: false,
                 debug: false,
+                debug_filter: None,
                 config: None,
                 dump: false,
                 migrate_config: false,
+                check_config: false,
+                shadow: None,
+                config_from_stdin: false,
+                config_inline: None,
+                state_dir: None,
+                schema: false,
+                print_default_config: false,
+                backend: None,
+                validate_regex: None,
+                command: None,
             },
         );
 
@@ -2047,6 +4580,7 @@ mod tests {
 
         let actual = renamer.generate_workspaces_string(
             vec![AppWorkspace {
+                excluded_count: 0,
                 id: 1,
                 clients: vec![AppClient {
                     initial_class: "kitty".to_string(),
@@ -2064,6 +4598,14 @@ mod tests {
                         &config,
                     ),
                     is_dedup_inactive_fullscreen: false,
+                    size: (0, 0),
+                    urgent_for: None,
+                    is_unmanaged: false,
+                    is_floating: false,
+                    is_pinned: false,
+                    is_xwayland: false,
+                    position: (0, 0),
+                    is_fullscreen_client: FullscreenMode::None,
                 }],
             }],
             &config,
@@ -2082,14 +4624,26 @@ mod tests {
             Args {
                 verbose: false,
                 debug: false,
+                debug_filter: None,
                 config: None,
                 dump: false,
                 migrate_config: false,
+                check_config: false,
+                shadow: None,
+                config_from_stdin: false,
+                config_inline: None,
+                state_dir: None,
+                schema: false,
+                print_default_config: false,
+                backend: None,
+                validate_regex: None,
+                command: None,
             },
         );
 
         let actual = renamer.generate_workspaces_string(
             vec![AppWorkspace {
+                excluded_count: 0,
                 id: 1,
                 clients: vec![AppClient {
                     initial_class: "kitty".to_string(),
@@ -2107,6 +4661,14 @@ mod tests {
                         &config,
                     ),
                     is_dedup_inactive_fullscreen: false,
+                    size: (0, 0),
+                    urgent_for: None,
+                    is_unmanaged: false,
+                    is_floating: false,
+                    is_pinned: false,
+                    is_xwayland: false,
+                    position: (0, 0),
+                    is_fullscreen_client: FullscreenMode::None,
                 }],
             }],
             &config,
@@ -2145,9 +4707,20 @@ mod tests {
             Args {
                 verbose: false,
                 debug: false,
+                debug_filter: None,
                 config: None,
                 dump: false,
                 migrate_config: false,
+                check_config: false,
+                shadow: None,
+                config_from_stdin: false,
+                config_inline: None,
+                state_dir: None,
+                schema: false,
+                print_default_config: false,
+                backend: None,
+                validate_regex: None,
+                command: None,
             },
         );
 
@@ -2155,6 +4728,7 @@ mod tests {
 
         let actual = renamer.generate_workspaces_string(
             vec![AppWorkspace {
+                excluded_count: 0,
                 id: 1,
                 clients: vec![AppClient {
                     initial_class: "kitty".to_string(),
@@ -2164,6 +4738,14 @@ mod tests {
                     is_active: false,
                     is_fullscreen: FullscreenMode::None,
                     is_dedup_inactive_fullscreen: false,
+                    size: (0, 0),
+                    urgent_for: None,
+                    is_unmanaged: false,
+                    is_floating: false,
+                    is_pinned: false,
+                    is_xwayland: false,
+                    position: (0, 0),
+                    is_fullscreen_client: FullscreenMode::None,
                     matched_rule: renamer.parse_icon(
                         "kitty".to_string(),
                         "kitty".to_string(),
@@ -2192,14 +4774,26 @@ mod tests {
             Args {
                 verbose: false,
                 debug: false,
+                debug_filter: None,
                 config: None,
                 dump: false,
                 migrate_config: false,
+                check_config: false,
+                shadow: None,
+                config_from_stdin: false,
+                config_inline: None,
+                state_dir: None,
+                schema: false,
+                print_default_config: false,
+                backend: None,
+                validate_regex: None,
+                command: None,
             },
         );
 
         let actual = renamer.generate_workspaces_string(
             vec![AppWorkspace {
+                excluded_count: 0,
                 id: 1,
                 clients: vec![AppClient {
                     initial_class: "kitty".to_string(),
@@ -2217,6 +4811,14 @@ mod tests {
                         &config,
                     ),
                     is_dedup_inactive_fullscreen: false,
+                    size: (0, 0),
+                    urgent_for: None,
+                    is_unmanaged: false,
+                    is_floating: false,
+                    is_pinned: false,
+                    is_xwayland: false,
+                    position: (0, 0),
+                    is_fullscreen_client: FullscreenMode::None,
                 }],
             }],
             &config,
@@ -2239,14 +4841,26 @@ mod tests {
             Args {
                 verbose: false,
                 debug: false,
+                debug_filter: None,
                 config: None,
                 dump: false,
                 migrate_config: false,
+                check_config: false,
+                shadow: None,
+                config_from_stdin: false,
+                config_inline: None,
+                state_dir: None,
+                schema: false,
+                print_default_config: false,
+                backend: None,
+                validate_regex: None,
+                command: None,
             },
         );
 
         let actual = renamer.generate_workspaces_string(
             vec![AppWorkspace {
+                excluded_count: 0,
                 id: 1,
                 clients: vec![AppClient {
                     initial_class: "kitty".to_string(),
@@ -2264,6 +4878,14 @@ mod tests {
                         &config,
                     ),
                     is_dedup_inactive_fullscreen: false,
+                    size: (0, 0),
+                    urgent_for: None,
+                    is_unmanaged: false,
+                    is_floating: false,
+                    is_pinned: false,
+                    is_xwayland: false,
+                    position: (0, 0),
+                    is_fullscreen_client: FullscreenMode::None,
                 }],
             }],
             &config,
@@ -2289,9 +4911,20 @@ mod tests {
             Args {
                 verbose: false,
                 debug: false,
+                debug_filter: None,
                 config: None,
                 dump: false,
                 migrate_config: false,
+                check_config: false,
+                shadow: None,
+                config_from_stdin: false,
+                config_inline: None,
+                state_dir: None,
+                schema: false,
+                print_default_config: false,
+                backend: None,
+                validate_regex: None,
+                command: None,
             },
         );
 
@@ -2300,6 +4933,7 @@ mod tests {
 
         let mut app_workspaces = vec![
             AppWorkspace {
+                excluded_count: 0,
                 id: 1,
                 clients: vec![AppClient {
                     initial_class: "kitty".to_string(),
@@ -2317,9 +4951,18 @@ mod tests {
                         &config,
                     ),
                     is_dedup_inactive_fullscreen: false,
+                    size: (0, 0),
+                    urgent_for: None,
+                    is_unmanaged: false,
+                    is_floating: false,
+                    is_pinned: false,
+                    is_xwayland: false,
+                    position: (0, 0),
+                    is_fullscreen_client: FullscreenMode::None,
                 }],
             },
             AppWorkspace {
+                excluded_count: 0,
                 id: 2,
                 clients: vec![AppClient {
                     initial_class: "kitty".to_string(),
@@ -2337,6 +4980,14 @@ mod tests {
                         &config,
                     ),
                     is_dedup_inactive_fullscreen: false,
+                    size: (0, 0),
+                    urgent_for: None,
+                    is_unmanaged: false,
+                    is_floating: false,
+                    is_pinned: false,
+                    is_xwayland: false,
+                    position: (0, 0),
+                    is_fullscreen_client: FullscreenMode::None,
                 }],
             },
         ];
@@ -2363,6 +5014,7 @@ mod tests {
         assert!(altered_strings2.is_empty());
 
         app_workspaces.push(AppWorkspace {
+            excluded_count: 0,
             id: 3,
             clients: vec![AppClient {
                 initial_class: "kitty".to_string(),
@@ -2380,6 +5032,14 @@ mod tests {
                     &config,
                 ),
                 is_dedup_inactive_fullscreen: false,
+                size: (0, 0),
+                urgent_for: None,
+                is_unmanaged: false,
+                is_floating: false,
+                is_pinned: false,
+                is_xwayland: false,
+                position: (0, 0),
+                is_fullscreen_client: FullscreenMode::None,
             }],
         });
 
@@ -2397,6 +5057,7 @@ mod tests {
 
         // Generate different workspace set - should update cache
         let app_workspaces2 = vec![AppWorkspace {
+            excluded_count: 0,
             id: 4,
             clients: vec![AppClient {
                 initial_class: "kitty".to_string(),
@@ -2414,6 +5075,14 @@ mod tests {
                     &config,
                 ),
                 is_dedup_inactive_fullscreen: false,
+                size: (0, 0),
+                urgent_for: None,
+                is_unmanaged: false,
+                is_floating: false,
+                is_pinned: false,
+                is_xwayland: false,
+                position: (0, 0),
+                is_fullscreen_client: FullscreenMode::None,
             }],
         }];
 
@@ -2434,6 +5103,12 @@ mod tests {
             assert_eq!(cache.get(&2), None);
         }
 
+        // Test cache flush
+        renamer.flush_cache().unwrap();
+        assert_eq!(renamer.workspace_strings_cache.lock().unwrap().len(), 0);
+        let altered_strings4 = renamer.get_altered_workspaces(&strings3).unwrap();
+        assert_eq!(strings3, altered_strings4);
+
         // Test cache reset
         renamer.reset_workspaces(config.clone()).unwrap();
         assert_eq!(renamer.workspace_strings_cache.lock().unwrap().len(), 0);
@@ -2475,9 +5150,20 @@ mod tests {
             Args {
                 verbose: false,
                 debug: false,
+                debug_filter: None,
                 config: None,
                 dump: false,
                 migrate_config: false,
+                check_config: false,
+                shadow: None,
+                config_from_stdin: false,
+                config_inline: None,
+                state_dir: None,
+                schema: false,
+                print_default_config: false,
+                backend: None,
+                validate_regex: None,
+                command: None,
             },
         );
 
@@ -2487,6 +5173,7 @@ mod tests {
 
         let mut actual = renamer.generate_workspaces_string(
             vec![AppWorkspace {
+                excluded_count: 0,
                 id: 1,
                 clients: vec![AppClient {
                     initial_class: "foot".to_string(),
@@ -2504,6 +5191,14 @@ mod tests {
                         &config,
                     ),
                     is_dedup_inactive_fullscreen: false,
+                    size: (0, 0),
+                    urgent_for: None,
+                    is_unmanaged: false,
+                    is_floating: false,
+                    is_pinned: false,
+                    is_xwayland: false,
+                    position: (0, 0),
+                    is_fullscreen_client: FullscreenMode::None,
                 }],
             }],
             &config,
@@ -2520,6 +5215,7 @@ mod tests {
 
         actual = renamer.generate_workspaces_string(
             vec![AppWorkspace {
+                excluded_count: 0,
                 id: 1,
                 clients: vec![AppClient {
                     initial_class: "foot".to_string(),
@@ -2537,6 +5233,14 @@ mod tests {
                         &config,
                     ),
                     is_dedup_inactive_fullscreen: false,
+                    size: (0, 0),
+                    urgent_for: None,
+                    is_unmanaged: false,
+                    is_floating: false,
+                    is_pinned: false,
+                    is_xwayland: false,
+                    position: (0, 0),
+                    is_fullscreen_client: FullscreenMode::None,
                 }],
             }],
             &config,
@@ -2545,6 +5249,1866 @@ mod tests {
         assert_eq!(actual, expected);
     }
 
+    #[test]
+    fn test_client_active_fullscreen_combinations() {
+        let mut config = crate::config::read_config_file(None, false, false).unwrap();
+        config
+            .class
+            .push((Regex::new("kitty").unwrap(), "term".to_string()));
+
+        config.format.client = "c{icon}".to_string();
+        config.format.client_active = "a{icon}".to_string();
+        config.format.client_fullscreen = "f{icon}".to_string();
+        config.format.client_active_fullscreen = "af{icon}".to_string();
+        config.format.client_dup = "d{icon}{counter}".to_string();
+        config.format.client_dup_fullscreen = "df{icon}{counter}".to_string();
+        config.format.client_dup_active_fullscreen = "daf{icon}{counter}".to_string();
+
+        let renamer = Renamer::new(
+            Config {
+                cfg_path: None,
+                config: config.clone(),
+            },
+            Args {
+                verbose: false,
+                debug: false,
+                debug_filter: None,
+                config: None,
+                dump: false,
+                migrate_config: false,
+                check_config: false,
+                shadow: None,
+                config_from_stdin: false,
+                config_inline: None,
+                state_dir: None,
+                schema: false,
+                print_default_config: false,
+                backend: None,
+                validate_regex: None,
+                command: None,
+            },
+        );
+
+        let cases = [
+            (false, false, false, "cterm cterm"),
+            (false, false, true, "dterm2"),
+            (false, true, false, "fterm fterm"),
+            (false, true, true, "dfterm2"),
+            (true, false, false, "caterm caterm"),
+            (true, false, true, "daterm2"),
+            (true, true, false, "afaterm afaterm"),
+            (true, true, true, "dafaterm2"),
+        ];
+
+        for (is_active, is_fullscreen, dedup, expected) in cases {
+            config.format.dedup = dedup;
+
+            let client = AppClient {
+                initial_class: "kitty".to_string(),
+                class: "kitty".to_string(),
+                title: "kitty".to_string(),
+                initial_title: "kitty".to_string(),
+                is_active,
+                is_fullscreen: if is_fullscreen {
+                    FullscreenMode::Fullscreen
+                } else {
+                    FullscreenMode::None
+                },
+                matched_rule: renamer.parse_icon(
+                    "kitty".to_string(),
+                    "kitty".to_string(),
+                    "kitty".to_string(),
+                    "kitty".to_string(),
+                    false,
+                    &config,
+                ),
+                is_dedup_inactive_fullscreen: false,
+                size: (0, 0),
+                urgent_for: None,
+                is_unmanaged: false,
+                is_floating: false,
+                is_pinned: false,
+                is_xwayland: false,
+                position: (0, 0),
+                is_fullscreen_client: FullscreenMode::None,
+            };
+
+            let actual = renamer.generate_workspaces_string(
+                vec![AppWorkspace {
+                    excluded_count: 0,
+                    id: 1,
+                    clients: vec![client.clone(), client],
+                }],
+                &config,
+            );
+
+            assert_eq!(
+                actual,
+                [(1, expected.to_string())].into_iter().collect(),
+                "active: {is_active}, fullscreen: {is_fullscreen}, dedup: {dedup}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_strip_class_prefixes_and_reverse_dns() {
+        let mut config = crate::config::read_config_file(None, false, false).unwrap();
+        config
+            .class
+            .push((Regex::new("^telegram").unwrap(), "tg".to_string()));
+        config
+            .class
+            .push((Regex::new("^Foliate$").unwrap(), "book".to_string()));
+
+        let renamer = Renamer::new(
+            Config {
+                cfg_path: None,
+                config: config.clone(),
+            },
+            Args::default(),
+        );
+
+        let parse_icon = |class: &str, config: &ConfigFile| {
+            renamer
+                .parse_icon(
+                    class.to_string(),
+                    class.to_string(),
+                    "".to_string(),
+                    "".to_string(),
+                    false,
+                    config,
+                )
+                .icon()
+        };
+
+        let default_icon = parse_icon("unknown", &config);
+        assert_eq!(parse_icon("org.telegram.desktop", &config), default_icon);
+
+        config.matching.strip_class_prefixes = vec!["org.".to_string(), "com.".to_string()];
+        assert_eq!(parse_icon("org.telegram.desktop", &config), "tg");
+        assert_eq!(
+            parse_icon("com.github.johnfactotum.Foliate", &config),
+            default_icon
+        );
+
+        config.matching.reverse_dns_short_name = true;
+        assert_eq!(
+            parse_icon("com.github.johnfactotum.Foliate", &config),
+            "book"
+        );
+        assert_eq!(parse_icon("telegram", &config), "tg");
+
+        assert_eq!(
+            icon::normalize_class("org.telegram.desktop", &config.matching),
+            "telegram"
+        );
+        assert_eq!(icon::normalize_class("kitty", &config.matching), "kitty");
+        assert_eq!(
+            icon::normalize_class("firefox.desktop", &config.matching),
+            "firefox.desktop"
+        );
+    }
+
+    #[test]
+    fn test_sort_by_priority() {
+        let ids = [5, 1, 3, 2, 4];
+        assert_eq!(
+            sort_by_priority(ids.iter(), Some(3), &[4, 3]),
+            vec![3, 4, 1, 2, 5]
+        );
+        assert_eq!(sort_by_priority(ids.iter(), None, &[]), vec![1, 2, 3, 4, 5]);
+    }
+
+    #[test]
+    fn test_hash_workspaces_names() {
+        let mut names = HashMap::new();
+        for id in 1..=20 {
+            names.insert(id, format!("{id}: term"));
+        }
+        let mut reversed = HashMap::new();
+        for id in (1..=20).rev() {
+            reversed.insert(id, format!("{id}: term"));
+        }
+        assert_eq!(
+            hash_workspaces_names(&names),
+            hash_workspaces_names(&reversed)
+        );
+
+        reversed.insert(3, "3: term term".to_string());
+        assert_ne!(
+            hash_workspaces_names(&names),
+            hash_workspaces_names(&reversed)
+        );
+        assert_ne!(
+            hash_workspaces_names(&HashMap::new()),
+            hash_workspaces_names(&HashMap::from([(1, String::new())]))
+        );
+    }
+
+    #[test]
+    fn test_diff_workspaces_names() {
+        let active_names = HashMap::from([(1, "1: term".to_string()), (2, "2".to_string())]);
+        let candidate_names = HashMap::from([(1, "1>term".to_string()), (2, "2".to_string())]);
+
+        let diff = diff_workspaces_names(&active_names, &candidate_names);
+        assert_eq!(diff, vec![(1, "1: term".to_string(), "1>term".to_string())]);
+
+        let diff = diff_workspaces_names(&active_names, &active_names);
+        assert!(diff.is_empty());
+
+        let diff = diff_workspaces_names(&active_names, &HashMap::new());
+        assert_eq!(diff.len(), 2);
+    }
+
+    #[test]
+    fn test_toggle_dedup() {
+        let mut config = crate::config::read_config_file(None, false, false).unwrap();
+        config.format.dedup = false;
+        let renamer = Renamer::new(
+            Config {
+                cfg_path: None,
+                config,
+            },
+            Args::default(),
+        );
+
+        assert!(renamer.toggle_dedup().unwrap());
+        assert!(renamer.cfg.lock().unwrap().config.format.dedup);
+        assert!(!renamer.toggle_dedup().unwrap());
+        assert!(!renamer.cfg.lock().unwrap().config.format.dedup);
+    }
+
+    #[test]
+    fn test_set_client_icon() {
+        let renamer = Renamer::new(
+            Config {
+                cfg_path: None,
+                config: crate::config::read_config_file(None, false, false).unwrap(),
+            },
+            Args::default(),
+        );
+
+        renamer.set_client_icon("55d2f0", "term").unwrap();
+        renamer.set_client_icon("0x55d2f0", "my term").unwrap();
+        assert_eq!(
+            *renamer.client_icon_overrides.lock().unwrap(),
+            HashMap::from([("0x55d2f0".to_string(), "my term".to_string())])
+        );
+    }
+
+    #[test]
+    fn test_unlock_workspace() {
+        let renamer = Renamer::new(
+            Config {
+                cfg_path: None,
+                config: crate::config::read_config_file(None, false, false).unwrap(),
+            },
+            Args::default(),
+        );
+
+        renamer.locked_workspaces.lock().unwrap().extend([1, 2]);
+        renamer
+            .workspace_strings_cache
+            .lock()
+            .unwrap()
+            .insert(1, "1: term".to_string());
+        *renamer.last_batch_hash.lock().unwrap() = Some(42);
+
+        renamer.unlock_workspace(1).unwrap();
+        assert_eq!(
+            *renamer.locked_workspaces.lock().unwrap(),
+            HashSet::from([2])
+        );
+        assert!(renamer.workspace_strings_cache.lock().unwrap().is_empty());
+        assert_eq!(*renamer.last_batch_hash.lock().unwrap(), None);
+    }
+
+    #[test]
+    fn test_mark_workspace_used() {
+        let renamer = Renamer::new(
+            Config {
+                cfg_path: None,
+                config: crate::config::read_config_file(None, false, false).unwrap(),
+            },
+            Args::default(),
+        );
+
+        renamer.mark_workspace_used(1).unwrap();
+        renamer.mark_workspace_used(2).unwrap();
+        renamer.mark_workspace_used(2).unwrap();
+        renamer.mark_workspace_used(1).unwrap();
+        assert_eq!(
+            *renamer.workspace_last_used.lock().unwrap(),
+            HashMap::from([(1, 2), (2, 1)])
+        );
+    }
+
+    #[test]
+    fn test_total_clients() {
+        let mut config = crate::config::read_config_file(None, false, false).unwrap();
+        config.format.workspace = "{id}:{clients} ({total_clients})".to_string();
+        config.format.workspace_empty = "{id} ({total_clients})".to_string();
+
+        let renamer = Renamer::new(
+            Config {
+                cfg_path: None,
+                config: config.clone(),
+            },
+            Args::default(),
+        );
+
+        let client = AppClient {
+            initial_class: "kitty".to_string(),
+            class: "kitty".to_string(),
+            title: "kitty".to_string(),
+            initial_title: "kitty".to_string(),
+            is_active: false,
+            is_fullscreen: FullscreenMode::None,
+            matched_rule: Inactive(Class("kitty".to_string(), "term".to_string())),
+            is_dedup_inactive_fullscreen: false,
+            size: (0, 0),
+            urgent_for: None,
+            is_unmanaged: false,
+            is_floating: false,
+            is_pinned: false,
+            is_xwayland: false,
+            position: (0, 0),
+            is_fullscreen_client: FullscreenMode::None,
+        };
+
+        let workspaces = vec![
+            AppWorkspace::new(1, vec![client.clone(), client.clone()]),
+            AppWorkspace::new(2, vec![client]),
+            AppWorkspace::new(3, vec![]),
+        ];
+
+        assert_eq!(count_clients(&workspaces), 3);
+        let global_vars = generate_global_vars(&workspaces);
+
+        let actual = renamer.generate_workspaces_names(workspaces, &global_vars, &config);
+        let expected = HashMap::from([
+            (1, "1:term term (3)".to_string()),
+            (2, "2:term (3)".to_string()),
+            (3, "3 (3)".to_string()),
+        ]);
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn test_blank_clients_render_as_empty() {
+        let mut config = crate::config::read_config_file(None, false, false).unwrap();
+        config.format.workspace = "{id}:{delim}{clients}".to_string();
+        config.format.workspace_empty = "{id}".to_string();
+
+        config.format.workspace_empty_active = Some("{id}_".to_string());
+
+        let vars = HashMap::new();
+        let render = |clients: &str, is_focused: bool| {
+            render_workspace(
+                1,
+                clients,
+                &config.format,
+                &config.workspaces_name,
+                &vars,
+                false,
+                is_focused,
+            )
+        };
+
+        assert_eq!(render("term", false), "1: term");
+        assert_eq!(render("", false), "1");
+        assert_eq!(render(" ", false), "1");
+        assert_eq!(render("term", true), "1: term");
+        assert_eq!(render("", true), "1_");
+    }
+
+    #[test]
+    fn test_collapse_delim() {
+        assert_eq!(collapse_delim("1:  term   web ", " ", false), "1: term web");
+        assert_eq!(collapse_delim(" 1: term ", " ", true), "1: term ");
+        assert_eq!(collapse_delim("|1||term|", "|", false), "1|term");
+        assert_eq!(collapse_delim("1  term", "", false), "1  term");
+
+        let mut config = crate::config::read_config_file(None, false, false).unwrap();
+        config.format.workspace =
+            "{id}{delim}{urgent}{delim}{clients}{delim}{special_name}".to_string();
+        config.format.delim = "·".to_string();
+        config.format.collapse_delim = true;
+        let vars = HashMap::from([
+            ("urgent".to_string(), String::new()),
+            ("special_name".to_string(), String::new()),
+        ]);
+        let actual = render_workspace(
+            1,
+            "term",
+            &config.format,
+            &config.workspaces_name,
+            &vars,
+            false,
+            false,
+        );
+        assert_eq!(actual, "1·term");
+    }
+
+    #[test]
+    fn test_id_alpha_and_roman() {
+        assert_eq!(id_alpha(1), "a");
+        assert_eq!(id_alpha(26), "z");
+        assert_eq!(id_alpha(27), "aa");
+        assert_eq!(id_alpha(703), "aaa");
+        assert_eq!(id_alpha(-98), "-98");
+        assert_eq!(id_roman(4), "IV");
+        assert_eq!(id_roman(14), "XIV");
+        assert_eq!(id_roman(1994), "MCMXCIV");
+        assert_eq!(id_roman(0), "0");
+        assert_eq!(id_roman(-98), "-98");
+
+        let mut config = crate::config::read_config_file(None, false, false).unwrap();
+        config.format.workspace = "{id_alpha}/{id_roman}:{clients}".to_string();
+        let actual = render_workspace(
+            12,
+            "term",
+            &config.format,
+            &config.workspaces_name,
+            &HashMap::new(),
+            false,
+            false,
+        );
+        assert_eq!(actual, "l/XII:term");
+    }
+
+    #[test]
+    fn test_client_index_active() {
+        let mut config = crate::config::read_config_file(None, false, false).unwrap();
+        config.format.dedup = false;
+        config.format.workspace = "{id}:({client_index_active}/{client_count})".to_string();
+        config.format.workspace_empty = "{id}:({client_index_active}/{client_count})".to_string();
+
+        let renamer = Renamer::new(
+            Config {
+                cfg_path: None,
+                config: config.clone(),
+            },
+            Args::default(),
+        );
+
+        let client = |is_active| AppClient {
+            initial_class: "kitty".to_string(),
+            class: "kitty".to_string(),
+            title: "kitty".to_string(),
+            initial_title: "kitty".to_string(),
+            is_active,
+            is_fullscreen: FullscreenMode::None,
+            matched_rule: Inactive(Class("kitty".to_string(), "term".to_string())),
+            is_dedup_inactive_fullscreen: false,
+            size: (0, 0),
+            urgent_for: None,
+            is_unmanaged: false,
+            is_floating: false,
+            is_pinned: false,
+            is_xwayland: false,
+            position: (0, 0),
+            is_fullscreen_client: FullscreenMode::None,
+        };
+
+        let workspaces = vec![
+            AppWorkspace::new(1, vec![client(false), client(true), client(false)]),
+            AppWorkspace::new(2, vec![client(false), client(false)]),
+            AppWorkspace::new(3, vec![]),
+        ];
+
+        let actual = renamer.generate_workspaces_names(workspaces, &HashMap::new(), &config);
+        let expected = HashMap::from([
+            (1, "1:(2/3)".to_string()),
+            (2, "2:(0/2)".to_string()),
+            (3, "3:(0/0)".to_string()),
+        ]);
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn test_fullscreen_count() {
+        let mut config = crate::config::read_config_file(None, false, false).unwrap();
+        config.format.workspace = "{id}:{clients} [{fullscreen_count}]".to_string();
+        config.format.workspace_empty = "{id} [{fullscreen_count}]".to_string();
+        config.format.client_fullscreen = "{icon}".to_string();
+
+        let renamer = Renamer::new(
+            Config {
+                cfg_path: None,
+                config: config.clone(),
+            },
+            Args::default(),
+        );
+
+        let client = |is_fullscreen| AppClient {
+            initial_class: "kitty".to_string(),
+            class: "kitty".to_string(),
+            title: "kitty".to_string(),
+            initial_title: "kitty".to_string(),
+            is_active: false,
+            is_fullscreen,
+            matched_rule: Inactive(Class("kitty".to_string(), "term".to_string())),
+            is_dedup_inactive_fullscreen: false,
+            size: (0, 0),
+            urgent_for: None,
+            is_unmanaged: false,
+            is_floating: false,
+            is_pinned: false,
+            is_xwayland: false,
+            position: (0, 0),
+            is_fullscreen_client: FullscreenMode::None,
+        };
+
+        let workspaces = vec![
+            AppWorkspace::new(
+                1,
+                vec![
+                    client(FullscreenMode::Fullscreen),
+                    client(FullscreenMode::None),
+                ],
+            ),
+            AppWorkspace::new(2, vec![client(FullscreenMode::Maximized)]),
+            AppWorkspace::new(3, vec![]),
+        ];
+
+        let actual = renamer.generate_workspaces_names(workspaces, &HashMap::new(), &config);
+        let expected = HashMap::from([
+            (1, "1:term term [1]".to_string()),
+            (2, "2:term [1]".to_string()),
+            (3, "3 [0]".to_string()),
+        ]);
+        assert_eq!(actual, expected);
+    }
+
+    fn hyprland_client(address: &str, workspace_id: i32, class: &str) -> Client {
+        serde_json::from_value(serde_json::json!({
+            "address": address, "at": [0, 0], "size": [0, 0],
+            "workspace": {"id": workspace_id, "name": workspace_id.to_string()},
+            "floating": false, "fullscreen": 0, "fullscreenClient": 0, "monitor": 0,
+            "initialClass": class, "class": class, "initialTitle": class, "title": class,
+            "pid": 1, "xwayland": false, "pinned": false, "grouped": [], "mapped": true,
+            "swallowing": null, "focusHistoryID": 0
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_rename_with_backend() {
+        let mut config = crate::config::read_config_file(None, false, false).unwrap();
+        config
+            .class
+            .push((Regex::new("kitty").unwrap(), "term".to_string()));
+        config.format.client_active = "*{icon}*".to_string();
+
+        let backend = Arc::new(backend::MockBackend::default());
+        *backend.clients.lock().unwrap() = vec![
+            hyprland_client("0x1", 1, "kitty"),
+            hyprland_client("0x2", 2, "kitty"),
+        ];
+        *backend.active_client.lock().unwrap() = "0x2".to_string();
+        let renamer = Renamer::with_backend(
+            Config {
+                cfg_path: None,
+                config,
+            },
+            Args::default(),
+            backend.clone(),
+        );
+
+        renamer.rename_workspace("test").unwrap();
+        let mut renames = std::mem::take(&mut *backend.renames.lock().unwrap());
+        renames.sort();
+        assert_eq!(
+            renames,
+            [(1, "1: term".to_string()), (2, "2: *term*".to_string())]
+        );
+
+        // Only the altered workspaces are renamed again
+        backend.clients.lock().unwrap().pop();
+        renamer.rename_workspace("test").unwrap();
+        assert_eq!(*backend.renames.lock().unwrap(), [(2, "2".to_string())]);
+    }
+
+    #[test]
+    fn test_include_unmanaged() {
+        let mut config = crate::config::read_config_file(None, false, false).unwrap();
+        config
+            .class
+            .push((Regex::new("kitty").unwrap(), "term".to_string()));
+        config.format.client_unmanaged = "({client})".to_string();
+
+        let mut unmanaged = hyprland_client("0x2", 1, "kitty");
+        unmanaged.pid = 0;
+        let backend = Arc::new(backend::MockBackend::default());
+        *backend.clients.lock().unwrap() = vec![hyprland_client("0x1", 1, "kitty"), unmanaged];
+        let rename = |config: &ConfigFile| {
+            let renamer = Renamer::with_backend(
+                Config {
+                    cfg_path: None,
+                    config: config.clone(),
+                },
+                Args::default(),
+                backend.clone(),
+            );
+            renamer.rename_workspace("test").unwrap();
+            std::mem::take(&mut *backend.renames.lock().unwrap())
+        };
+
+        assert_eq!(rename(&config), [(1, "1: term".to_string())]);
+        config.behavior.include_unmanaged = true;
+        assert_eq!(rename(&config), [(1, "1: term (term)".to_string())]);
+    }
+
+    #[test]
+    fn test_client_floating_pinned() {
+        let mut config = crate::config::read_config_file(None, false, false).unwrap();
+        config
+            .class
+            .push((Regex::new("kitty").unwrap(), "term".to_string()));
+        config.format.client_floating = "~{client}".to_string();
+
+        let mut floating = hyprland_client("0x2", 1, "kitty");
+        floating.floating = true;
+        let mut pinned = hyprland_client("0x3", 1, "kitty");
+        pinned.floating = true;
+        pinned.pinned = true;
+        let backend = Arc::new(backend::MockBackend::default());
+        *backend.clients.lock().unwrap() =
+            vec![hyprland_client("0x1", 1, "kitty"), floating, pinned];
+        let rename = |config: &ConfigFile| {
+            let renamer = Renamer::with_backend(
+                Config {
+                    cfg_path: None,
+                    config: config.clone(),
+                },
+                Args::default(),
+                backend.clone(),
+            );
+            renamer.rename_workspace("test").unwrap();
+            std::mem::take(&mut *backend.renames.lock().unwrap())
+        };
+
+        // Pinned clients default to the floating format
+        assert_eq!(rename(&config), [(1, "1: term ~term ~term".to_string())]);
+
+        // Without nesting the floating format, so no deeper than it
+        config.format.placeholder_depth = 2;
+        assert_eq!(rename(&config), [(1, "1: term ~term ~term".to_string())]);
+
+        config.format.placeholder_depth = 4;
+        config.format.client_pinned = Some("^{client}".to_string());
+        assert_eq!(rename(&config), [(1, "1: term ~term ^term".to_string())]);
+    }
+
+    #[test]
+    fn test_mirror_focused_name() {
+        let mut config = crate::config::read_config_file(None, false, false).unwrap();
+        config
+            .class
+            .push((Regex::new("kitty").unwrap(), "term".to_string()));
+        config.format.client_active = "{icon}".to_string();
+        let mirror_file = std::env::temp_dir().join("hyprland-autoname-workspaces-test-mirror");
+        config.behavior.mirror_env = Some("AUTONAME_WS".to_string());
+        config.behavior.mirror_file = Some(mirror_file.clone());
+
+        let backend = Arc::new(backend::MockBackend::default());
+        *backend.clients.lock().unwrap() = vec![hyprland_client("0x1", 1, "kitty")];
+        *backend.visible.lock().unwrap() = vec![1];
+        let renamer = Renamer::with_backend(
+            Config {
+                cfg_path: None,
+                config,
+            },
+            Args::default(),
+            backend.clone(),
+        );
+
+        renamer.rename_workspace("test").unwrap();
+        renamer.rename_workspace("test").unwrap();
+        assert_eq!(
+            *backend.envs.lock().unwrap(),
+            [("AUTONAME_WS".to_string(), "1: term".to_string())]
+        );
+        assert_eq!(std::fs::read_to_string(&mirror_file).unwrap(), "1: term\n");
+
+        // An empty focused workspace is mirrored with its id
+        *backend.visible.lock().unwrap() = vec![3];
+        renamer.rename_workspace("test").unwrap();
+        assert_eq!(backend.envs.lock().unwrap()[1].1, "3");
+    }
+
+    #[test]
+    fn test_on_conflict() {
+        let mut config = crate::config::read_config_file(None, false, false).unwrap();
+        config
+            .class
+            .push((Regex::new("kitty").unwrap(), "term".to_string()));
+        config.format.client = "{icon}".to_string();
+        config.behavior.on_conflict = ConflictPolicy::Skip;
+
+        let mut named_elsewhere = hyprland_client("0x1", 1, "kitty");
+        named_elsewhere.workspace.name = "1: web".to_string();
+        let backend = Arc::new(backend::MockBackend::default());
+        *backend.clients.lock().unwrap() =
+            vec![named_elsewhere, hyprland_client("0x2", 2, "kitty")];
+        let renamer = Renamer::with_backend(
+            Config {
+                cfg_path: None,
+                config: config.clone(),
+            },
+            Args::default(),
+            backend.clone(),
+        );
+
+        renamer.rename_workspace("test").unwrap();
+        assert_eq!(
+            std::mem::take(&mut *backend.renames.lock().unwrap()),
+            [(2, "2: term\u{2063}".to_string())]
+        );
+
+        config.behavior.on_conflict = ConflictPolicy::Warn;
+        renamer.cfg.lock().unwrap().config = config;
+        renamer.rename_workspace("test").unwrap();
+        assert_eq!(
+            *backend.renames.lock().unwrap(),
+            [(1, "1: term\u{2063}".to_string())]
+        );
+        assert_eq!(
+            *renamer.warned_foreign_names.lock().unwrap(),
+            HashMap::from([(1, "1: web".to_string())])
+        );
+    }
+
+    #[test]
+    fn test_idle() {
+        let mut config = crate::config::read_config_file(None, false, false).unwrap();
+        config
+            .class
+            .push((Regex::new("kitty").unwrap(), "term".to_string()));
+        config.format.client = "{icon}".to_string();
+
+        let backend = Arc::new(backend::MockBackend::default());
+        *backend.clients.lock().unwrap() = vec![hyprland_client("0x1", 1, "kitty")];
+        let renamer = Renamer::with_backend(
+            Config {
+                cfg_path: None,
+                config,
+            },
+            Args::default(),
+            backend.clone(),
+        );
+
+        assert_eq!(
+            renamer.handle_command(ControlCommand::Idle).unwrap(),
+            "idle"
+        );
+        renamer.handle_event("window_opened").unwrap();
+        assert!(backend.renames.lock().unwrap().is_empty());
+
+        // The watchers are parked until resumed
+        let (parked, resumed) = std::sync::mpsc::channel();
+        let this = renamer.clone();
+        thread::spawn(move || {
+            this.wait_while_idle().unwrap();
+            parked.send(()).unwrap();
+        });
+        assert!(resumed.recv_timeout(Duration::from_millis(50)).is_err());
+
+        // Resuming renames everything once
+        assert_eq!(
+            renamer.handle_command(ControlCommand::Resume).unwrap(),
+            "resumed"
+        );
+        resumed.recv_timeout(Duration::from_secs(1)).unwrap();
+        assert_eq!(
+            *backend.renames.lock().unwrap(),
+            [(1, "1: term".to_string())]
+        );
+        renamer.handle_command(ControlCommand::Resume).unwrap();
+        assert_eq!(backend.renames.lock().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_monitors() {
+        let mut config = crate::config::read_config_file(None, false, false).unwrap();
+        config
+            .class
+            .push((Regex::new("kitty").unwrap(), "term".to_string()));
+        config.format.client = "{icon}".to_string();
+        config.behavior.monitors = vec!["DP-1".to_string()];
+
+        let backend = Arc::new(backend::MockBackend::default());
+        *backend.clients.lock().unwrap() = vec![
+            hyprland_client("0x1", 1, "kitty"),
+            hyprland_client("0x2", 2, "kitty"),
+        ];
+        *backend.monitors.lock().unwrap() =
+            vec![(1, "DP-1".to_string()), (2, "HDMI-A-1".to_string())];
+        let renamer = Renamer::with_backend(
+            Config {
+                cfg_path: None,
+                config,
+            },
+            Args::default(),
+            backend.clone(),
+        );
+
+        renamer.rename_workspace("test").unwrap();
+        assert_eq!(
+            *backend.renames.lock().unwrap(),
+            [(1, "1: term".to_string())]
+        );
+
+        // Names of the other monitors are kept at exit too
+        backend.renames.lock().unwrap().clear();
+        let config = renamer.cfg.lock().unwrap().config.clone();
+        renamer.reset_workspaces(config).unwrap();
+        assert_eq!(*backend.renames.lock().unwrap(), [(1, "1".to_string())]);
+    }
+
+    #[test]
+    fn test_reset_vars() {
+        let mut config = crate::config::read_config_file(None, false, false).unwrap();
+        config.format.workspace = "{id}:{total_clients}{xwayland}{urgent}".to_string();
+        config.format.workspace_empty = config.format.workspace.clone();
+
+        let backend = Arc::new(backend::MockBackend::default());
+        *backend.clients.lock().unwrap() = vec![hyprland_client("0x1", 1, "kitty")];
+        let renamer = Renamer::with_backend(
+            Config {
+                cfg_path: None,
+                config,
+            },
+            Args::default(),
+            backend.clone(),
+        );
+
+        renamer.rename_workspace("test").unwrap();
+        backend.renames.lock().unwrap().clear();
+        let config = renamer.cfg.lock().unwrap().config.clone();
+        renamer.reset_workspaces(config).unwrap();
+        assert_eq!(*backend.renames.lock().unwrap(), [(1, "1:0".to_string())]);
+    }
+
+    #[test]
+    fn test_max_clients_per_monitor() {
+        let mut config = crate::config::read_config_file(None, false, false).unwrap();
+        config
+            .class
+            .push((Regex::new("kitty").unwrap(), "term".to_string()));
+        config.format.client = "{icon}".to_string();
+        config.format.max_clients = Some(2);
+        config.format.max_clients_per_monitor = HashMap::from([("eDP-1".to_string(), 1)]);
+
+        let backend = Arc::new(backend::MockBackend::default());
+        *backend.clients.lock().unwrap() = (1..=6)
+            .map(|i| hyprland_client(&format!("0x{i}"), 1 + i / 4, "kitty"))
+            .collect();
+        *backend.monitors.lock().unwrap() = vec![(1, "eDP-1".to_string()), (2, "DP-1".to_string())];
+        let renamer = Renamer::with_backend(
+            Config {
+                cfg_path: None,
+                config,
+            },
+            Args::default(),
+            backend.clone(),
+        );
+
+        renamer.rename_workspace("test").unwrap();
+        let mut renames = backend.renames.lock().unwrap().clone();
+        renames.sort();
+        assert_eq!(
+            renames,
+            [(1, "1: term".to_string()), (2, "2: term term".to_string())]
+        );
+    }
+
+    #[test]
+    fn test_behavior_toggles() {
+        let mut config = crate::config::read_config_file(None, false, false).unwrap();
+        config
+            .class
+            .push((Regex::new("kitty").unwrap(), "term".to_string()));
+        config.behavior.startup_throttle = false;
+        config.behavior.only_visible = true;
+        config.behavior.debounce = 50;
+
+        let backend = Arc::new(backend::MockBackend::default());
+        *backend.clients.lock().unwrap() = vec![
+            hyprland_client("0x1", 1, "kitty"),
+            hyprland_client("0x2", 2, "kitty"),
+        ];
+        *backend.visible.lock().unwrap() = vec![2];
+        let renamer = Renamer::with_backend(
+            Config {
+                cfg_path: None,
+                config,
+            },
+            Args::default(),
+            backend.clone(),
+        );
+
+        // Events are renamed once they settle
+        renamer.handle_event("test").unwrap();
+        assert!(backend.renames.lock().unwrap().is_empty());
+        assert!(!renamer
+            .flush_debounced_event(Duration::from_secs(60))
+            .unwrap());
+        assert!(renamer.flush_debounced_event(Duration::ZERO).unwrap());
+
+        // Hidden workspaces are renamed once visible
+        assert_eq!(
+            std::mem::take(&mut *backend.renames.lock().unwrap()),
+            [(2, "2: term".to_string())]
+        );
+        *backend.visible.lock().unwrap() = vec![1];
+        renamer.rename_workspace("test").unwrap();
+        assert_eq!(
+            *backend.renames.lock().unwrap(),
+            [(1, "1: term".to_string())]
+        );
+    }
+
+    #[test]
+    fn test_is_monitor_active() {
+        let mut config = crate::config::read_config_file(None, false, false).unwrap();
+        config
+            .class
+            .push((Regex::new("kitty").unwrap(), "term".to_string()));
+        config.format.workspace = "{id}{is_monitor_active}: {clients}".to_string();
+        config.format.monitor_active_marker = "@".to_string();
+
+        let backend = Arc::new(backend::MockBackend::default());
+        *backend.clients.lock().unwrap() = vec![
+            hyprland_client("0x1", 1, "kitty"),
+            hyprland_client("0x2", 2, "kitty"),
+            hyprland_client("0x3", 3, "kitty"),
+        ];
+        // Workspace 1 is focused, 3 is shown on the other monitor
+        *backend.visible.lock().unwrap() = vec![1, 3];
+        let renamer = Renamer::with_backend(
+            Config {
+                cfg_path: None,
+                config,
+            },
+            Args::default(),
+            backend.clone(),
+        );
+
+        renamer.rename_workspace("test").unwrap();
+        let mut renames = std::mem::take(&mut *backend.renames.lock().unwrap());
+        renames.sort();
+        assert_eq!(
+            renames,
+            [
+                (1, "1@: term".to_string()),
+                (2, "2: term".to_string()),
+                (3, "3@: term".to_string())
+            ]
+        );
+    }
+
+    #[test]
+    fn test_workspace_windows() {
+        let mut config = crate::config::read_config_file(None, false, false).unwrap();
+        config
+            .class
+            .push((Regex::new("kitty").unwrap(), "term".to_string()));
+        config.format.workspace =
+            "{id}: {clients} ({client_count}/{workspace_windows})".to_string();
+
+        let backend = Arc::new(backend::MockBackend::default());
+        *backend.clients.lock().unwrap() = vec![
+            hyprland_client("0x1", 1, "kitty"),
+            hyprland_client("0x2", 2, "kitty"),
+        ];
+        // Workspace 1 has windows we don't see, e.g. without a pid
+        *backend.windows.lock().unwrap() = Some(HashMap::from([(1, 3)]));
+        let renamer = Renamer::with_backend(
+            Config {
+                cfg_path: None,
+                config,
+            },
+            Args::default(),
+            backend.clone(),
+        );
+
+        renamer.rename_workspace("test").unwrap();
+        let mut renames = std::mem::take(&mut *backend.renames.lock().unwrap());
+        renames.sort();
+        assert_eq!(
+            renames,
+            [
+                (1, "1: term (1/3)".to_string()),
+                (2, "2: term (1/1)".to_string())
+            ]
+        );
+    }
+
+    #[test]
+    fn test_move_window_to_special_workspace() {
+        let mut config = crate::config::read_config_file(None, false, false).unwrap();
+        config
+            .class
+            .push((Regex::new("kitty").unwrap(), "term".to_string()));
+        config.format.workspace_empty = "{id}".to_string();
+
+        let backend = Arc::new(backend::MockBackend::default());
+        *backend.clients.lock().unwrap() = vec![hyprland_client("0x1", 1, "kitty")];
+        let renamer = Renamer::with_backend(
+            Config {
+                cfg_path: None,
+                config,
+            },
+            Args::default(),
+            backend.clone(),
+        );
+        renamer.rename_workspace("test").unwrap();
+        backend.renames.lock().unwrap().clear();
+
+        // Hyprland still lists the window on its origin workspace
+        let scratch = WorkspaceBasic {
+            id: -98,
+            name: "special:scratch".to_string(),
+        };
+        renamer.move_window("0x1", scratch).unwrap();
+        renamer.rename_workspace("window_moved").unwrap();
+        let mut renames = std::mem::take(&mut *backend.renames.lock().unwrap());
+        renames.sort();
+        assert_eq!(
+            renames,
+            [(-98, "-98: term".to_string()), (1, "1".to_string())]
+        );
+
+        // And the other way
+        *backend.clients.lock().unwrap() = vec![hyprland_client("0x1", -98, "kitty")];
+        let origin = WorkspaceBasic {
+            id: 1,
+            name: "1".to_string(),
+        };
+        renamer.move_window("0x1", origin).unwrap();
+        renamer.rename_workspace("window_moved").unwrap();
+        let mut renames = std::mem::take(&mut *backend.renames.lock().unwrap());
+        renames.sort();
+        assert_eq!(
+            renames,
+            [(-98, "-98".to_string()), (1, "1: term".to_string())]
+        );
+    }
+
+    #[test]
+    fn test_resync() {
+        let mut config = crate::config::read_config_file(None, false, false).unwrap();
+        config.format.workspace_persistent_empty = Some("{id}{persistent}".to_string());
+
+        let backend = Arc::new(backend::MockBackend::default());
+        *backend.clients.lock().unwrap() = vec![hyprland_client("0x1", 1, "kitty")];
+        *backend.persistent.lock().unwrap() = HashSet::from([1, 2]);
+        let renamer = Renamer::with_backend(
+            Config {
+                cfg_path: None,
+                config,
+            },
+            Args::default(),
+            backend.clone(),
+        );
+        renamer.rename_workspace("test").unwrap();
+        backend.renames.lock().unwrap().clear();
+
+        // The workspace rules changed on the Hyprland side
+        *backend.persistent.lock().unwrap() = HashSet::from([1]);
+        renamer.resync("config_reloaded").unwrap();
+        let renames = backend.renames.lock().unwrap().clone();
+        assert!(renames.iter().any(|(id, _)| *id == 1));
+        assert_eq!(
+            *renamer.persistent_workspaces.lock().unwrap(),
+            HashSet::from([1])
+        );
+    }
+
+    #[test]
+    fn test_spawn_watchers() {
+        let mut config = crate::config::read_config_file(None, false, false).unwrap();
+        config.behavior.startup_throttle = false;
+        config.behavior.urgent_timeout = 0;
+        let renamer = Renamer::with_backend(
+            Config {
+                cfg_path: None,
+                config,
+            },
+            Args::default(),
+            Arc::new(backend::MockBackend::default()),
+        );
+        renamer.spawn_watchers();
+        assert!(renamer.spawned_watchers.lock().unwrap().is_empty());
+
+        // Enabled by a reloaded config
+        renamer.cfg.lock().unwrap().config.behavior.debounce = 50;
+        renamer.spawn_watchers();
+        assert_eq!(
+            *renamer.spawned_watchers.lock().unwrap(),
+            HashSet::from([Watcher::Debounce])
+        );
+    }
+
+    #[test]
+    fn test_low_power_debounce() {
+        let mut config = crate::config::read_config_file(None, false, false).unwrap();
+        assert_eq!(debounce(&config.behavior, false), Duration::ZERO);
+        assert_eq!(debounce(&config.behavior, true), Duration::from_secs(2));
+
+        config.behavior.debounce = 5000;
+        assert_eq!(debounce(&config.behavior, true), Duration::from_secs(5));
+
+        config.behavior.low_power_on_battery = true;
+        config.behavior.startup_throttle = false;
+        let backend = Arc::new(backend::MockBackend::default());
+        *backend.clients.lock().unwrap() = vec![hyprland_client("0x1", 1, "kitty")];
+        let renamer = Renamer::with_backend(
+            Config {
+                cfg_path: None,
+                config,
+            },
+            Args::default(),
+            backend.clone(),
+        );
+        renamer
+            .update_power_state(|power| power.on_battery = true)
+            .unwrap();
+        backend.renames.lock().unwrap().clear();
+
+        // Title changes are ignored, and the first pending event keeps its
+        // time so a storm of events can't postpone the rename forever
+        renamer.handle_event("window_title_changed").unwrap();
+        assert!(renamer.debounced_event.lock().unwrap().is_none());
+        renamer.handle_event("window_opened").unwrap();
+        let (_, at) = renamer.debounced_event.lock().unwrap().clone().unwrap();
+        renamer.handle_event("window_closed").unwrap();
+        assert_eq!(
+            *renamer.debounced_event.lock().unwrap(),
+            Some(("window_closed".to_string(), at))
+        );
+        assert!(backend.renames.lock().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_shutdown() {
+        let config = crate::config::read_config_file(None, false, false).unwrap();
+        let backend = Arc::new(backend::MockBackend::default());
+        *backend.clients.lock().unwrap() = vec![hyprland_client("0x1", 1, "kitty")];
+        let renamer = Renamer::with_backend(
+            Config {
+                cfg_path: None,
+                config: config.clone(),
+            },
+            Args::default(),
+            backend.clone(),
+        );
+        renamer.rename_workspace("test").unwrap();
+        backend.renames.lock().unwrap().clear();
+
+        // Reset with the config reloaded since the start
+        let mut reloaded = config.clone();
+        reloaded.format.workspace_empty = "{id}_".to_string();
+        renamer
+            .set_reloaded_config(Path::new("config.toml"), reloaded)
+            .unwrap();
+
+        // The reset waits for the in-flight rename
+        let in_flight = renamer.dispatch_lock.lock().unwrap();
+        let shutdown = {
+            let renamer = renamer.clone();
+            thread::spawn(move || renamer.shutdown().is_ok())
+        };
+        while !renamer.is_shutting_down() {
+            thread::sleep(Duration::from_millis(1));
+        }
+        assert!(backend.renames.lock().unwrap().is_empty());
+        drop(in_flight);
+        assert!(shutdown.join().unwrap());
+        assert_eq!(*backend.renames.lock().unwrap(), [(1, "1_".to_string())]);
+
+        // Nothing is renamed after the reset
+        *backend.clients.lock().unwrap() = vec![hyprland_client("0x2", 2, "kitty")];
+        renamer.rename_workspace("test").unwrap();
+        assert_eq!(*backend.renames.lock().unwrap(), [(1, "1_".to_string())]);
+    }
+
+    #[test]
+    fn test_active_fallback() {
+        use crate::config::ActiveFallback;
+
+        let mut config = crate::config::read_config_file(None, false, false).unwrap();
+        config
+            .class
+            .push((Regex::new("alacritty").unwrap(), "a".to_string()));
+        config
+            .class_active
+            .push((Regex::new("DEFAULT").unwrap(), "DDD".to_string()));
+        config.format.client_active = "*{icon}*".to_string();
+
+        let render = |active_fallback| {
+            let mut config = config.clone();
+            config.format.active_fallback = active_fallback;
+            let backend = Arc::new(backend::MockBackend::default());
+            *backend.clients.lock().unwrap() = vec![hyprland_client("0x1", 1, "alacritty")];
+            *backend.active_client.lock().unwrap() = "0x1".to_string();
+            let renamer = Renamer::with_backend(
+                Config {
+                    cfg_path: None,
+                    config,
+                },
+                Args::default(),
+                backend.clone(),
+            );
+            renamer.rename_workspace("test").unwrap();
+            let renames = backend.renames.lock().unwrap().clone();
+            renames[0].1.clone()
+        };
+
+        assert_eq!(render(ActiveFallback::Wrap), "1: *a*");
+        assert_eq!(render(ActiveFallback::UseInactive), "1: a");
+        assert_eq!(render(ActiveFallback::Default), "1: DDD");
+    }
+
+    #[test]
+    fn test_reload_packs() {
+        let dir = std::env::temp_dir().join("hyprland-autoname-workspaces-reload-packs-test");
+        _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let pack = dir.join("pack.toml");
+        let cfg_path = dir.join("config.toml");
+        std::fs::write(&pack, "[class]\nfoot = \"pack foot\"\n").unwrap();
+        std::fs::write(
+            &cfg_path,
+            format!("packs = [\"file://{}\"]\n", pack.display()),
+        )
+        .unwrap();
+
+        // Started like the daemon
+        let args = Args {
+            config: Some(cfg_path.display().to_string()),
+            ..Args::default()
+        };
+        let (config, layers) = crate::config::read_daemon_config(&args).unwrap();
+        let backend = Arc::new(backend::MockBackend::default());
+        *backend.clients.lock().unwrap() = vec![hyprland_client("0x1", 1, "foot")];
+        let renamer = Renamer::with_backend(config, args, backend.clone());
+        renamer.set_pack_layers(layers).unwrap();
+        renamer.rename_workspace("test").unwrap();
+
+        // Only read again with a whole reload of the config
+        std::fs::write(
+            &cfg_path,
+            format!(
+                "packs = [\"file://{}\"]\n\n[class]\nfoot = \"config foot\"\n",
+                pack.display()
+            ),
+        )
+        .unwrap();
+        std::fs::write(&pack, "[class]\nfoot = \"new foot\"\n").unwrap();
+        renamer.reload_packs(&cfg_path, &[pack]).unwrap();
+        renamer.rename_workspace("test").unwrap();
+        assert_eq!(
+            backend.renames.lock().unwrap().last().unwrap(),
+            &(1, "1: new foot".to_string())
+        );
+    }
+
+    #[test]
+    fn test_title_history_rules() {
+        let mut config = crate::config::read_config_file(None, false, false).unwrap();
+        config
+            .class
+            .push((Regex::new("zoom").unwrap(), "video".to_string()));
+        config.title_history_in_class.push((
+            Regex::new("zoom").unwrap(),
+            vec![(
+                Regex::new("Meeting started").unwrap(),
+                "meeting".to_string(),
+            )],
+        ));
+
+        let backend = Arc::new(backend::MockBackend::default());
+        *backend.clients.lock().unwrap() = vec![hyprland_client("0x1", 1, "zoom")];
+        let renamer = Renamer::with_backend(
+            Config {
+                cfg_path: None,
+                config,
+            },
+            Args::default(),
+            backend.clone(),
+        );
+        let rename = |title: &str| {
+            backend.clients.lock().unwrap()[0].title = title.to_string();
+            renamer.rename_workspace("test").unwrap();
+            backend.renames.lock().unwrap().pop().map(|(_, name)| name)
+        };
+
+        assert_eq!(rename("Zoom"), Some("1: video".to_string()));
+        assert_eq!(rename("Meeting started"), Some("1: meeting".to_string()));
+        // The flashed title is remembered
+        assert_eq!(rename("Zoom Meeting"), None);
+
+        // Until the window is closed
+        backend.clients.lock().unwrap().clear();
+        renamer.rename_workspace("test").unwrap();
+        *backend.clients.lock().unwrap() = vec![hyprland_client("0x1", 1, "zoom")];
+        assert_eq!(rename("Zoom Meeting"), Some("1: video".to_string()));
+    }
+
+    #[test]
+    fn test_persistent_workspaces() {
+        let mut config = crate::config::read_config_file(None, false, false).unwrap();
+        config
+            .class
+            .push((Regex::new("kitty").unwrap(), "term".to_string()));
+        config.format.workspace = "{id}{persistent}:{delim}{clients}".to_string();
+        config.format.workspace_persistent_empty = Some("({id})".to_string());
+
+        let backend = Arc::new(backend::MockBackend::default());
+        *backend.clients.lock().unwrap() = vec![hyprland_client("0x1", 1, "kitty")];
+        *backend.persistent.lock().unwrap() = HashSet::from([1, 3]);
+        let renamer = Renamer::with_backend(
+            Config {
+                cfg_path: None,
+                config,
+            },
+            Args::default(),
+            backend.clone(),
+        );
+
+        renamer.rename_workspace("test").unwrap();
+        let mut renames = std::mem::take(&mut *backend.renames.lock().unwrap());
+        renames.sort();
+        assert_eq!(
+            renames,
+            [(1, "1P: term".to_string()), (3, "(3)".to_string())]
+        );
+
+        // Transient leftovers use the regular empty format
+        backend.persistent.lock().unwrap().remove(&1);
+        backend.clients.lock().unwrap().clear();
+        renamer.rename_workspace("test").unwrap();
+        assert_eq!(*backend.renames.lock().unwrap(), [(1, "1".to_string())]);
+    }
+
+    #[test]
+    fn test_rewrite_titles() {
+        let mut config = crate::config::read_config_file(None, false, false).unwrap();
+        config
+            .class
+            .push((Regex::new("kitty").unwrap(), "term".to_string()));
+        config.title_in_class.push((
+            Regex::new("kitty").unwrap(),
+            vec![(Regex::new("vim (.+)").unwrap(), "vim {match1}".to_string())],
+        ));
+        config.behavior.rewrite_titles = true;
+
+        let backend = Arc::new(backend::MockBackend::default());
+        *backend.clients.lock().unwrap() = vec![hyprland_client("0x1", 1, "kitty")];
+        let renamer = Renamer::with_backend(
+            Config {
+                cfg_path: None,
+                config,
+            },
+            Args::default(),
+            backend.clone(),
+        );
+
+        renamer.rename_workspace("test").unwrap();
+        renamer.rename_workspace("test").unwrap();
+        backend.clients.lock().unwrap()[0].title = "vim main.rs".to_string();
+        renamer.rename_workspace("test").unwrap();
+        assert_eq!(
+            *backend.tags.lock().unwrap(),
+            [
+                ("0x1".to_string(), "autoname-term".to_string()),
+                ("0x1".to_string(), "autoname-vim-main-rs".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_publish_renames() {
+        use std::io::{BufRead, BufReader};
+        use std::os::unix::net::UnixStream;
+
+        let mut config = crate::config::read_config_file(None, false, false).unwrap();
+        config
+            .class
+            .push((Regex::new("kitty").unwrap(), "term".to_string()));
+        let backend = Arc::new(backend::MockBackend::default());
+        *backend.clients.lock().unwrap() = vec![hyprland_client("0x1", 1, "kitty")];
+        let renamer = Renamer::with_backend(
+            Config {
+                cfg_path: None,
+                config,
+            },
+            Args::default(),
+            backend.clone(),
+        );
+
+        let (stream, peer) = UnixStream::pair().unwrap();
+        renamer.subscribers().add(stream);
+        renamer.rename_workspace("test").unwrap();
+        backend.clients.lock().unwrap().clear();
+        renamer.rename_workspace("test").unwrap();
+
+        // With the transition format
+        renamer
+            .cfg
+            .lock()
+            .unwrap()
+            .config
+            .format
+            .workspace_transition = Some("{workspace_prev_name} -> {workspace_name}".to_string());
+        *backend.clients.lock().unwrap() = vec![hyprland_client("0x1", 1, "kitty")];
+        renamer.rename_workspace("test").unwrap();
+
+        let mut lines = BufReader::new(peer).lines();
+        assert_eq!(
+            lines.next().unwrap().unwrap(),
+            r#"{"new":"1: term","old":null,"workspace":1}"#
+        );
+        assert_eq!(
+            lines.next().unwrap().unwrap(),
+            r#"{"new":"1","old":"1: term","workspace":1}"#
+        );
+        assert_eq!(
+            lines.next().unwrap().unwrap(),
+            r#"{"new":"1: term","old":"1","transition":"1 -> 1: term","workspace":1}"#
+        );
+    }
+
+    #[test]
+    fn test_special_workspace() {
+        let mut config = crate::config::read_config_file(None, false, false).unwrap();
+        config
+            .class
+            .push((Regex::new("kitty").unwrap(), "term".to_string()));
+
+        let backend = Arc::new(backend::MockBackend::default());
+        let mut scratchpad = hyprland_client("0x1", -98, "kitty");
+        scratchpad.workspace.name = "special:scratch".to_string();
+        *backend.clients.lock().unwrap() = vec![scratchpad];
+        let renamer = Renamer::with_backend(
+            Config {
+                cfg_path: None,
+                config: config.clone(),
+            },
+            Args::default(),
+            backend.clone(),
+        );
+        renamer.rename_workspace("test").unwrap();
+        assert_eq!(
+            std::mem::take(&mut *backend.renames.lock().unwrap()),
+            [(-98, "-98: term".to_string())]
+        );
+
+        config.format.workspace = "{id}{delim}{clients} ({id_abs})".to_string();
+        config.format.special_prefix = Some("S:{special_name}".to_string());
+        renamer.cfg.lock().unwrap().config = config.clone();
+        renamer.rename_workspace("test").unwrap();
+        assert_eq!(
+            std::mem::take(&mut *backend.renames.lock().unwrap()),
+            [(-98, "S:scratch term (98)".to_string())]
+        );
+
+        // The special format doesn't apply to regular workspaces
+        config.format.workspace_special = Some("[{name}]{delim}{clients}".to_string());
+        renamer.cfg.lock().unwrap().config = config.clone();
+        backend
+            .clients
+            .lock()
+            .unwrap()
+            .push(hyprland_client("0x2", 1, "kitty"));
+        renamer.rename_workspace("test").unwrap();
+        let mut renames = std::mem::take(&mut *backend.renames.lock().unwrap());
+        renames.sort();
+        assert_eq!(
+            renames,
+            [
+                (-98, "[scratch] term".to_string()),
+                (1, "1 term (1)".to_string())
+            ]
+        );
+
+        // Unless empty, with its own format
+        config.format.workspace_special_empty = Some("[{name}]".to_string());
+        renamer.cfg.lock().unwrap().config = config;
+        backend
+            .clients
+            .lock()
+            .unwrap()
+            .retain(|c| c.workspace.id > 0);
+        renamer.rename_workspace("test").unwrap();
+        assert_eq!(
+            *backend.renames.lock().unwrap(),
+            [(-98, "[scratch]".to_string())]
+        );
+        assert_eq!(special_name(-99, None), "special");
+        assert_eq!(special_name(3, Some(&"3".to_string())), "");
+    }
+
+    #[test]
+    fn test_braces_in_titles() {
+        let config = crate::config::read_config_file(None, false, false).unwrap();
+        let renamer = Renamer::new(
+            Config {
+                cfg_path: None,
+                config: config.clone(),
+            },
+            Args::default(),
+        );
+
+        let client = AppClient {
+            initial_class: "firefox".to_string(),
+            class: "firefox".to_string(),
+            title: "{icon} and {unknown}".to_string(),
+            initial_title: "firefox".to_string(),
+            is_active: false,
+            is_fullscreen: FullscreenMode::None,
+            matched_rule: Inactive(Class("DEFAULT".to_string(), "{class}: {title}".to_string())),
+            is_dedup_inactive_fullscreen: false,
+            size: (0, 0),
+            urgent_for: None,
+            is_unmanaged: false,
+            is_floating: false,
+            is_pinned: false,
+            is_xwayland: false,
+            position: (0, 0),
+            is_fullscreen_client: FullscreenMode::None,
+        };
+
+        let workspaces = vec![AppWorkspace::new(1, vec![client])];
+        let actual = renamer.generate_workspaces_names(workspaces, &HashMap::new(), &config);
+        assert_eq!(actual[&1], "1: firefox: {icon} and {unknown}");
+    }
+
+    #[test]
+    fn test_urgent_vars() {
+        let mut config = crate::config::read_config_file(None, false, false).unwrap();
+        config.format.workspace = "{id}{urgent}:{clients} {urgent_for}".to_string();
+        config.format.client = "{icon}{urgent}".to_string();
+
+        let renamer = Renamer::new(
+            Config {
+                cfg_path: None,
+                config: config.clone(),
+            },
+            Args::default(),
+        );
+
+        let client = |urgent_for: Option<u64>| AppClient {
+            initial_class: "kitty".to_string(),
+            class: "kitty".to_string(),
+            title: "kitty".to_string(),
+            initial_title: "kitty".to_string(),
+            is_active: false,
+            is_fullscreen: FullscreenMode::None,
+            matched_rule: Inactive(Class("kitty".to_string(), "term".to_string())),
+            is_dedup_inactive_fullscreen: false,
+            size: (0, 0),
+            urgent_for: urgent_for.map(Duration::from_secs),
+            is_unmanaged: false,
+            is_floating: false,
+            is_pinned: false,
+            is_xwayland: false,
+            position: (0, 0),
+            is_fullscreen_client: FullscreenMode::None,
+        };
+
+        let workspaces = vec![
+            AppWorkspace::new(1, vec![client(Some(12)), client(Some(150)), client(None)]),
+            AppWorkspace::new(2, vec![client(None)]),
+        ];
+
+        let actual = renamer.generate_workspaces_names(workspaces, &HashMap::new(), &config);
+        let expected = HashMap::from([
+            (1, "1!:term! term! term 2m".to_string()),
+            (2, "2:term".to_string()),
+        ]);
+        assert_eq!(actual, expected);
+        assert!(uses_urgent_for_var(&config.format));
+    }
+
+    #[test]
+    fn test_uses_vars_of_every_workspace_format() {
+        let mut format = crate::config::read_config_file(None, false, false)
+            .unwrap()
+            .format;
+        assert!(!uses_monitor_vars(&format));
+        assert!(!uses_persistent_vars(&format));
+        assert!(!uses_workspace_windows_var(&format));
+
+        format.workspace_special = Some("{monitor}".to_string());
+        format.workspace_special_empty = Some("{workspace_windows}".to_string());
+        assert!(uses_monitor_vars(&format));
+        assert!(uses_workspace_windows_var(&format));
+        assert!(!uses_persistent_vars(&format));
+    }
+
+    #[test]
+    fn test_move_workspace_and_rename_cmd() {
+        let dir = std::env::temp_dir().join("hyprland-autoname-workspaces-move-rename-cmd-test");
+        _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+
+        let mut config = crate::config::read_config_file(None, false, false).unwrap();
+        config.behavior.rename_cmd = Some(format!("touch {}/{{monitor}}-{{id}}", dir.display()));
+        let backend = Arc::new(backend::MockBackend::default());
+        *backend.clients.lock().unwrap() = vec![hyprland_client("0x1", 1, "kitty")];
+        *backend.monitors.lock().unwrap() = vec![(1, "DP-1".to_string())];
+        let renamer = Renamer::with_backend(
+            Config {
+                cfg_path: None,
+                config: config.clone(),
+            },
+            Args::default(),
+            backend.clone(),
+        );
+        renamer.rename_workspace("test").unwrap();
+        assert!(dir.join("DP-1-1").exists());
+
+        // Same name, but the command is run again with the new monitor
+        let moved = WorkspaceMovedEventData {
+            id: 1,
+            name: hyprland::shared::WorkspaceType::Regular("1".to_string()),
+            monitor: "HDMI-A-1".to_string(),
+        };
+        assert!(renamer.move_workspace(&moved, &config).unwrap());
+        renamer.rename_workspace("workspace_moved").unwrap();
+        assert!(dir.join("HDMI-A-1-1").exists());
+        assert!(backend.renames.lock().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_move_workspace_and_monitor_var() {
+        let mut config = crate::config::read_config_file(None, false, false).unwrap();
+        let renamer = Renamer::new(
+            Config {
+                cfg_path: None,
+                config: config.clone(),
+            },
+            Args::default(),
+        );
+
+        let moved = |monitor: &str| WorkspaceMovedEventData {
+            id: 1,
+            name: hyprland::shared::WorkspaceType::Regular("1".to_string()),
+            monitor: monitor.to_string(),
+        };
+
+        // The default formats don't depend on the monitor
+        assert!(!renamer.move_workspace(&moved("DP-1"), &config).unwrap());
+
+        config.format.workspace = "{monitor}:{id}:{clients}".to_string();
+        config.format.workspace_empty = "{monitor}({monitor_id}):{id}".to_string();
+        assert!(renamer.move_workspace(&moved("HDMI-A-1"), &config).unwrap());
+        assert!(!renamer.move_workspace(&moved("HDMI-A-1"), &config).unwrap());
+
+        renamer
+            .monitor_ids
+            .lock()
+            .unwrap()
+            .insert("HDMI-A-1".to_string(), 1);
+        let actual = renamer.generate_workspaces_names(
+            vec![AppWorkspace::new(1, vec![]), AppWorkspace::new(2, vec![])],
+            &HashMap::new(),
+            &config,
+        );
+        let expected = HashMap::from([(1, "HDMI-A-1(1):1".to_string()), (2, "():2".to_string())]);
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn test_client_sort_by_position() {
+        let mut config = crate::config::read_config_file(None, false, false).unwrap();
+        config.format.client = "{icon}".to_string();
+
+        let renamer = Renamer::new(
+            Config {
+                cfg_path: None,
+                config: config.clone(),
+            },
+            Args::default(),
+        );
+
+        let client = |icon: &str, position| AppClient {
+            initial_class: icon.to_string(),
+            class: icon.to_string(),
+            title: icon.to_string(),
+            initial_title: icon.to_string(),
+            is_active: false,
+            is_fullscreen: FullscreenMode::None,
+            matched_rule: Inactive(Class(icon.to_string(), icon.to_string())),
+            is_dedup_inactive_fullscreen: false,
+            size: (0, 0),
+            urgent_for: None,
+            is_unmanaged: false,
+            is_floating: false,
+            is_pinned: false,
+            is_xwayland: false,
+            is_fullscreen_client: FullscreenMode::None,
+            position,
+        };
+        let workspaces = vec![AppWorkspace::new(
+            1,
+            vec![
+                client("right", (960, 0)),
+                client("bottom-left", (0, 540)),
+                client("top-left", (0, 0)),
+            ],
+        )];
+
+        let strings = renamer.generate_workspaces_string(workspaces.clone(), &config);
+        assert_eq!(strings[&1], "right bottom-left top-left");
+
+        config.format.client_sort = crate::config::ClientSort::Position;
+        let strings = renamer.generate_workspaces_string(workspaces, &config);
+        assert_eq!(strings[&1], "top-left bottom-left right");
+    }
+
+    #[test]
+    fn test_modified_markers() {
+        let mut config = crate::config::read_config_file(None, false, false).unwrap();
+        config.format.client = "{icon}".to_string();
+        config.format.client_modified = "{icon}{modified}".to_string();
+        config
+            .modified_markers
+            .push((Regex::new(r"^\*").unwrap(), "*".to_string()));
+
+        let renamer = Renamer::new(
+            Config {
+                cfg_path: None,
+                config: config.clone(),
+            },
+            Args::default(),
+        );
+
+        let client = |title: &str| AppClient {
+            initial_class: "kitty".to_string(),
+            class: "kitty".to_string(),
+            title: title.to_string(),
+            initial_title: "kitty".to_string(),
+            is_active: false,
+            is_fullscreen: FullscreenMode::None,
+            matched_rule: Inactive(Class("kitty".to_string(), "vim".to_string())),
+            is_dedup_inactive_fullscreen: false,
+            size: (0, 0),
+            urgent_for: None,
+            is_unmanaged: false,
+            is_floating: false,
+            is_pinned: false,
+            is_xwayland: false,
+            position: (0, 0),
+            is_fullscreen_client: FullscreenMode::None,
+        };
+
+        let workspaces = vec![
+            AppWorkspace::new(1, vec![client("*main.rs - vim")]),
+            AppWorkspace::new(2, vec![client("main.rs - vim")]),
+        ];
+        let strings = renamer.generate_workspaces_string(workspaces, &config);
+
+        assert_eq!(strings[&1], "vim*");
+        assert_eq!(strings[&2], "vim");
+    }
+
+    #[test]
+    fn test_prefer_initial() {
+        let mut config = crate::config::read_config_file(None, false, false).unwrap();
+        config
+            .class
+            .push((Regex::new("kitty").unwrap(), "term".to_string()));
+        config.title_in_class.push((
+            Regex::new("kitty").unwrap(),
+            vec![(Regex::new("vim").unwrap(), "editor".to_string())],
+        ));
+
+        let renamer = Renamer::new(
+            Config {
+                cfg_path: None,
+                config: config.clone(),
+            },
+            Args::default(),
+        );
+
+        let parse_icon = |config: &ConfigFile| {
+            renamer
+                .parse_icon(
+                    "kitty".to_string(),
+                    "kitty".to_string(),
+                    "zsh".to_string(),
+                    "vim".to_string(),
+                    false,
+                    config,
+                )
+                .icon()
+        };
+
+        assert_eq!(parse_icon(&config), "editor");
+
+        config.matching.prefer_initial = true;
+        assert_eq!(parse_icon(&config), "editor");
+
+        config
+            .initial_class
+            .push((Regex::new("kitty").unwrap(), "stable term".to_string()));
+        assert_eq!(parse_icon(&config), "stable term");
+
+        config.matching.prefer_initial = false;
+        assert_eq!(parse_icon(&config), "editor");
+    }
+
+    #[test]
+    fn test_continue_rules() {
+        let mut config = crate::config::read_config_file(None, false, false).unwrap();
+        config
+            .class
+            .push((Regex::new("firefox").unwrap(), "browser".to_string()));
+        config.title_in_class.push((
+            Regex::new("firefox").unwrap(),
+            vec![(
+                Regex::new("(?P<repo>\\w+) · GitHub").unwrap(),
+                "gh:{repo} ".to_string(),
+            )],
+        ));
+
+        let renamer = Renamer::new(
+            Config {
+                cfg_path: None,
+                config: config.clone(),
+            },
+            Args::default(),
+        );
+
+        let parse_icon = |config: &ConfigFile, title: &str| {
+            renamer.parse_icon(
+                "firefox".to_string(),
+                "firefox".to_string(),
+                title.to_string(),
+                title.to_string(),
+                false,
+                config,
+            )
+        };
+
+        assert_eq!(parse_icon(&config, "crate · GitHub").icon(), "gh:{repo} ");
+
+        config.matching.continue_rules = vec!["(?P<repo>\\w+) · GitHub".to_string()];
+        let icon = parse_icon(&config, "crate · GitHub");
+        assert_eq!(icon.icon(), "gh:{repo} browser");
+        assert_eq!(icon.captures().unwrap()["repo"], "crate");
+        assert_eq!(parse_icon(&config, "News").icon(), "browser");
+
+        // Nothing to continue with, the match wins anyway
+        config.class.clear();
+        assert_eq!(parse_icon(&config, "crate · GitHub").icon(), "gh:{repo} ");
+    }
+
     #[test]
     fn test_workspaces_name_config() {
         let mut config = crate::config::read_config_file(None, false, false).unwrap();
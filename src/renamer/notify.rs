@@ -0,0 +1,23 @@
+use crate::config::ConfigBehaviorRaw;
+use notify_rust::{Notification, Urgency};
+
+const BIN_NAME: &str = env!("CARGO_BIN_NAME");
+
+/// Prints the error, and shows it as a desktop notification if enabled,
+/// since the daemon usually runs detached from any terminal.
+pub fn notify_error(behavior: &ConfigBehaviorRaw, summary: &str, body: &str) {
+    eprintln!("{summary}: {body}");
+    if !behavior.notify_on_error {
+        return;
+    }
+
+    let notified = Notification::new()
+        .appname(BIN_NAME)
+        .summary(summary)
+        .body(body)
+        .urgency(Urgency::Critical)
+        .show();
+    if let Err(e) = notified {
+        eprintln!("Unable to send notification: {e}");
+    }
+}
@@ -0,0 +1,80 @@
+use zbus::blocking::Connection;
+use zbus::dbus_proxy;
+
+#[dbus_proxy(
+    interface = "org.freedesktop.UPower",
+    default_service = "org.freedesktop.UPower",
+    default_path = "/org/freedesktop/UPower"
+)]
+trait UPower {
+    #[dbus_proxy(property)]
+    fn on_battery(&self) -> zbus::Result<bool>;
+}
+
+#[dbus_proxy(
+    interface = "net.hadess.PowerProfiles",
+    default_service = "net.hadess.PowerProfiles",
+    default_path = "/net/hadess/PowerProfiles"
+)]
+trait PowerProfiles {
+    #[dbus_proxy(property)]
+    fn active_profile(&self) -> zbus::Result<String>;
+}
+
+/// Power source and profile, as reported by upower and
+/// power-profiles-daemon.
+#[derive(Default, Debug, Clone, PartialEq, Eq)]
+pub struct PowerState {
+    pub on_battery: bool,
+    pub profile: String,
+}
+
+impl PowerState {
+    pub fn is_low_power(&self) -> bool {
+        self.on_battery || matches!(self.profile.as_str(), "power-saver" | "low-power")
+    }
+}
+
+pub fn connect() -> zbus::Result<Connection> {
+    Connection::system()
+}
+
+/// Calls `on_change` with the power source, then every time it changes,
+/// until upower goes away.
+pub fn watch_on_battery(connection: &Connection, on_change: impl Fn(bool)) -> zbus::Result<()> {
+    let upower = UPowerProxyBlocking::new(connection)?;
+    on_change(upower.on_battery()?);
+    for changed in upower.receive_on_battery_changed() {
+        on_change(changed.get()?);
+    }
+    Ok(())
+}
+
+/// Calls `on_change` with the power profile, then every time it changes,
+/// until power-profiles-daemon goes away.
+pub fn watch_profile(connection: &Connection, on_change: impl Fn(String)) -> zbus::Result<()> {
+    let profiles = PowerProfilesProxyBlocking::new(connection)?;
+    on_change(profiles.active_profile()?);
+    for changed in profiles.receive_active_profile_changed() {
+        on_change(changed.get()?);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_low_power() {
+        let state = |on_battery, profile: &str| PowerState {
+            on_battery,
+            profile: profile.to_string(),
+        };
+        assert!(state(true, "balanced").is_low_power());
+        assert!(state(false, "power-saver").is_low_power());
+        assert!(state(false, "low-power").is_low_power());
+        assert!(!state(false, "performance").is_low_power());
+        assert!(!PowerState::default().is_low_power());
+    }
+}
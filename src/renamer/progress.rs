@@ -0,0 +1,82 @@
+use crate::config::TitleProgress;
+
+const BAR_PARTIALS: [char; 8] = ['▏', '▎', '▍', '▌', '▋', '▊', '▉', '█'];
+const BAR_EMPTY: char = '░';
+
+/// Extracts a percentage from a title using the first matching pattern.
+///
+/// A pattern with one capture group is read as a percentage, e.g. `(\d+)%`,
+/// a pattern with two capture groups is read as `current` of `total`,
+/// e.g. `(\d+) of (\d+)`. The result is clamped between 0 and 100.
+pub fn parse_progress(title: &str, title_progress: &TitleProgress) -> Option<u32> {
+    title_progress.patterns.iter().find_map(|re| {
+        let captures = re.captures(title)?;
+        let first = captures.get(1)?.as_str().parse::<u64>().ok()?;
+        let percent = match captures.get(2) {
+            Some(total) => {
+                let total = total.as_str().parse::<u64>().ok()?;
+                if total == 0 {
+                    return None;
+                }
+                first * 100 / total
+            }
+            None => first,
+        };
+        Some(percent.min(100) as u32)
+    })
+}
+
+/// Renders a percentage as a unicode bar of `width` cells, with eighth blocks
+/// for the partially filled cell.
+pub fn to_progress_bar(percent: u32, width: usize) -> String {
+    let eighths = (percent.min(100) as usize * width * 8) / 100;
+    let full = eighths / 8;
+    let partial = eighths % 8;
+
+    let mut bar = BAR_PARTIALS[7].to_string().repeat(full);
+    if partial > 0 {
+        bar.push(BAR_PARTIALS[partial - 1]);
+    }
+    let filled = full + usize::from(partial > 0);
+    bar.push_str(&BAR_EMPTY.to_string().repeat(width.saturating_sub(filled)));
+    bar
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use regex::Regex;
+
+    fn title_progress() -> TitleProgress {
+        TitleProgress {
+            patterns: vec![
+                Regex::new(r"(\d+)%").unwrap(),
+                Regex::new(r"(\d+) of (\d+)").unwrap(),
+            ],
+            bar_width: 5,
+        }
+    }
+
+    #[test]
+    fn test_parse_progress() {
+        let title_progress = title_progress();
+        assert_eq!(parse_progress("wget: 42% done", &title_progress), Some(42));
+        assert_eq!(
+            parse_progress("emerge: (13 of 20) dev-lang/rust", &title_progress),
+            Some(65)
+        );
+        assert_eq!(parse_progress("150%", &title_progress), Some(100));
+        assert_eq!(parse_progress("0 of 0", &title_progress), None);
+        assert_eq!(parse_progress("zsh", &title_progress), None);
+        assert_eq!(parse_progress("42%", &TitleProgress::default()), None);
+    }
+
+    #[test]
+    fn test_to_progress_bar() {
+        assert_eq!(to_progress_bar(0, 5), "░░░░░");
+        assert_eq!(to_progress_bar(50, 5), "██▌░░");
+        assert_eq!(to_progress_bar(65, 5), "███▎░");
+        assert_eq!(to_progress_bar(100, 5), "█████");
+        assert_eq!(to_progress_bar(100, 0), "");
+    }
+}
@@ -0,0 +1,72 @@
+use std::process::Command;
+
+/// Renames the workspace with the command of `behavior.rename_cmd` rather
+/// than the backend. Its words are not given to a shell, so names need no
+/// quoting.
+pub fn run_rename_cmd(template: &str, id: &str, vars: &[(&str, &str)]) {
+    let Some((program, args)) = rename_args(template, vars) else {
+        eprintln!("Unable to rename workspace {id}: rename_cmd is empty");
+        return;
+    };
+    match Command::new(&program).args(args).status() {
+        Ok(status) if !status.success() => {
+            eprintln!("Unable to rename workspace {id}: {program} exited with {status}")
+        }
+        Ok(_) => {}
+        Err(e) => eprintln!("Unable to rename workspace {id}: {program}: {e}"),
+    }
+}
+
+/// Splits the template on whitespace, then replaces the variables, like
+/// `{id}` and `{name}`, in every word.
+fn rename_args(template: &str, vars: &[(&str, &str)]) -> Option<(String, Vec<String>)> {
+    let mut words = template.split_whitespace().map(|word| {
+        vars.iter().fold(word.to_string(), |word, (var, value)| {
+            word.replace(&format!("{{{var}}}"), value)
+        })
+    });
+    let program = words.next()?;
+    Some((program, words.collect()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rename_args() {
+        assert_eq!(
+            rename_args(
+                "hyprctl dispatch renameworkspace {id} {name}",
+                &[("id", "2"), ("name", "2: term {web}")]
+            ),
+            Some((
+                "hyprctl".to_string(),
+                vec![
+                    "dispatch".to_string(),
+                    "renameworkspace".to_string(),
+                    "2".to_string(),
+                    "2: term {web}".to_string(),
+                ]
+            ))
+        );
+        assert_eq!(
+            rename_args(
+                "rename.sh --name={name}",
+                &[("id", "-98"), ("name", "scratch")]
+            ),
+            Some(("rename.sh".to_string(), vec!["--name=scratch".to_string()]))
+        );
+        assert_eq!(
+            rename_args(
+                "rename.sh {monitor}:{id} {monitor_id}",
+                &[("id", "3"), ("monitor", "DP-1"), ("monitor_id", "1")]
+            ),
+            Some((
+                "rename.sh".to_string(),
+                vec!["DP-1:3".to_string(), "1".to_string()]
+            ))
+        );
+        assert_eq!(rename_args("  ", &[("id", "1"), ("name", "1")]), None);
+    }
+}
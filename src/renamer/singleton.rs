@@ -0,0 +1,43 @@
+use std::collections::{HashMap, HashSet};
+
+/// The last focused window of each singleton class, by class. The class is
+/// only shown on the workspace of this window.
+#[derive(Debug, Default)]
+pub struct SingletonTracker {
+    focused: HashMap<String, String>,
+}
+
+impl SingletonTracker {
+    pub fn record(&mut self, class: &str, address: &str) {
+        self.focused.insert(class.to_string(), address.to_string());
+    }
+
+    /// Forgets the closed windows.
+    pub fn retain(&mut self, addresses: &HashSet<String>) {
+        self.focused
+            .retain(|_, address| addresses.contains(address));
+    }
+
+    pub fn get(&self, class: &str) -> Option<&str> {
+        self.focused.get(class).map(String::as_str)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_singleton_tracker() {
+        let mut singletons = SingletonTracker::default();
+        singletons.record("firefox", "0x1");
+        singletons.record("firefox", "0x2");
+        singletons.record("chromium", "0x3");
+        assert_eq!(singletons.get("firefox"), Some("0x2"));
+        assert_eq!(singletons.get("kitty"), None);
+
+        singletons.retain(&HashSet::from(["0x1".to_string(), "0x3".to_string()]));
+        assert_eq!(singletons.get("firefox"), None);
+        assert_eq!(singletons.get("chromium"), Some("0x3"));
+    }
+}
@@ -0,0 +1,251 @@
+use crate::renamer::event_queue::QueueStats;
+use std::fmt::Write;
+use std::path::Path;
+use std::time::{Duration, SystemTime};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ListenerState {
+    Starting,
+    Listening,
+    Stopped,
+}
+
+/// Health of the daemon, reported by `hyprland-autoname-workspaces status`.
+#[derive(Debug, Clone)]
+pub struct DaemonStatus {
+    started_at: SystemTime,
+    last_render: Option<SystemTime>,
+    listener: ListenerState,
+    last_error: Option<String>,
+    cache: CacheStats,
+    /// Since when the session is idle, the renames are paused meanwhile
+    idle_since: Option<SystemTime>,
+}
+
+/// Effectiveness of the names cache. A hit is a workspace whose name didn't
+/// change, so its rename is not dispatched.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CacheStats {
+    pub hits: u64,
+    pub misses: u64,
+    /// Renders skipped as a whole, as no name changed
+    pub skipped_batches: u64,
+}
+
+impl CacheStats {
+    pub fn record(&mut self, hits: usize, misses: usize, skipped_batch: bool) {
+        self.hits += hits as u64;
+        self.misses += misses as u64;
+        self.skipped_batches += u64::from(skipped_batch);
+    }
+
+    /// Returns the percentage of hits, if the cache was used.
+    pub fn hit_ratio(&self) -> Option<u64> {
+        let total = self.hits + self.misses;
+        (total > 0).then(|| self.hits * 100 / total)
+    }
+}
+
+impl std::fmt::Display for CacheStats {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.hit_ratio() {
+            Some(ratio) => write!(f, "{ratio}% hits, ")?,
+            None => write!(f, "unused, ")?,
+        }
+        write!(
+            f,
+            "{} dispatches avoided, {} made, {} batches skipped",
+            self.hits, self.misses, self.skipped_batches
+        )
+    }
+}
+
+impl DaemonStatus {
+    pub fn new(now: SystemTime) -> Self {
+        DaemonStatus {
+            started_at: now,
+            last_render: None,
+            listener: ListenerState::Starting,
+            last_error: None,
+            cache: CacheStats::default(),
+            idle_since: None,
+        }
+    }
+
+    pub fn on_render(&mut self, now: SystemTime) {
+        self.last_render = Some(now);
+    }
+
+    pub fn on_error(&mut self, error: impl Into<String>) {
+        self.last_error = Some(error.into());
+    }
+
+    /// Records a render, and returns the stats of the cache so far.
+    pub fn on_cache(&mut self, hits: usize, misses: usize, skipped_batch: bool) -> CacheStats {
+        self.cache.record(hits, misses, skipped_batch);
+        self.cache
+    }
+
+    /// Records the session going idle, unless it already is.
+    pub fn on_idle(&mut self, now: SystemTime) {
+        self.idle_since.get_or_insert(now);
+    }
+
+    /// Records the session being active again, returns true if it was idle.
+    pub fn on_resume(&mut self) -> bool {
+        self.idle_since.take().is_some()
+    }
+
+    pub fn is_idle(&self) -> bool {
+        self.idle_since.is_some()
+    }
+
+    pub fn set_listener(&mut self, listener: ListenerState) {
+        self.listener = listener;
+    }
+
+    /// Renders the status, one `key: value` per line.
+    pub fn report(
+        &self,
+        pid: u32,
+        config_path: Option<&Path>,
+        quarantine: &[String],
+        workspaces: usize,
+        events: QueueStats,
+        now: SystemTime,
+    ) -> String {
+        let ago = |time: SystemTime| match now.duration_since(time) {
+            Ok(elapsed) => format!("{} ago", format_duration(elapsed)),
+            Err(_) => "just now".to_string(),
+        };
+
+        let config = match config_path {
+            Some(path) => match path.metadata().and_then(|m| m.modified()) {
+                Ok(mtime) => format!("{} (modified {})", path.display(), ago(mtime)),
+                Err(_) => format!("{} (missing)", path.display()),
+            },
+            None => "inline".to_string(),
+        };
+        let mut quarantine = quarantine.to_vec();
+        quarantine.sort();
+        let quarantine = match quarantine.len() {
+            0 => "none".to_string(),
+            count => format!("{count} ({})", quarantine.join("; ")),
+        };
+        let listener = match self.listener {
+            ListenerState::Starting => "starting",
+            ListenerState::Listening => "listening",
+            ListenerState::Stopped => "stopped",
+        };
+
+        let mut report = String::new();
+        _ = writeln!(report, "pid: {pid}");
+        _ = writeln!(
+            report,
+            "uptime: {}",
+            format_duration(now.duration_since(self.started_at).unwrap_or_default())
+        );
+        _ = writeln!(report, "config: {config}");
+        _ = writeln!(report, "invalid regexes: {quarantine}");
+        _ = writeln!(
+            report,
+            "last render: {}",
+            self.last_render.map_or("never".to_string(), ago)
+        );
+        _ = writeln!(report, "workspaces: {workspaces}");
+        _ = writeln!(report, "cache: {}", self.cache);
+        _ = writeln!(report, "listener: {listener}");
+        _ = writeln!(report, "events: {events}");
+        _ = writeln!(
+            report,
+            "session: {}",
+            self.idle_since
+                .map_or("active".to_string(), |since| format!(
+                    "idle since {}",
+                    ago(since)
+                ))
+        );
+        _ = write!(
+            report,
+            "last error: {}",
+            self.last_error.as_deref().unwrap_or("none")
+        );
+        report
+    }
+}
+
+/// Formats the duration as `1h 2m 3s`, skipping the leading zero units.
+fn format_duration(duration: Duration) -> String {
+    let secs = duration.as_secs();
+    let (hours, minutes, seconds) = (secs / 3600, secs / 60 % 60, secs % 60);
+    match (hours, minutes) {
+        (0, 0) => format!("{seconds}s"),
+        (0, _) => format!("{minutes}m {seconds}s"),
+        _ => format!("{hours}h {minutes}m {seconds}s"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_duration() {
+        assert_eq!(format_duration(Duration::from_secs(0)), "0s");
+        assert_eq!(format_duration(Duration::from_secs(62)), "1m 2s");
+        assert_eq!(format_duration(Duration::from_secs(3725)), "1h 2m 5s");
+    }
+
+    #[test]
+    fn test_report() {
+        let started_at = SystemTime::UNIX_EPOCH;
+        let mut status = DaemonStatus::new(started_at);
+        let now = started_at + Duration::from_secs(90);
+
+        assert_eq!(
+            status.report(42, None, &[], 0, QueueStats::default(), now),
+            "pid: 42\nuptime: 1m 30s\nconfig: inline\ninvalid regexes: none\nlast render: never\n\
+             workspaces: 0\ncache: unused, 0 dispatches avoided, 0 made, 0 batches skipped\n\
+             listener: starting\nevents: 0 queued, 0 stale dropped, 0 dropped when full\nsession: active\nlast error: none"
+        );
+
+        status.on_render(started_at + Duration::from_secs(85));
+        status.on_error("Unable to reload config");
+        status.set_listener(ListenerState::Listening);
+        status.on_cache(0, 3, false);
+        status.on_cache(3, 0, true);
+        status.on_cache(2, 1, false);
+        let quarantine = [
+            r#"exclude."steam": unclosed group"#.to_string(),
+            r#"class."Class1[": unclosed character class"#.to_string(),
+        ];
+        let config_path = Some(Path::new("/nonexistent.toml"));
+        let events = QueueStats {
+            queued: 12,
+            stale: 9,
+            overflowed: 1,
+        };
+        let report = status.report(42, config_path, &quarantine, 3, events, now);
+        assert!(report.contains("config: /nonexistent.toml (missing)\n"));
+        assert!(report.contains(
+            "invalid regexes: 2 (class.\"Class1[\": unclosed character class; \
+             exclude.\"steam\": unclosed group)\n"
+        ));
+        assert!(report.contains("last render: 5s ago\n"));
+        assert!(report.contains("workspaces: 3\n"));
+        assert!(
+            report.contains("cache: 55% hits, 5 dispatches avoided, 4 made, 1 batches skipped\n")
+        );
+        assert!(report.contains("listener: listening\n"));
+        assert!(report.contains("events: 12 queued, 9 stale dropped, 1 dropped when full\n"));
+        assert!(report.ends_with("last error: Unable to reload config"));
+
+        status.on_idle(started_at + Duration::from_secs(30));
+        status.on_idle(started_at + Duration::from_secs(60));
+        assert!(status.is_idle());
+        let report = status.report(42, None, &[], 3, QueueStats::default(), now);
+        assert!(report.contains("session: idle since 1m 0s ago\n"));
+        assert!(status.on_resume());
+        assert!(!status.on_resume());
+    }
+}
@@ -0,0 +1,90 @@
+use std::collections::{HashMap, HashSet};
+
+/// Hyprland can't change window titles, windows are tagged with the name
+/// of their icon instead, e.g. `autoname-term`, for tools reading tags.
+const TAG_PREFIX: &str = "autoname-";
+
+/// The tag set on every window, by address.
+#[derive(Debug, Default)]
+pub struct WindowTags {
+    tags: HashMap<String, String>,
+}
+
+impl WindowTags {
+    /// Returns the tag to remove and the tag to set, if the tag changed.
+    pub fn update(&mut self, address: &str, tag: String) -> Option<(Option<String>, String)> {
+        match self.tags.insert(address.to_string(), tag.clone()) {
+            Some(previous) if previous == tag => None,
+            previous => Some((previous, tag)),
+        }
+    }
+
+    /// Forgets the closed windows.
+    pub fn retain(&mut self, addresses: &HashSet<String>) {
+        self.tags.retain(|address, _| addresses.contains(address));
+    }
+}
+
+/// Returns the tag of a rendered icon: its words, without markup, in lower
+/// case. None if there are no words, e.g. for a glyph.
+pub fn tag_name(icon: &str) -> Option<String> {
+    let mut text = String::new();
+    let mut in_markup = false;
+    for c in icon.chars() {
+        match c {
+            '<' => in_markup = true,
+            '>' => in_markup = false,
+            c if !in_markup => text.push(c),
+            _ => {}
+        }
+    }
+
+    let words: Vec<String> = text
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(str::to_lowercase)
+        .collect();
+    (!words.is_empty()).then(|| format!("{TAG_PREFIX}{}", words.join("-")))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tag_name() {
+        assert_eq!(tag_name("term"), Some("autoname-term".to_string()));
+        assert_eq!(
+            tag_name("<span color='red'>Web Browser</span>"),
+            Some("autoname-web-browser".to_string())
+        );
+        assert_eq!(
+            tag_name("\u{f489} kitty: vim"),
+            Some("autoname-kitty-vim".to_string())
+        );
+        assert_eq!(tag_name("\u{f489}"), None);
+    }
+
+    #[test]
+    fn test_update() {
+        let mut tags = WindowTags::default();
+        assert_eq!(
+            tags.update("0x1", "autoname-term".to_string()),
+            Some((None, "autoname-term".to_string()))
+        );
+        assert_eq!(tags.update("0x1", "autoname-term".to_string()), None);
+        assert_eq!(
+            tags.update("0x1", "autoname-vim".to_string()),
+            Some((
+                Some("autoname-term".to_string()),
+                "autoname-vim".to_string()
+            ))
+        );
+
+        tags.retain(&HashSet::new());
+        assert_eq!(
+            tags.update("0x1", "autoname-vim".to_string()),
+            Some((None, "autoname-vim".to_string()))
+        );
+    }
+}
@@ -0,0 +1,145 @@
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+/// Storms are only detected in the first seconds of the session.
+const STARTUP_PERIOD: Duration = Duration::from_secs(30);
+/// That many opened windows within `STORM_WINDOW` start a storm.
+const STORM_OPENS: usize = 5;
+const STORM_WINDOW: Duration = Duration::from_secs(2);
+/// Cadence of the renames during a storm.
+const BATCH_INTERVAL: Duration = Duration::from_secs(1);
+/// The storm is over once no event happened for that long.
+const QUIET_PERIOD: Duration = Duration::from_secs(2);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TickAction {
+    Idle,
+    Render,
+    FinalRender,
+}
+
+#[derive(Debug)]
+struct Storm {
+    last_event: Instant,
+    last_render: Instant,
+    pending: bool,
+}
+
+/// Detects the "startup storm" of a session restore opening many windows,
+/// to rename workspaces at a slower cadence until it is over.
+#[derive(Debug)]
+pub struct StartupThrottle {
+    started: Instant,
+    recent_opens: VecDeque<Instant>,
+    storm: Option<Storm>,
+}
+
+impl StartupThrottle {
+    pub fn new(now: Instant) -> Self {
+        StartupThrottle {
+            started: now,
+            recent_opens: VecDeque::new(),
+            storm: None,
+        }
+    }
+
+    /// Returns true if the event should be rendered right away,
+    /// false if it is deferred to the next batch.
+    pub fn on_event(&mut self, event: &str, now: Instant) -> bool {
+        if let Some(storm) = &mut self.storm {
+            storm.last_event = now;
+            storm.pending = true;
+            return false;
+        }
+
+        if event != "window_opened" || now.duration_since(self.started) > STARTUP_PERIOD {
+            return true;
+        }
+
+        self.recent_opens.push_back(now);
+        while let Some(&oldest) = self.recent_opens.front() {
+            if now.duration_since(oldest) <= STORM_WINDOW {
+                break;
+            }
+            self.recent_opens.pop_front();
+        }
+
+        if self.recent_opens.len() < STORM_OPENS {
+            return true;
+        }
+
+        eprintln!("Startup storm detected, batching renames until it is over");
+        self.recent_opens.clear();
+        self.storm = Some(Storm {
+            last_event: now,
+            last_render: now,
+            pending: true,
+        });
+        false
+    }
+
+    /// Called periodically, tells if the deferred events must be rendered.
+    pub fn on_tick(&mut self, now: Instant) -> TickAction {
+        let Some(storm) = &mut self.storm else {
+            return TickAction::Idle;
+        };
+
+        if now.duration_since(storm.last_event) >= QUIET_PERIOD {
+            eprintln!("Startup storm is over");
+            self.storm = None;
+            TickAction::FinalRender
+        } else if storm.pending && now.duration_since(storm.last_render) >= BATCH_INTERVAL {
+            storm.last_render = now;
+            storm.pending = false;
+            TickAction::Render
+        } else {
+            TickAction::Idle
+        }
+    }
+
+    /// Returns true once no storm can happen anymore.
+    pub fn is_over(&self, now: Instant) -> bool {
+        self.storm.is_none() && now.duration_since(self.started) > STARTUP_PERIOD
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_startup_storm() {
+        let start = Instant::now();
+        let at = |ms: u64| start + Duration::from_millis(ms);
+        let mut throttle = StartupThrottle::new(start);
+
+        // A few windows are rendered right away
+        assert!(throttle.on_event("window_opened", at(0)));
+        assert!(throttle.on_event("active_window_changed", at(10)));
+        assert!(throttle.on_event("window_opened", at(100)));
+        assert!(throttle.on_event("window_opened", at(200)));
+        assert!(throttle.on_event("window_opened", at(300)));
+
+        // The 5th window within 2s starts the storm
+        assert!(!throttle.on_event("window_opened", at(400)));
+        assert!(!throttle.on_event("window_title_changed", at(500)));
+        assert_eq!(throttle.on_tick(at(600)), TickAction::Idle);
+        assert_eq!(throttle.on_tick(at(1400)), TickAction::Render);
+        assert_eq!(throttle.on_tick(at(1600)), TickAction::Idle);
+
+        assert!(!throttle.on_event("window_opened", at(1700)));
+        assert_eq!(throttle.on_tick(at(2500)), TickAction::Render);
+
+        // Quiet for 2s, the storm is over
+        assert_eq!(throttle.on_tick(at(3700)), TickAction::FinalRender);
+        assert!(throttle.on_event("window_opened", at(3800)));
+        assert!(!throttle.is_over(at(3800)));
+
+        // Too late for a storm
+        let mut throttle = StartupThrottle::new(start);
+        for ms in 0..10 {
+            assert!(throttle.on_event("window_opened", at(31_000 + ms)));
+        }
+        assert!(throttle.is_over(at(31_000)));
+    }
+}
@@ -0,0 +1,58 @@
+use std::collections::{HashMap, HashSet};
+
+/// The recent titles of every window, by address, the oldest first.
+#[derive(Debug, Default)]
+pub struct TitleHistory {
+    titles: HashMap<String, Vec<String>>,
+}
+
+impl TitleHistory {
+    /// Appends the title if it changed, keeping at most `size` titles.
+    pub fn record(&mut self, address: &str, title: &str, size: usize) {
+        let titles = self.titles.entry(address.to_string()).or_default();
+        if titles.last().map(String::as_str) != Some(title) {
+            titles.push(title.to_string());
+        }
+        let excess = titles.len().saturating_sub(size);
+        titles.drain(..excess);
+    }
+
+    /// Forgets the closed windows.
+    pub fn retain(&mut self, addresses: &HashSet<String>) {
+        self.titles.retain(|address, _| addresses.contains(address));
+    }
+
+    pub fn get(&self, address: &str) -> &[String] {
+        self.titles.get(address).map_or(&[], Vec::as_slice)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_title_history() {
+        let mut history = TitleHistory::default();
+        history.record("0x1", "Zoom", 3);
+        history.record("0x1", "Zoom", 3);
+        history.record("0x1", "Meeting started", 3);
+        history.record("0x1", "Zoom Meeting", 3);
+        assert_eq!(
+            history.get("0x1"),
+            ["Zoom", "Meeting started", "Zoom Meeting"]
+        );
+
+        history.record("0x1", "Zoom", 3);
+        assert_eq!(
+            history.get("0x1"),
+            ["Meeting started", "Zoom Meeting", "Zoom"]
+        );
+
+        history.record("0x2", "kitty", 0);
+        assert!(history.get("0x2").is_empty());
+
+        history.retain(&HashSet::from(["0x2".to_string()]));
+        assert!(history.get("0x1").is_empty());
+    }
+}
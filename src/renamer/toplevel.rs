@@ -0,0 +1,358 @@
+use crate::renamer::backend::{ActiveWorkspace, Backend};
+use hyprland::data::{Client, FullscreenMode, WorkspaceBasic};
+use hyprland::shared::{Address, MonitorId};
+use serde_json::json;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::error::Error;
+use std::io::{self, Write};
+use std::sync::Mutex;
+use wayland_client::globals::{registry_queue_init, GlobalListContents};
+use wayland_client::protocol::wl_registry::{self, WlRegistry};
+use wayland_client::{event_created_child, Connection, Dispatch, Proxy, QueueHandle};
+use wayland_protocols_wlr::foreign_toplevel::v1::client::zwlr_foreign_toplevel_handle_v1::{
+    self as handle, ZwlrForeignToplevelHandleV1,
+};
+use wayland_protocols_wlr::foreign_toplevel::v1::client::zwlr_foreign_toplevel_manager_v1::{
+    self as manager, ZwlrForeignToplevelManagerV1,
+};
+
+/// The single workspace of the windows.
+pub const TOPLEVEL_WORKSPACE_ID: i32 = 1;
+
+const MANAGER_INTERFACE: &str = "zwlr_foreign_toplevel_manager_v1";
+const MANAGER_VERSION: u32 = 3;
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Toplevel {
+    pub id: u32,
+    pub app_id: String,
+    pub title: String,
+    pub activated: bool,
+    pub maximized: bool,
+    pub fullscreen: bool,
+}
+
+impl Toplevel {
+    fn address(&self) -> Address {
+        Address::new(format!("{:x}", self.id))
+    }
+
+    fn to_client(&self) -> Client {
+        let fullscreen = match (self.fullscreen, self.maximized) {
+            (true, _) => FullscreenMode::Fullscreen,
+            (false, true) => FullscreenMode::Maximized,
+            (false, false) => FullscreenMode::None,
+        };
+        Client {
+            address: self.address(),
+            at: (0, 0),
+            size: (0, 0),
+            workspace: WorkspaceBasic {
+                id: TOPLEVEL_WORKSPACE_ID,
+                name: TOPLEVEL_WORKSPACE_ID.to_string(),
+            },
+            floating: false,
+            fullscreen,
+            fullscreen_client: fullscreen,
+            monitor: 0,
+            initial_class: self.app_id.clone(),
+            class: self.app_id.clone(),
+            initial_title: self.title.clone(),
+            title: self.title.clone(),
+            // Unknown, but clients without pid are skipped
+            pid: 1,
+            xwayland: false,
+            pinned: false,
+            grouped: vec![],
+            mapped: true,
+            swallowing: None,
+            focus_history_id: 0,
+        }
+    }
+}
+
+/// Experimental backend for other compositors (river, niri, sway...),
+/// serving the windows of the `zwlr_foreign_toplevel_manager_v1` protocol.
+///
+/// These compositors have no workspace we can rename, so all the windows
+/// are on workspace 1, whose name is printed as a waybar JSON line instead,
+/// e.g. `{"text":"1: term"}`.
+#[derive(Debug, Default)]
+pub struct ToplevelBackend {
+    toplevels: Mutex<Vec<Toplevel>>,
+}
+
+impl ToplevelBackend {
+    pub fn set_toplevels(&self, toplevels: Vec<Toplevel>) {
+        if let Ok(mut current) = self.toplevels.lock() {
+            *current = toplevels;
+        }
+    }
+
+    fn toplevels(&self) -> Vec<Toplevel> {
+        self.toplevels.lock().map(|t| t.clone()).unwrap_or_default()
+    }
+}
+
+impl Backend for ToplevelBackend {
+    fn clients(&self) -> Result<Vec<Client>, Box<dyn Error>> {
+        Ok(self.toplevels().iter().map(Toplevel::to_client).collect())
+    }
+
+    fn active_client(&self) -> String {
+        self.toplevels()
+            .iter()
+            .find(|t| t.activated)
+            .map_or(Address::new("0"), Toplevel::address)
+            .to_string()
+    }
+
+    fn active_workspace(&self) -> Option<ActiveWorkspace> {
+        Some(ActiveWorkspace {
+            id: TOPLEVEL_WORKSPACE_ID,
+            monitor: String::new(),
+            windows: self.toplevels().len() as u16,
+        })
+    }
+
+    fn visible_workspaces(&self) -> (Option<i32>, Vec<i32>) {
+        (Some(TOPLEVEL_WORKSPACE_ID), vec![TOPLEVEL_WORKSPACE_ID])
+    }
+
+    fn workspace_monitors(&self) -> Option<Vec<(i32, String)>> {
+        Some(vec![(TOPLEVEL_WORKSPACE_ID, String::new())])
+    }
+
+    fn monitor_ids(&self) -> Option<HashMap<String, MonitorId>> {
+        None
+    }
+
+    fn workspace_windows(&self) -> Option<HashMap<i32, u16>> {
+        None
+    }
+
+    fn persistent_workspaces(&self) -> HashSet<i32> {
+        HashSet::new()
+    }
+
+    fn tag_window(&self, _address: &str, _previous: Option<&str>, _tag: &str) {}
+
+    fn set_env(&self, _name: &str, _value: &str) {}
+
+    fn rename_workspace(&self, _id: i32, name: &str) {
+        let mut stdout = io::stdout().lock();
+        _ = writeln!(stdout, "{}", json!({ "text": name }));
+        _ = stdout.flush();
+    }
+}
+
+/// Connects to the compositor, and calls `on_change` with all the windows
+/// each time one of them changes. Returns when the connection is lost.
+pub fn listen(mut on_change: impl FnMut(Vec<Toplevel>)) -> Result<(), Box<dyn Error>> {
+    let connection = Connection::connect_to_env()?;
+    let (globals, mut queue) = registry_queue_init::<ToplevelState>(&connection)?;
+    let _manager: ZwlrForeignToplevelManagerV1 = globals
+        .bind(&queue.handle(), 1..=MANAGER_VERSION, ())
+        .map_err(|_| format!("The compositor doesn't support {MANAGER_INTERFACE}"))?;
+
+    let mut state = ToplevelState::default();
+    loop {
+        queue.blocking_dispatch(&mut state)?;
+        if state.finished {
+            return Err("The toplevel manager stopped".into());
+        }
+        if std::mem::take(&mut state.changed) {
+            on_change(state.toplevels());
+        }
+    }
+}
+
+/// The toplevels being configured, and the ones done.
+#[derive(Debug, Default)]
+struct ToplevelState {
+    pending: BTreeMap<u32, Toplevel>,
+    done: BTreeMap<u32, Toplevel>,
+    changed: bool,
+    finished: bool,
+}
+
+impl ToplevelState {
+    fn toplevels(&self) -> Vec<Toplevel> {
+        self.done.values().cloned().collect()
+    }
+
+    fn on_toplevel(&mut self, id: u32) {
+        self.pending.insert(
+            id,
+            Toplevel {
+                id,
+                ..Toplevel::default()
+            },
+        );
+    }
+
+    /// Applies the event to the pending toplevel, returns true once it is
+    /// closed, so its handle can be destroyed.
+    fn on_handle_event(&mut self, id: u32, event: handle::Event) -> bool {
+        let Some(toplevel) = self.pending.get_mut(&id) else {
+            return false;
+        };
+
+        match event {
+            handle::Event::Title { title } => toplevel.title = title,
+            handle::Event::AppId { app_id } => toplevel.app_id = app_id,
+            handle::Event::State { state } => {
+                let states: Vec<u32> = state
+                    .chunks_exact(4)
+                    .map(|b| u32::from_ne_bytes([b[0], b[1], b[2], b[3]]))
+                    .collect();
+                toplevel.maximized = states.contains(&(handle::State::Maximized as u32));
+                toplevel.activated = states.contains(&(handle::State::Activated as u32));
+                toplevel.fullscreen = states.contains(&(handle::State::Fullscreen as u32));
+            }
+            handle::Event::Done => {
+                self.changed |= self.done.get(&id) != Some(toplevel);
+                self.done.insert(id, toplevel.clone());
+            }
+            handle::Event::Closed => {
+                self.pending.remove(&id);
+                self.changed |= self.done.remove(&id).is_some();
+                return true;
+            }
+            _ => {}
+        }
+        false
+    }
+}
+
+impl Dispatch<WlRegistry, GlobalListContents> for ToplevelState {
+    fn event(
+        _state: &mut Self,
+        _registry: &WlRegistry,
+        _event: wl_registry::Event,
+        _data: &GlobalListContents,
+        _connection: &Connection,
+        _queue: &QueueHandle<Self>,
+    ) {
+    }
+}
+
+impl Dispatch<ZwlrForeignToplevelManagerV1, ()> for ToplevelState {
+    fn event(
+        state: &mut Self,
+        _manager: &ZwlrForeignToplevelManagerV1,
+        event: manager::Event,
+        _data: &(),
+        _connection: &Connection,
+        _queue: &QueueHandle<Self>,
+    ) {
+        match event {
+            manager::Event::Toplevel { toplevel } => state.on_toplevel(toplevel.id().protocol_id()),
+            manager::Event::Finished => state.finished = true,
+            _ => {}
+        }
+    }
+
+    event_created_child!(ToplevelState, ZwlrForeignToplevelManagerV1, [
+        manager::EVT_TOPLEVEL_OPCODE => (ZwlrForeignToplevelHandleV1, ()),
+    ]);
+}
+
+impl Dispatch<ZwlrForeignToplevelHandleV1, ()> for ToplevelState {
+    fn event(
+        state: &mut Self,
+        toplevel: &ZwlrForeignToplevelHandleV1,
+        event: handle::Event,
+        _data: &(),
+        _connection: &Connection,
+        _queue: &QueueHandle<Self>,
+    ) {
+        if state.on_handle_event(toplevel.id().protocol_id(), event) {
+            toplevel.destroy();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn states(states: &[handle::State]) -> handle::Event {
+        handle::Event::State {
+            state: states
+                .iter()
+                .flat_map(|&state| (state as u32).to_ne_bytes())
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn test_toplevel_state() {
+        let mut state = ToplevelState::default();
+        let id = 12;
+
+        state.on_toplevel(id);
+        let app_id = "kitty".to_string();
+        assert!(!state.on_handle_event(id, handle::Event::AppId { app_id }));
+        let title = "zsh".to_string();
+        state.on_handle_event(id, handle::Event::Title { title });
+        state.on_handle_event(id, states(&[handle::State::Activated]));
+        assert!(state.toplevels().is_empty());
+        assert!(!state.changed);
+
+        state.on_handle_event(id, handle::Event::Done);
+        assert!(std::mem::take(&mut state.changed));
+        assert_eq!(
+            state.toplevels(),
+            [Toplevel {
+                id,
+                app_id: "kitty".to_string(),
+                title: "zsh".to_string(),
+                activated: true,
+                ..Toplevel::default()
+            }]
+        );
+        state.on_handle_event(id, handle::Event::Done);
+        assert!(!state.changed);
+
+        state.on_handle_event(id, states(&[handle::State::Maximized]));
+        state.on_handle_event(id, handle::Event::Done);
+        assert!(std::mem::take(&mut state.changed));
+        assert!(state.toplevels()[0].maximized);
+        assert!(!state.toplevels()[0].activated);
+
+        assert!(state.on_handle_event(id, handle::Event::Closed));
+        assert!(state.changed);
+        assert!(state.toplevels().is_empty());
+        assert!(!state.on_handle_event(id, handle::Event::Done));
+    }
+
+    #[test]
+    fn test_toplevel_backend() {
+        let backend = ToplevelBackend::default();
+        assert_eq!(backend.active_client(), Address::new("0").to_string());
+
+        backend.set_toplevels(vec![
+            Toplevel {
+                id: 1,
+                app_id: "kitty".to_string(),
+                fullscreen: true,
+                ..Toplevel::default()
+            },
+            Toplevel {
+                id: 2,
+                app_id: "firefox".to_string(),
+                activated: true,
+                maximized: true,
+                ..Toplevel::default()
+            },
+        ]);
+        let clients = backend.clients().unwrap();
+        assert_eq!(clients[0].class, "kitty");
+        assert_eq!(clients[0].fullscreen, FullscreenMode::Fullscreen);
+        assert_eq!(clients[1].fullscreen, FullscreenMode::Maximized);
+        assert_eq!(clients[1].workspace.id, TOPLEVEL_WORKSPACE_ID);
+        assert_eq!(backend.active_client(), clients[1].address.to_string());
+        assert_eq!(backend.active_workspace().unwrap().windows, 2);
+    }
+}
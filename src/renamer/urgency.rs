@@ -0,0 +1,164 @@
+use std::collections::{HashMap, HashSet};
+use std::time::{Duration, Instant};
+
+/// Windows which requested attention, by address, with the time of their
+/// request. A window stops being urgent once focused, closed, or after the
+/// timeout.
+#[derive(Debug, Default)]
+pub struct UrgencyTracker {
+    since: HashMap<String, Instant>,
+}
+
+impl UrgencyTracker {
+    pub fn on_urgent(&mut self, address: &str, now: Instant) {
+        self.since.entry(address.to_string()).or_insert(now);
+    }
+
+    /// Forgets the focused window, and the closed ones.
+    pub fn on_clients(&mut self, active_address: &str, addresses: &HashSet<String>) {
+        self.since
+            .retain(|address, _| address != active_address && addresses.contains(address));
+    }
+
+    /// Forgets the windows urgent for longer than the timeout, and returns
+    /// true if any was forgotten.
+    pub fn expire(&mut self, now: Instant, timeout: Option<Duration>) -> bool {
+        let Some(timeout) = timeout else {
+            return false;
+        };
+        let count = self.since.len();
+        self.since
+            .retain(|_, since| now.duration_since(*since) < timeout);
+        self.since.len() != count
+    }
+
+    pub fn urgent_for(&self, address: &str, now: Instant) -> Option<Duration> {
+        self.since
+            .get(address)
+            .map(|since| now.duration_since(*since))
+    }
+
+    /// Returns the next time a name may change by itself: an urgent window
+    /// times out, or the age shown by `{urgent_for}` ticks, if shown.
+    pub fn next_deadline(
+        &self,
+        now: Instant,
+        timeout: Option<Duration>,
+        with_age: bool,
+    ) -> Option<Instant> {
+        self.since
+            .values()
+            .flat_map(|since| {
+                let age = now.duration_since(*since);
+                let tick = with_age.then(|| now + next_tick(age));
+                let expiry = timeout.map(|timeout| *since + timeout);
+                [tick, expiry]
+            })
+            .flatten()
+            .min()
+    }
+}
+
+/// Formats the age coarsely, so names don't change every second for long:
+/// `42s`, then `3m`, then `2h`.
+pub fn format_age(age: Duration) -> String {
+    let secs = age.as_secs();
+    match secs {
+        0..=59 => format!("{secs}s"),
+        60..=3599 => format!("{}m", secs / 60),
+        _ => format!("{}h", secs / 3600),
+    }
+}
+
+/// Variables of an urgent client or workspace, empty when not urgent.
+pub fn urgency_vars(urgent_for: Option<Duration>, marker: &str) -> [(String, String); 2] {
+    [
+        (
+            "urgent".to_string(),
+            urgent_for.map(|_| marker.to_string()).unwrap_or_default(),
+        ),
+        (
+            "urgent_for".to_string(),
+            urgent_for.map(format_age).unwrap_or_default(),
+        ),
+    ]
+}
+
+/// Time until `format_age` renders another value.
+fn next_tick(age: Duration) -> Duration {
+    let unit = match age.as_secs() {
+        0..=59 => 1,
+        60..=3599 => 60,
+        _ => 3600,
+    };
+    let unit = Duration::from_secs(unit);
+    let elapsed = Duration::from_nanos((age.as_nanos() % unit.as_nanos()) as u64);
+    unit - elapsed
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_age() {
+        assert_eq!(format_age(Duration::from_secs(0)), "0s");
+        assert_eq!(format_age(Duration::from_secs(59)), "59s");
+        assert_eq!(format_age(Duration::from_secs(150)), "2m");
+        assert_eq!(format_age(Duration::from_secs(7300)), "2h");
+    }
+
+    #[test]
+    fn test_urgency_tracker() {
+        let start = Instant::now();
+        let mut tracker = UrgencyTracker::default();
+        tracker.on_urgent("0x1", start);
+        tracker.on_urgent("0x2", start + Duration::from_secs(10));
+        tracker.on_urgent("0x1", start + Duration::from_secs(20));
+
+        let now = start + Duration::from_secs(30);
+        assert_eq!(
+            tracker.urgent_for("0x1", now),
+            Some(Duration::from_secs(30))
+        );
+        assert_eq!(tracker.urgent_for("0x3", now), None);
+
+        let timeout = Some(Duration::from_secs(25));
+        assert!(!tracker.expire(now, None));
+        assert!(tracker.expire(now, timeout));
+        assert_eq!(tracker.urgent_for("0x1", now), None);
+        assert!(!tracker.expire(now, timeout));
+
+        tracker.on_urgent("0x3", now);
+        tracker.on_clients(
+            "0x3",
+            &HashSet::from(["0x2".to_string(), "0x3".to_string()]),
+        );
+        assert_eq!(tracker.urgent_for("0x3", now), None);
+        tracker.on_clients("", &HashSet::new());
+        assert!(tracker.since.is_empty());
+    }
+
+    #[test]
+    fn test_next_deadline() {
+        let start = Instant::now();
+        let mut tracker = UrgencyTracker::default();
+        assert_eq!(tracker.next_deadline(start, None, true), None);
+
+        tracker.on_urgent("0x1", start);
+        let now = start + Duration::from_millis(90_500);
+        assert_eq!(tracker.next_deadline(now, None, false), None);
+        assert_eq!(
+            tracker.next_deadline(now, None, true),
+            Some(start + Duration::from_secs(120))
+        );
+        assert_eq!(
+            tracker.next_deadline(now, Some(Duration::from_secs(100)), true),
+            Some(start + Duration::from_secs(100))
+        );
+        assert_eq!(
+            tracker.next_deadline(start + Duration::from_millis(1_500), None, true),
+            Some(start + Duration::from_secs(2))
+        );
+    }
+}
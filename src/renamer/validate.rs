@@ -0,0 +1,154 @@
+use crate::config::Config;
+use crate::params::Args;
+use crate::renamer::compat::HyprlandCompat;
+use crate::renamer::icon::{floating_icon, get_captures, xwayland_icon};
+use crate::renamer::Renamer;
+use hyprland::data::Client;
+use regex::Regex;
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fmt::Write;
+
+/// Compiles the pattern and reports which open windows it matches, on
+/// their class and on their title, with the captures.
+pub fn validate_regex(pattern: &str) -> Result<String, Box<dyn Error>> {
+    let re = Regex::new(pattern)?;
+    let clients = HyprlandCompat::detect().get_clients()?;
+    Ok(regex_report(&re, &clients))
+}
+
+/// An open window, with the rule giving its icon when not focused.
+#[cfg_attr(not(feature = "tui"), allow(dead_code))]
+#[derive(Debug, PartialEq, Eq)]
+pub struct MatchedClient {
+    pub class: String,
+    pub title: String,
+    pub rule: String,
+    /// Where the rule is defined, empty if unknown
+    pub source: String,
+    pub icon: String,
+}
+
+#[cfg_attr(not(feature = "tui"), allow(dead_code))]
+pub fn open_clients() -> Result<Vec<Client>, Box<dyn Error>> {
+    HyprlandCompat::detect().get_clients()
+}
+
+/// Returns the rule the config matches for each window.
+#[cfg_attr(not(feature = "tui"), allow(dead_code))]
+pub fn matched_clients(config: &Config, clients: &[Client]) -> Vec<MatchedClient> {
+    let renamer = Renamer::new(config.clone(), Args::default());
+    clients
+        .iter()
+        .map(|client| {
+            let status = floating_icon(
+                xwayland_icon(
+                    renamer.parse_icon(
+                        client.initial_class.clone(),
+                        client.class.clone(),
+                        client.initial_title.clone(),
+                        client.title.clone(),
+                        false,
+                        &config.config,
+                    ),
+                    &client.class,
+                    client.xwayland,
+                    false,
+                    &config.config,
+                ),
+                &client.class,
+                client.floating,
+                false,
+                &config.config,
+            );
+            MatchedClient {
+                class: client.class.clone(),
+                title: client.title.clone(),
+                source: config
+                    .config
+                    .rule_source(&status.rule())
+                    .unwrap_or_default(),
+                rule: status.rule(),
+                icon: status.icon(),
+            }
+        })
+        .collect()
+}
+
+fn regex_report<'a>(re: &Regex, clients: &'a [Client]) -> String {
+    let mut report = format!("pattern '{re}' is valid\n");
+    for mode in ["class", "title"] {
+        let value = |client: &'a Client| match mode {
+            "class" => client.class.as_str(),
+            _ => client.title.as_str(),
+        };
+        let matches: Vec<&Client> = clients.iter().filter(|c| re.is_match(value(c))).collect();
+        _ = writeln!(
+            report,
+            "\n{mode} matches ({} of {}):",
+            matches.len(),
+            clients.len()
+        );
+        for client in matches {
+            _ = write!(report, "  {} {:?}", client.address, value(client));
+            let captures: BTreeMap<_, _> = get_captures(Some(value(client)), re)
+                .unwrap_or_default()
+                .into_iter()
+                .filter(|(name, _)| name != "match0")
+                .collect();
+            for (name, capture) in captures {
+                _ = write!(report, " {name}={capture:?}");
+            }
+            _ = writeln!(report);
+        }
+    }
+    report.trim_end().to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn client(address: &str, class: &str, title: &str) -> Client {
+        serde_json::from_value(serde_json::json!({
+            "address": address, "at": [0, 0], "size": [0, 0],
+            "workspace": {"id": 1, "name": "1"},
+            "floating": false, "fullscreen": 0, "fullscreenClient": 0, "monitor": 0,
+            "initialClass": class, "class": class, "initialTitle": title, "title": title,
+            "pid": 1, "xwayland": false, "pinned": false, "grouped": [], "mapped": true,
+            "swallowing": null, "focusHistoryID": 0
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_matched_clients() {
+        let config = Config::from_toml("[class]\n\"(?i)kitty\" = \"term\"", false).unwrap();
+        let clients = [client("0x1", "kitty", "vim"), client("0x2", "mpv", "video")];
+        let matched = matched_clients(&config, &clients);
+        assert_eq!(
+            (matched[0].rule.as_str(), matched[0].icon.as_str()),
+            ("(?i)kitty", "term")
+        );
+        assert_eq!(matched[1].rule, "DEFAULT");
+    }
+
+    #[test]
+    fn test_regex_report() {
+        let clients = [
+            client("0x1", "kitty", "vim main.rs"),
+            client("0x2", "firefox", "GitHub"),
+        ];
+        let re = Regex::new(r"(?i)(vim|kitty) ?(?P<file>\S*)").unwrap();
+        assert_eq!(
+            regex_report(&re, &clients),
+            "pattern '(?i)(vim|kitty) ?(?P<file>\\S*)' is valid\n\
+             \n\
+             class matches (1 of 2):\n  \
+             0x1 \"kitty\" file=\"\" match1=\"kitty\" match2=\"\"\n\
+             \n\
+             title matches (1 of 2):\n  \
+             0x1 \"vim main.rs\" file=\"main.rs\" match1=\"vim\" match2=\"main.rs\""
+        );
+    }
+}
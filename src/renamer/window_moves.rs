@@ -0,0 +1,81 @@
+use hyprland::data::{Client, WorkspaceBasic};
+use std::collections::HashMap;
+
+/// The workspace of every window, and where windows were moved by the
+/// `movewindow` events. Hyprland may still list a moved window on the
+/// workspace it left, e.g. into a scratchpad, so the event wins until the
+/// listed workspace catches up.
+#[derive(Debug, Default)]
+pub struct WindowMoves {
+    workspaces: HashMap<String, i32>,
+    pending: HashMap<String, WorkspaceBasic>,
+}
+
+impl WindowMoves {
+    /// Records the move, and returns the workspace the window left, if known.
+    pub fn on_move(&mut self, address: &str, workspace: WorkspaceBasic) -> Option<i32> {
+        let id = workspace.id;
+        let origin = self.workspaces.insert(address.to_string(), id);
+        self.pending.insert(address.to_string(), workspace);
+        origin.filter(|&origin| origin != id)
+    }
+
+    /// Puts the clients on the workspace they were moved to, and forgets the
+    /// moves Hyprland caught up with, and the closed windows.
+    pub fn apply(&mut self, clients: &mut [Client]) {
+        let mut pending = std::mem::take(&mut self.pending);
+        self.workspaces.clear();
+        for client in clients.iter_mut() {
+            let address = client.address.to_string();
+            if let Some(workspace) = pending.remove(&address) {
+                if client.workspace.id != workspace.id {
+                    client.workspace = workspace.clone();
+                    self.pending.insert(address.clone(), workspace);
+                }
+            }
+            self.workspaces.insert(address, client.workspace.id);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn client(address: &str, workspace_id: i32) -> Client {
+        serde_json::from_value(serde_json::json!({
+            "address": address, "at": [0, 0], "size": [0, 0],
+            "workspace": {"id": workspace_id, "name": workspace_id.to_string()},
+            "floating": false, "fullscreen": 0, "fullscreenClient": 0, "monitor": 0,
+            "initialClass": "kitty", "class": "kitty", "initialTitle": "", "title": "",
+            "pid": 1, "xwayland": false, "pinned": false, "grouped": [], "mapped": true,
+            "swallowing": null, "focusHistoryID": 0
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_window_moves() {
+        let scratch = WorkspaceBasic {
+            id: -98,
+            name: "special:scratch".to_string(),
+        };
+        let mut moves = WindowMoves::default();
+        moves.apply(&mut [client("0x1", 1)]);
+        assert_eq!(moves.on_move("0x1", scratch.clone()), Some(1));
+        assert_eq!(moves.on_move("0x1", scratch.clone()), None);
+
+        // Hyprland still lists the window where it was
+        let mut clients = [client("0x1", 1)];
+        moves.apply(&mut clients);
+        assert_eq!(clients[0].workspace, scratch);
+
+        // Then catches up
+        let mut clients = [client("0x1", -98)];
+        moves.apply(&mut clients);
+        assert!(moves.pending.is_empty());
+        let mut clients = [client("0x1", 1)];
+        moves.apply(&mut clients);
+        assert_eq!(clients[0].workspace.id, 1);
+    }
+}
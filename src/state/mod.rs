@@ -0,0 +1,87 @@
+use std::error::Error;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process;
+
+const BIN_NAME: &str = env!("CARGO_BIN_NAME");
+
+/// Directory of the files we keep between runs (pid file, audit log...),
+/// `$XDG_STATE_HOME/hyprland-autoname-workspaces` unless overridden.
+///
+/// The directory is only created when a file is placed in it.
+#[derive(Default, Debug, Clone, PartialEq, Eq)]
+pub struct StateDir {
+    path: Option<PathBuf>,
+}
+
+impl StateDir {
+    pub fn new(path: Option<PathBuf>) -> Self {
+        StateDir { path }
+    }
+
+    pub fn path(&self) -> Result<PathBuf, Box<dyn Error>> {
+        match &self.path {
+            Some(path) => Ok(path.clone()),
+            None => Ok(xdg::BaseDirectories::with_prefix(BIN_NAME)?.get_state_home()),
+        }
+    }
+
+    /// Returns the path of the file in the state directory, creating the
+    /// directory if needed. Absolute paths are returned as is.
+    pub fn place_file(&self, file: impl AsRef<Path>) -> Result<PathBuf, Box<dyn Error>> {
+        let file = file.as_ref();
+        if file.is_absolute() {
+            return Ok(file.to_path_buf());
+        }
+        let path = self.path()?.join(file);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        Ok(path)
+    }
+
+    /// Writes our pid, so scripts can find the running daemon.
+    pub fn write_pid_file(&self) -> Result<PathBuf, Box<dyn Error>> {
+        let path = self.place_file("pid")?;
+        fs::write(&path, format!("{}\n", process::id()))?;
+        Ok(path)
+    }
+
+    pub fn remove_pid_file(&self) -> Result<(), Box<dyn Error>> {
+        Ok(fs::remove_file(self.path()?.join("pid"))?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_state_dir() {
+        let dir = PathBuf::from("/tmp/hyprland-autoname-workspaces-state-test");
+        _ = fs::remove_dir_all(&dir);
+        let state = StateDir::new(Some(dir.clone()));
+
+        // Created lazily
+        assert_eq!(state.path().unwrap(), dir);
+        assert!(!dir.exists());
+
+        assert_eq!(
+            state.place_file("audit.log").unwrap(),
+            dir.join("audit.log")
+        );
+        assert!(dir.exists());
+        assert_eq!(
+            state.place_file("/tmp/audit.log").unwrap(),
+            PathBuf::from("/tmp/audit.log")
+        );
+
+        let pid_file = state.write_pid_file().unwrap();
+        assert_eq!(
+            fs::read_to_string(&pid_file).unwrap(),
+            format!("{}\n", process::id())
+        );
+        state.remove_pid_file().unwrap();
+        assert!(!pid_file.exists());
+    }
+}
@@ -0,0 +1,198 @@
+use crate::config::{add_class_rule, Config};
+use crate::renamer::{matched_clients, open_clients, MatchedClient};
+use crossterm::event::{self, Event, KeyCode, KeyEventKind};
+use crossterm::terminal::{
+    disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen,
+};
+use crossterm::ExecutableCommand;
+use hyprland::data::Client;
+use ratatui::prelude::*;
+use ratatui::widgets::{Block, Borders, Paragraph, Row, Table, TableState};
+use std::error::Error;
+use std::io::{self, Stdout};
+use std::path::Path;
+
+const HELP: &str = "↑/↓ select, enter give an icon to the class, q quit";
+
+/// What a key press asks the event loop to do.
+#[derive(Debug, PartialEq, Eq)]
+enum Action {
+    Continue,
+    Quit,
+    AddRule { pattern: String, icon: String },
+}
+
+/// The windows, the selected one, and the icon being typed for its class.
+#[derive(Default)]
+struct App {
+    clients: Vec<MatchedClient>,
+    selected: usize,
+    input: Option<String>,
+    message: Option<String>,
+}
+
+impl App {
+    fn on_key(&mut self, key: KeyCode) -> Action {
+        match (&mut self.input, key) {
+            (Some(input), KeyCode::Char(c)) => input.push(c),
+            (Some(input), KeyCode::Backspace) => _ = input.pop(),
+            (Some(_), KeyCode::Esc) => self.input = None,
+            (Some(_), KeyCode::Enter) => {
+                let icon = self.input.take().unwrap_or_default();
+                if let (false, Some(client)) = (icon.is_empty(), self.clients.get(self.selected)) {
+                    let pattern = format!("^{}$", regex::escape(&client.class));
+                    return Action::AddRule { pattern, icon };
+                }
+            }
+            (None, KeyCode::Char('q') | KeyCode::Esc) => return Action::Quit,
+            (None, KeyCode::Up | KeyCode::Char('k')) => {
+                self.selected = self.selected.saturating_sub(1)
+            }
+            (None, KeyCode::Down | KeyCode::Char('j')) => {
+                self.selected = (self.selected + 1).min(self.clients.len().saturating_sub(1))
+            }
+            (None, KeyCode::Enter) if !self.clients.is_empty() => {
+                self.message = None;
+                self.input = Some(String::new());
+            }
+            _ => {}
+        }
+        Action::Continue
+    }
+
+    fn footer(&self) -> String {
+        match (&self.input, self.clients.get(self.selected)) {
+            (Some(input), Some(client)) => format!("icon for {}: {input}▏", client.class),
+            _ => self.message.clone().unwrap_or_else(|| HELP.to_string()),
+        }
+    }
+}
+
+/// Lists the open windows with the rule matching each, and writes the
+/// icons typed for their class to the config, which the running daemon
+/// reloads.
+pub fn run(cfg_path: &Path) -> Result<(), Box<dyn Error>> {
+    let clients = open_clients()?;
+    let mut app = App {
+        clients: load_rules(cfg_path, &clients)?,
+        ..App::default()
+    };
+
+    enable_raw_mode()?;
+    io::stdout().execute(EnterAlternateScreen)?;
+    let result = Terminal::new(CrosstermBackend::new(io::stdout()))
+        .map_err(Into::into)
+        .and_then(|mut terminal| event_loop(&mut terminal, &mut app, cfg_path, &clients));
+    disable_raw_mode()?;
+    io::stdout().execute(LeaveAlternateScreen)?;
+    result
+}
+
+fn load_rules(cfg_path: &Path, clients: &[Client]) -> Result<Vec<MatchedClient>, Box<dyn Error>> {
+    let config = Config::new(cfg_path.to_path_buf(), false, false)?;
+    Ok(matched_clients(&config, clients))
+}
+
+fn event_loop(
+    terminal: &mut Terminal<CrosstermBackend<Stdout>>,
+    app: &mut App,
+    cfg_path: &Path,
+    clients: &[Client],
+) -> Result<(), Box<dyn Error>> {
+    loop {
+        terminal.draw(|frame| draw(frame, app))?;
+        let Event::Key(key) = event::read()? else {
+            continue;
+        };
+        if key.kind != KeyEventKind::Press {
+            continue;
+        }
+        match app.on_key(key.code) {
+            Action::Continue => {}
+            Action::Quit => return Ok(()),
+            Action::AddRule { pattern, icon } => {
+                let written = add_class_rule(cfg_path, &pattern, &icon)
+                    .and_then(|_| load_rules(cfg_path, clients));
+                app.message = Some(match written {
+                    Ok(rules) => {
+                        app.clients = rules;
+                        format!("'{pattern}' = '{icon}' written to {}", cfg_path.display())
+                    }
+                    Err(e) => format!("Unable to write the rule: {e}"),
+                });
+            }
+        }
+    }
+}
+
+fn draw(frame: &mut Frame, app: &App) {
+    let areas = Layout::vertical([Constraint::Min(1), Constraint::Length(1)]).split(frame.size());
+    let rows = app.clients.iter().map(|client| {
+        Row::new([
+            client.class.as_str(),
+            client.title.as_str(),
+            client.rule.as_str(),
+            client.source.as_str(),
+            client.icon.as_str(),
+        ])
+    });
+    let widths = [
+        Constraint::Percentage(15),
+        Constraint::Percentage(30),
+        Constraint::Percentage(15),
+        Constraint::Percentage(25),
+        Constraint::Percentage(15),
+    ];
+    let table = Table::new(rows, widths)
+        .header(Row::new(["class", "title", "rule", "defined in", "icon"]).bold())
+        .block(Block::default().borders(Borders::ALL).title("windows"))
+        .highlight_style(Style::new().reversed());
+    let mut state = TableState::default().with_selected(Some(app.selected));
+    frame.render_stateful_widget(table, areas[0], &mut state);
+    frame.render_widget(Paragraph::new(app.footer()), areas[1]);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn matched(class: &str) -> MatchedClient {
+        MatchedClient {
+            class: class.to_string(),
+            title: String::new(),
+            rule: "DEFAULT".to_string(),
+            source: String::new(),
+            icon: "no icon".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_app_add_rule() {
+        let mut app = App {
+            clients: vec![matched("kitty"), matched("org.mpv")],
+            ..App::default()
+        };
+        assert_eq!(app.on_key(KeyCode::Down), Action::Continue);
+        assert_eq!(app.on_key(KeyCode::Down), Action::Continue);
+        assert_eq!(app.selected, 1);
+
+        app.on_key(KeyCode::Enter);
+        for c in "vidq".chars() {
+            app.on_key(KeyCode::Char(c));
+        }
+        app.on_key(KeyCode::Backspace);
+        assert_eq!(app.footer(), "icon for org.mpv: vid▏");
+        assert_eq!(
+            app.on_key(KeyCode::Enter),
+            Action::AddRule {
+                pattern: r"^org\.mpv$".to_string(),
+                icon: "vid".to_string()
+            }
+        );
+        assert_eq!(app.input, None);
+
+        app.on_key(KeyCode::Enter);
+        assert_eq!(app.on_key(KeyCode::Esc), Action::Continue);
+        assert_eq!(app.on_key(KeyCode::Char('q')), Action::Quit);
+    }
+}